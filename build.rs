@@ -19,6 +19,10 @@ use std::process::Command;
 use std::process::Stdio;
 use which::which;
 
+#[cfg(feature = "inspector_protocol")]
+#[path = "tools/inspector_protocol.rs"]
+mod inspector_protocol;
+
 fn main() {
   println!("cargo:rerun-if-changed=.gn");
   println!("cargo:rerun-if-changed=BUILD.gn");
@@ -55,6 +59,10 @@ fn main() {
     println!("cargo:rerun-if-env-changed={}", env);
   }
 
+  // The protocol types are needed even when V8 isn't built, e.g. for docs.
+  #[cfg(feature = "inspector_protocol")]
+  inspector_protocol::generate(Path::new("v8/include/js_protocol.json"));
+
   // Detect if trybuild tests are being compiled.
   let is_trybuild = env::var_os("DENO_TRYBUILD").is_some();
 
@@ -0,0 +1,574 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Procedural macros for the `v8` crate. Use them through the `macros`
+//! feature of `v8` rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::format_ident;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Attribute;
+use syn::Error;
+use syn::Expr;
+use syn::ExprLit;
+use syn::FnArg;
+use syn::Ident;
+use syn::ImplItem;
+use syn::ImplItemFn;
+use syn::ItemImpl;
+use syn::Lit;
+use syn::LitStr;
+use syn::Result;
+use syn::Type;
+
+/// Exposes a Rust type to JavaScript as a class, by implementing
+/// `v8::Class` for it from an inherent `impl` block.
+///
+/// Every function of the block becomes part of the class, according to its
+/// attributes:
+///
+/// - `#[constructor]`: the constructor, called by `new`. It returns `Self` or
+///   `Result<Self, v8::JsError>`. Without a constructor, the class can't be
+///   instantiated from JavaScript.
+/// - `#[getter]` and `#[setter]`: the accessors of a property on the
+///   prototype. A setter named `set_foo` sets the property `foo`.
+/// - `#[skip]`: not exposed.
+/// - Other functions taking `&self` become methods on the prototype, and
+///   functions without a receiver become static methods of the constructor.
+///
+/// JavaScript names are the function names in camelCase, unless given with
+/// `#[js_name = "..."]`. The class is named after the type, unless given with
+/// `#[v8::class(name = "...")]`.
+///
+/// Arguments are converted with `v8::FromV8`, and return values with
+/// `v8::ToV8`; functions may also return `Result<T, v8::JsError>` to throw.
+/// A `&mut v8::HandleScope` parameter receives the scope of the call instead
+/// of an argument. Methods take `&self` because JavaScript may re-enter the
+/// object; use interior mutability to modify it.
+///
+/// ```ignore
+/// struct Counter {
+///   count: Cell<i32>,
+/// }
+///
+/// #[v8::class]
+/// impl Counter {
+///   #[constructor]
+///   fn new(start: Option<i32>) -> Self {
+///     Self { count: Cell::new(start.unwrap_or(0)) }
+///   }
+///
+///   fn increment(&self) -> i32 {
+///     self.count.set(self.count.get() + 1);
+///     self.count.get()
+///   }
+///
+///   #[getter]
+///   fn count(&self) -> i32 {
+///     self.count.get()
+///   }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn class(attr: TokenStream, item: TokenStream) -> TokenStream {
+  let mut name = None;
+  let parser = syn::meta::parser(|meta| {
+    if meta.path.is_ident("name") {
+      name = Some(meta.value()?.parse::<LitStr>()?.value());
+      Ok(())
+    } else {
+      Err(meta.error("unsupported class attribute"))
+    }
+  });
+  parse_macro_input!(attr with parser);
+  let item = parse_macro_input!(item as ItemImpl);
+  match expand_class(name, item) {
+    Ok(tokens) => tokens.into(),
+    Err(error) => error.to_compile_error().into(),
+  }
+}
+
+#[derive(PartialEq)]
+enum Kind {
+  Constructor,
+  Method,
+  Static,
+  Getter,
+  Setter,
+}
+
+/// The JavaScript side of a function in the impl block.
+struct Member {
+  kind: Kind,
+  js_name: String,
+  rust_name: Ident,
+  callback: Ident,
+  params: Vec<Param>,
+}
+
+enum Param {
+  Scope,
+  Argument,
+}
+
+impl Member {
+  fn length(&self) -> i32 {
+    self
+      .params
+      .iter()
+      .filter(|param| matches!(param, Param::Argument))
+      .count() as i32
+  }
+}
+
+fn expand_class(
+  name: Option<String>,
+  mut item: ItemImpl,
+) -> Result<TokenStream2> {
+  if let Some((_, path, _)) = &item.trait_ {
+    return Err(Error::new_spanned(
+      path,
+      "#[v8::class] must be used on an inherent impl block",
+    ));
+  }
+  if !item.generics.params.is_empty() {
+    return Err(Error::new_spanned(
+      &item.generics,
+      "#[v8::class] does not support generic types",
+    ));
+  }
+  let name = match name {
+    Some(name) => name,
+    None => type_name(&item.self_ty)?,
+  };
+
+  let mut members = Vec::new();
+  for impl_item in &mut item.items {
+    if let ImplItem::Fn(function) = impl_item {
+      if let Some(member) = parse_member(function)? {
+        members.push(member);
+      }
+    }
+  }
+  let constructors = members
+    .iter()
+    .filter(|member| member.kind == Kind::Constructor)
+    .collect::<Vec<_>>();
+  if let Some(second) = constructors.get(1) {
+    return Err(Error::new_spanned(
+      &second.rust_name,
+      "a class can only have one constructor",
+    ));
+  }
+
+  let callback_fns = members.iter().map(callback_fn);
+  let constructor = match constructors.first() {
+    Some(constructor) => {
+      let callback = map_callback(&constructor.callback);
+      let length = constructor.length();
+      quote! {
+        ::v8::FunctionTemplate::builder_raw(#callback)
+          .length(#length)
+          .build(scope)
+      }
+    }
+    None => {
+      let callback = map_callback(&illegal_constructor());
+      quote! { ::v8::FunctionTemplate::new_raw(scope, #callback) }
+    }
+  };
+  let illegal_constructor_fn = if constructors.is_empty() {
+    let ident = illegal_constructor();
+    quote! {
+      #[doc(hidden)]
+      fn #ident<'s>(
+        _scope: &mut ::v8::HandleScope<'s>,
+        _args: &::v8::FunctionCallbackArguments<'s>,
+      ) -> ::std::result::Result<::v8::Local<'s, ::v8::Value>, ::v8::JsError> {
+        ::std::result::Result::Err(::v8::JsError::type_error("Illegal constructor"))
+      }
+    }
+  } else {
+    quote! {}
+  };
+
+  let mut properties = Vec::new();
+  let mut accessors: Vec<(&str, Option<&Member>, Option<&Member>)> = Vec::new();
+  for member in &members {
+    let slot = match member.kind {
+      Kind::Constructor => continue,
+      Kind::Method | Kind::Static => {
+        properties.push(install_function(member));
+        continue;
+      }
+      Kind::Getter | Kind::Setter => {
+        let index = accessors
+          .iter()
+          .position(|(name, _, _)| *name == member.js_name)
+          .unwrap_or_else(|| {
+            accessors.push((&member.js_name, None, None));
+            accessors.len() - 1
+          });
+        let (_, getter, setter) = &mut accessors[index];
+        if member.kind == Kind::Getter {
+          getter
+        } else {
+          setter
+        }
+      }
+    };
+    if slot.replace(member).is_some() {
+      return Err(Error::new_spanned(
+        &member.rust_name,
+        format!("duplicate accessor for property `{}`", member.js_name),
+      ));
+    }
+  }
+  for (js_name, getter, setter) in accessors {
+    let getter = accessor_template(getter);
+    let setter = accessor_template(setter);
+    properties.push(quote! {
+      let name = ::v8::String::new(scope, #js_name).unwrap();
+      let getter = #getter;
+      let setter = #setter;
+      prototype.set_accessor_property(
+        name.into(),
+        getter,
+        setter,
+        ::v8::PropertyAttribute::DONT_ENUM,
+      );
+    });
+  }
+
+  let references = members
+    .iter()
+    .map(|member| member.callback.clone())
+    .chain(constructors.is_empty().then(illegal_constructor))
+    .map(|callback| {
+      let callback = map_callback(&callback);
+      quote! { ::v8::ExternalReference { function: #callback } }
+    });
+
+  let self_ty = &item.self_ty;
+  Ok(quote! {
+    #item
+
+    impl #self_ty {
+      #(#callback_fns)*
+      #illegal_constructor_fn
+    }
+
+    impl ::v8::Class for #self_ty {
+      const NAME: &'static str = #name;
+
+      #[allow(unused_variables)]
+      fn build_template<'s>(
+        scope: &mut ::v8::HandleScope<'s, ()>,
+      ) -> ::v8::Local<'s, ::v8::FunctionTemplate> {
+        let template = #constructor;
+        ::v8::class::init_template::<Self>(scope, template);
+        let signature = ::v8::Signature::new(scope, template);
+        let prototype = template.prototype_template(scope);
+        #(#properties)*
+        template
+      }
+
+      fn external_references(
+      ) -> ::std::vec::Vec<::v8::ExternalReference<'static>> {
+        ::std::vec![#(#references),*]
+      }
+    }
+  })
+}
+
+fn type_name(ty: &Type) -> Result<String> {
+  if let Type::Path(path) = ty {
+    if let Some(segment) = path.path.segments.last() {
+      return Ok(segment.ident.to_string());
+    }
+  }
+  Err(Error::new_spanned(
+    ty,
+    "cannot derive a class name; use #[v8::class(name = \"...\")]",
+  ))
+}
+
+fn illegal_constructor() -> Ident {
+  Ident::new("__v8_class_illegal_constructor", Span::call_site())
+}
+
+fn map_callback(callback: &Ident) -> TokenStream2 {
+  quote! {
+    ::v8::MapFnTo::<
+      ::v8::FunctionCallback,
+      ::v8::class::FallibleCallbackTag,
+    >::map_fn_to(Self::#callback)
+  }
+}
+
+/// Removes the attributes of the macro from `function` and describes it, or
+/// returns `None` if it is skipped.
+fn parse_member(function: &mut ImplItemFn) -> Result<Option<Member>> {
+  let mut kind = None;
+  let mut js_name = None;
+  let mut skip = false;
+  let mut error = None;
+  function.attrs.retain(|attr: &Attribute| {
+    let path = attr.path();
+    let new_kind = if path.is_ident("constructor") {
+      Kind::Constructor
+    } else if path.is_ident("getter") {
+      Kind::Getter
+    } else if path.is_ident("setter") {
+      Kind::Setter
+    } else if path.is_ident("js_name") {
+      match js_name_value(attr) {
+        Ok(value) => js_name = Some(value),
+        Err(e) => error = Some(e),
+      }
+      return false;
+    } else if path.is_ident("skip") {
+      skip = true;
+      return false;
+    } else {
+      return true;
+    };
+    if let Err(e) = attr.meta.require_path_only() {
+      error = Some(e);
+    } else if kind.replace(new_kind).is_some() {
+      error = Some(Error::new_spanned(attr, "conflicting class attributes"));
+    }
+    false
+  });
+  if let Some(error) = error {
+    return Err(error);
+  }
+  if skip {
+    return Ok(None);
+  }
+
+  let signature = &function.sig;
+  let mut has_receiver = false;
+  let mut params = Vec::new();
+  for input in &signature.inputs {
+    match input {
+      FnArg::Receiver(receiver) => {
+        if receiver.reference.is_none() || receiver.mutability.is_some() {
+          return Err(Error::new_spanned(
+            receiver,
+            "class methods must take `&self`; use interior mutability to \
+             modify the value",
+          ));
+        }
+        has_receiver = true;
+      }
+      FnArg::Typed(arg) if is_handle_scope(&arg.ty) => {
+        params.push(Param::Scope)
+      }
+      FnArg::Typed(_) => params.push(Param::Argument),
+    }
+  }
+  if signature.generics.type_params().next().is_some() {
+    return Err(Error::new_spanned(
+      &signature.generics,
+      "class methods can't have type parameters",
+    ));
+  }
+
+  let rust_name = signature.ident.clone();
+  let kind = match kind {
+    Some(kind) => kind,
+    None if has_receiver => Kind::Method,
+    None => Kind::Static,
+  };
+  let arguments = params
+    .iter()
+    .filter(|param| matches!(param, Param::Argument))
+    .count();
+  let problem = match kind {
+    Kind::Constructor if has_receiver => {
+      Some("a constructor can't take `self`")
+    }
+    Kind::Getter | Kind::Setter if !has_receiver => {
+      Some("accessors must take `&self`")
+    }
+    Kind::Getter if arguments != 0 => Some("getters can't take arguments"),
+    Kind::Setter if arguments != 1 => {
+      Some("setters must take exactly one argument")
+    }
+    _ => None,
+  };
+  if let Some(problem) = problem {
+    return Err(Error::new(signature.span(), problem));
+  }
+
+  let js_name = js_name.unwrap_or_else(|| {
+    let name = rust_name.to_string();
+    let name = match kind {
+      Kind::Setter => name.strip_prefix("set_").unwrap_or(&name),
+      _ => &name,
+    };
+    camel_case(name)
+  });
+  let callback = match kind {
+    Kind::Constructor => format_ident!("__v8_class_constructor"),
+    Kind::Method => format_ident!("__v8_class_method_{}", rust_name),
+    Kind::Static => format_ident!("__v8_class_static_{}", rust_name),
+    Kind::Getter => format_ident!("__v8_class_get_{}", rust_name),
+    Kind::Setter => format_ident!("__v8_class_set_{}", rust_name),
+  };
+  Ok(Some(Member {
+    kind,
+    js_name,
+    rust_name,
+    callback,
+    params,
+  }))
+}
+
+fn js_name_value(attr: &Attribute) -> Result<String> {
+  let meta = attr.meta.require_name_value()?;
+  match &meta.value {
+    Expr::Lit(ExprLit {
+      lit: Lit::Str(name),
+      ..
+    }) => Ok(name.value()),
+    value => Err(Error::new_spanned(value, "expected a string literal")),
+  }
+}
+
+/// Whether `ty` is a `&mut HandleScope`, which receives the scope of the call.
+fn is_handle_scope(ty: &Type) -> bool {
+  let Type::Reference(reference) = ty else {
+    return false;
+  };
+  let Type::Path(path) = &*reference.elem else {
+    return false;
+  };
+  reference.mutability.is_some()
+    && path
+      .path
+      .segments
+      .last()
+      .is_some_and(|segment| segment.ident == "HandleScope")
+}
+
+fn camel_case(name: &str) -> String {
+  let mut result = String::with_capacity(name.len());
+  let mut upper = false;
+  for c in name.trim_start_matches('_').chars() {
+    if c == '_' {
+      upper = true;
+    } else if upper {
+      result.extend(c.to_uppercase());
+      upper = false;
+    } else {
+      result.push(c);
+    }
+  }
+  result
+}
+
+/// Generates the callback that converts the arguments of a call, invokes the
+/// member and converts its result.
+fn callback_fn(member: &Member) -> TokenStream2 {
+  let Member {
+    rust_name,
+    callback,
+    params,
+    ..
+  } = member;
+  let mut bindings = Vec::new();
+  let mut call_args = Vec::new();
+  if matches!(member.kind, Kind::Method | Kind::Getter | Kind::Setter) {
+    bindings.push(quote! {
+      let this = ::v8::class::this::<Self>(scope, args)?;
+    });
+    call_args.push(quote! { this });
+  }
+  let mut index = 0i32;
+  for param in params {
+    match param {
+      Param::Scope => call_args.push(quote! { scope }),
+      Param::Argument => {
+        let value = format_ident!("arg{}", index as usize);
+        bindings.push(quote! {
+          let #value = ::v8::class::argument(scope, args, #index)?;
+        });
+        call_args.push(quote! { #value });
+        index += 1;
+      }
+    }
+  }
+  let body = if member.kind == Kind::Constructor {
+    quote! {
+      ::v8::class::check_construct_call::<Self>(args)?;
+      #(#bindings)*
+      let value = ::v8::class::ConstructorResult::<Self>::into_value(
+        Self::#rust_name(#(#call_args),*),
+      )?;
+      ::v8::class::wrap(scope, args.this(), value);
+      ::std::result::Result::Ok(args.this().into())
+    }
+  } else {
+    quote! {
+      #(#bindings)*
+      let result = Self::#rust_name(#(#call_args),*);
+      ::v8::TypedFunctionResult::into_result(result, scope)
+    }
+  };
+  quote! {
+    #[doc(hidden)]
+    fn #callback<'s>(
+      scope: &mut ::v8::HandleScope<'s>,
+      args: &::v8::FunctionCallbackArguments<'s>,
+    ) -> ::std::result::Result<::v8::Local<'s, ::v8::Value>, ::v8::JsError> {
+      #body
+    }
+  }
+}
+
+/// Creates the function template of a method or static method and adds it
+/// to the prototype or the constructor.
+fn install_function(member: &Member) -> TokenStream2 {
+  let js_name = &member.js_name;
+  let callback = map_callback(&member.callback);
+  let length = member.length();
+  let (signature, target) = match member.kind {
+    Kind::Method => (quote! { .signature(signature) }, quote! { prototype }),
+    _ => (quote! {}, quote! { template }),
+  };
+  quote! {
+    let name = ::v8::String::new(scope, #js_name).unwrap();
+    let function = ::v8::FunctionTemplate::builder_raw(#callback)
+      #signature
+      .length(#length)
+      .constructor_behavior(::v8::ConstructorBehavior::Throw)
+      .build(scope);
+    #target.set_with_attr(
+      name.into(),
+      function.into(),
+      ::v8::PropertyAttribute::DONT_ENUM,
+    );
+  }
+}
+
+fn accessor_template(member: Option<&Member>) -> TokenStream2 {
+  let Some(member) = member else {
+    return quote! { ::std::option::Option::None };
+  };
+  let callback = map_callback(&member.callback);
+  let length = member.length();
+  quote! {
+    ::std::option::Option::Some(
+      ::v8::FunctionTemplate::builder_raw(#callback)
+        .signature(signature)
+        .length(#length)
+        .constructor_behavior(::v8::ConstructorBehavior::Throw)
+        .build(scope),
+    )
+  }
+}
@@ -2,11 +2,17 @@
 
 use std::cell::Cell;
 use std::ffi::c_void;
+use std::fs::File;
+use std::io;
 use std::ops::Deref;
 use std::ptr::null;
 use std::ptr::NonNull;
 use std::slice;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
+use crate::binding::v8__ArrayBuffer__kMaxByteLength;
 use crate::support::long;
 use crate::support::MaybeBool;
 use crate::support::Opaque;
@@ -46,6 +52,7 @@ extern "C" {
   fn v8__ArrayBuffer__IsDetachable(this: *const ArrayBuffer) -> bool;
   fn v8__ArrayBuffer__WasDetached(this: *const ArrayBuffer) -> bool;
   fn v8__ArrayBuffer__ByteLength(this: *const ArrayBuffer) -> usize;
+  fn v8__ArrayBuffer__MaxByteLength(this: *const ArrayBuffer) -> usize;
   fn v8__ArrayBuffer__GetBackingStore(
     this: *const ArrayBuffer,
   ) -> SharedRef<BackingStore>;
@@ -53,17 +60,27 @@ extern "C" {
     isolate: *mut Isolate,
     byte_length: usize,
   ) -> *mut BackingStore;
+  fn v8__ArrayBuffer__NewResizableBackingStore(
+    byte_length: usize,
+    max_byte_length: usize,
+  ) -> *mut BackingStore;
   fn v8__ArrayBuffer__NewBackingStore__with_data(
     data: *mut c_void,
     byte_length: usize,
     deleter: BackingStoreDeleterCallback,
     deleter_data: *mut c_void,
   ) -> *mut BackingStore;
+  fn v8__ArrayBuffer__NewBackingStore__from_file(
+    file: isize,
+    byte_length: usize,
+    copy_on_write: bool,
+  ) -> *mut BackingStore;
   fn v8__BackingStore__EmptyBackingStore(shared: bool) -> *mut BackingStore;
 
   fn v8__BackingStore__Data(this: *const BackingStore) -> *mut c_void;
   fn v8__BackingStore__ByteLength(this: *const BackingStore) -> usize;
   fn v8__BackingStore__IsShared(this: *const BackingStore) -> bool;
+  fn v8__BackingStore__MaxByteLength(this: *const BackingStore) -> usize;
   fn v8__BackingStore__IsResizableByUserJavaScript(
     this: *const BackingStore,
   ) -> bool;
@@ -190,6 +207,186 @@ pub unsafe fn new_rust_allocator<T: Sized + Send + Sync + 'static>(
   ))
 }
 
+/// Byte counters and the limit of an allocator created with
+/// [`new_accounting_allocator`]. Shared with the embedder, which can read the
+/// counters and change the limit while the allocator is in use.
+#[derive(Debug)]
+pub struct ArrayBufferQuota {
+  limit: AtomicUsize,
+  live_bytes: AtomicUsize,
+  peak_bytes: AtomicUsize,
+  rejected_allocations: AtomicUsize,
+}
+
+impl ArrayBufferQuota {
+  /// Creates a quota that allows up to `limit` bytes of live ArrayBuffer
+  /// memory. Use `usize::MAX` to only track usage.
+  pub fn new(limit: usize) -> Arc<Self> {
+    Arc::new(Self {
+      limit: AtomicUsize::new(limit),
+      live_bytes: AtomicUsize::new(0),
+      peak_bytes: AtomicUsize::new(0),
+      rejected_allocations: AtomicUsize::new(0),
+    })
+  }
+
+  pub fn limit(&self) -> usize {
+    self.limit.load(Ordering::Relaxed)
+  }
+
+  /// Changes the limit. Lowering it below the current usage doesn't free any
+  /// memory, but makes further allocations fail until enough is freed.
+  pub fn set_limit(&self, limit: usize) {
+    self.limit.store(limit, Ordering::Relaxed);
+  }
+
+  /// The number of bytes currently allocated.
+  pub fn live_bytes(&self) -> usize {
+    self.live_bytes.load(Ordering::Relaxed)
+  }
+
+  /// The highest value `live_bytes` has reached.
+  pub fn peak_bytes(&self) -> usize {
+    self.peak_bytes.load(Ordering::Relaxed)
+  }
+
+  /// The number of allocations that failed because they would have exceeded
+  /// the limit.
+  pub fn rejected_allocations(&self) -> usize {
+    self.rejected_allocations.load(Ordering::Relaxed)
+  }
+
+  /// Reserves `len` bytes, or returns false if that would exceed the limit.
+  fn reserve(&self, len: usize) -> bool {
+    let limit = self.limit();
+    let reserved = self.live_bytes.fetch_update(
+      Ordering::Relaxed,
+      Ordering::Relaxed,
+      |live| live.checked_add(len).filter(|&total| total <= limit),
+    );
+    match reserved {
+      Ok(live) => {
+        self.peak_bytes.fetch_max(live + len, Ordering::Relaxed);
+        true
+      }
+      Err(_) => {
+        self.rejected_allocations.fetch_add(1, Ordering::Relaxed);
+        false
+      }
+    }
+  }
+
+  fn release(&self, len: usize) {
+    self.live_bytes.fetch_sub(len, Ordering::Relaxed);
+  }
+}
+
+/// Creates an allocator that keeps track of the ArrayBuffer memory it hands
+/// out in `quota`, and refuses allocations that would exceed its limit.
+///
+/// When an allocation is refused, V8 first collects garbage and retries. If
+/// that doesn't free enough memory, the JavaScript operation that needed the
+/// buffer throws a `RangeError`. Buffers allocated through the V8 API, e.g.
+/// with `ArrayBuffer::new`, crash with an out-of-memory error instead.
+///
+/// Each isolate should get its own allocator, installed with
+/// `CreateParams::array_buffer_allocator`, to enforce a per-isolate quota.
+pub fn new_accounting_allocator(
+  quota: Arc<ArrayBufferQuota>,
+) -> UniqueRef<Allocator> {
+  // V8 requires memory to be at least as aligned as what `calloc` returns.
+  const ALIGN: usize = 16;
+
+  fn layout(len: usize) -> std::alloc::Layout {
+    // Zero-sized allocations aren't allowed by `std::alloc`.
+    std::alloc::Layout::from_size_align(len.max(1), ALIGN).unwrap()
+  }
+
+  unsafe extern "C" fn allocate(
+    quota: &ArrayBufferQuota,
+    len: usize,
+  ) -> *mut c_void {
+    if !quota.reserve(len) {
+      return std::ptr::null_mut();
+    }
+    let data = std::alloc::alloc_zeroed(layout(len));
+    if data.is_null() {
+      quota.release(len);
+    }
+    data as *mut c_void
+  }
+
+  unsafe extern "C" fn allocate_uninitialized(
+    quota: &ArrayBufferQuota,
+    len: usize,
+  ) -> *mut c_void {
+    if !quota.reserve(len) {
+      return std::ptr::null_mut();
+    }
+    let data = std::alloc::alloc(layout(len));
+    if data.is_null() {
+      quota.release(len);
+    }
+    data as *mut c_void
+  }
+
+  unsafe extern "C" fn free(
+    quota: &ArrayBufferQuota,
+    data: *mut c_void,
+    len: usize,
+  ) {
+    std::alloc::dealloc(data as *mut u8, layout(len));
+    quota.release(len);
+  }
+
+  unsafe extern "C" fn reallocate(
+    quota: &ArrayBufferQuota,
+    data: *mut c_void,
+    old_length: usize,
+    new_length: usize,
+  ) -> *mut c_void {
+    if new_length > old_length && !quota.reserve(new_length - old_length) {
+      return std::ptr::null_mut();
+    }
+    let new_data = std::alloc::realloc(
+      data as *mut u8,
+      layout(old_length),
+      new_length.max(1),
+    );
+    if new_data.is_null() {
+      if new_length > old_length {
+        quota.release(new_length - old_length);
+      }
+      return std::ptr::null_mut();
+    }
+    if new_length > old_length {
+      // Like `allocate`, the additional memory has to be zeroed.
+      new_data
+        .add(old_length)
+        .write_bytes(0, new_length - old_length);
+    } else {
+      quota.release(old_length - new_length);
+    }
+    new_data as *mut c_void
+  }
+
+  unsafe extern "C" fn drop(quota: *const ArrayBufferQuota) {
+    std::mem::drop(Arc::from_raw(quota));
+  }
+
+  const VTABLE: RustAllocatorVtable<ArrayBufferQuota> = RustAllocatorVtable {
+    allocate,
+    allocate_uninitialized,
+    free,
+    reallocate,
+    drop,
+  };
+
+  // SAFETY: The handle is an `Arc` that is released by `drop`, which the
+  // allocator calls exactly once when it is destroyed.
+  unsafe { new_rust_allocator(Arc::into_raw(quota), &VTABLE) }
+}
+
 #[test]
 fn test_rust_allocator() {
   use std::sync::atomic::{AtomicUsize, Ordering};
@@ -298,6 +495,18 @@ impl sealed::Rawable<[u8]> for Box<[u8]> {
   }
 }
 
+/// How [`ArrayBuffer::new_backing_store_from_file`] maps a file into memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileMapMode {
+  /// The file is mapped without write access. Writing to the buffer crashes
+  /// the process.
+  ReadOnly,
+  /// The file is mapped privately with write access. Pages are copied on
+  /// first write, so modifications are visible only through this mapping and
+  /// never reach the file.
+  CopyOnWrite,
+}
+
 /// A wrapper around the backing store (i.e. the raw memory) of an array buffer.
 /// See a document linked in http://crbug.com/v8/9908 for more information.
 ///
@@ -316,6 +525,14 @@ pub struct BackingStore([usize; 6]);
 unsafe impl Send for BackingStore {}
 
 impl BackingStore {
+  /// Returns whether the memory of `store`, a reference obtained from an
+  /// ArrayBuffer, can only be reached through that ArrayBuffer: it isn't
+  /// shared memory, and no other ArrayBuffer or `SharedRef` refers to it.
+  #[inline(always)]
+  pub(crate) fn is_exclusive(store: &SharedRef<BackingStore>) -> bool {
+    !store.is_shared() && store.use_count() == 2
+  }
+
   /// Return a pointer to the beginning of the memory block for this backing
   /// store. The pointer is only valid as long as this backing store object
   /// lives.
@@ -334,6 +551,16 @@ impl BackingStore {
     unsafe { v8__BackingStore__ByteLength(self) }
   }
 
+  /// The maximum length (in bytes) that this backing store may grow to.
+  ///
+  /// If this backing store was created for a resizable ArrayBuffer or a
+  /// growable SharedArrayBuffer, it is >= byte_length(). Otherwise it is
+  /// equal to byte_length().
+  #[inline(always)]
+  pub fn max_byte_length(&self) -> usize {
+    unsafe { v8__BackingStore__MaxByteLength(self) }
+  }
+
   /// Indicates whether the backing store was created for an ArrayBuffer or
   /// a SharedArrayBuffer.
   #[inline(always)]
@@ -393,6 +620,9 @@ impl Shared for BackingStore {
 }
 
 impl ArrayBuffer {
+  /// The largest supported ArrayBuffer byte length.
+  pub const MAX_BYTE_LENGTH: usize = v8__ArrayBuffer__kMaxByteLength;
+
   /// Create a new ArrayBuffer. Allocate |byte_length| bytes.
   /// Allocated memory will be owned by a created ArrayBuffer and
   /// will be deallocated when it is garbage-collected,
@@ -445,6 +675,13 @@ impl ArrayBuffer {
     unsafe { v8__ArrayBuffer__ByteLength(self) }
   }
 
+  /// Maximum length in bytes. For a resizable ArrayBuffer this is the length
+  /// it may be resized to by JavaScript, otherwise it equals byte_length().
+  #[inline(always)]
+  pub fn max_byte_length(&self) -> usize {
+    unsafe { v8__ArrayBuffer__MaxByteLength(self) }
+  }
+
   /// Returns true if this ArrayBuffer may be detached.
   #[inline(always)]
   pub fn is_detachable(&self) -> bool {
@@ -492,6 +729,53 @@ impl ArrayBuffer {
     NonNull::new(raw_ptr)
   }
 
+  /// Borrows the contents of this ArrayBuffer without copying them.
+  ///
+  /// The slice mutably borrows the isolate, so no JavaScript can run and the
+  /// buffer can't be detached or resized while it is alive. A detached buffer
+  /// yields an empty slice. Returns `None` if `isolate` doesn't own this
+  /// buffer, or if its contents can be reached without the isolate: when this
+  /// is actually a `SharedArrayBuffer`, e.g. one returned by
+  /// `ArrayBufferView::buffer`, or when another ArrayBuffer or a `BackingStore`
+  /// reference shares them.
+  #[inline(always)]
+  pub fn as_slice<'a>(&'a self, isolate: &'a mut Isolate) -> Option<&'a [u8]> {
+    let (data, length) = self.contents(isolate)?;
+    // SAFETY: The contents can't be modified or freed without the isolate,
+    // which is borrowed for `'a`.
+    Some(unsafe { slice::from_raw_parts(data, length) })
+  }
+
+  /// Mutably borrows the contents of this ArrayBuffer without copying them.
+  ///
+  /// See [`Self::as_slice`].
+  #[inline(always)]
+  pub fn as_mut_slice<'a>(
+    &'a self,
+    isolate: &'a mut Isolate,
+  ) -> Option<&'a mut [u8]> {
+    let (data, length) = self.contents(isolate)?;
+    // SAFETY: See `as_slice`. Any other slice of this isolate's buffers would
+    // need another borrow of the isolate, so this one is unique.
+    Some(unsafe { slice::from_raw_parts_mut(data, length) })
+  }
+
+  #[inline(always)]
+  fn contents(&self, isolate: &Isolate) -> Option<(*mut u8, usize)> {
+    if self.is_shared_array_buffer()
+      || !std::ptr::eq(self.get_isolate_ptr(), isolate)
+    {
+      return None;
+    }
+    match self.data() {
+      Some(data) if BackingStore::is_exclusive(&self.get_backing_store()) => {
+        Some((data.as_ptr() as *mut u8, self.byte_length()))
+      }
+      Some(_) => None,
+      None => Some((NonNull::dangling().as_ptr(), 0)),
+    }
+  }
+
   /// Get a shared pointer to the backing store of this array buffer. This
   /// pointer coordinates the lifetime management of the internal storage
   /// with any live ArrayBuffers on the heap, even across isolates. The embedder
@@ -521,6 +805,29 @@ impl ArrayBuffer {
     }
   }
 
+  /// Returns a new standalone BackingStore for a resizable ArrayBuffer. It
+  /// reserves `max_byte_length` bytes of address space up front and commits
+  /// `byte_length` bytes, so the buffer can later grow in place through
+  /// `ArrayBuffer.prototype.resize` without moving its contents.
+  ///
+  /// Panics if `byte_length` exceeds `max_byte_length`, or if
+  /// `max_byte_length` exceeds [`ArrayBuffer::MAX_BYTE_LENGTH`]. Crashes with
+  /// an out-of-memory error if the memory can't be reserved.
+  #[inline(always)]
+  pub fn new_resizable_backing_store(
+    byte_length: usize,
+    max_byte_length: usize,
+  ) -> UniqueRef<BackingStore> {
+    assert!(byte_length <= max_byte_length);
+    assert!(max_byte_length <= Self::MAX_BYTE_LENGTH);
+    unsafe {
+      UniqueRef::from_raw(v8__ArrayBuffer__NewResizableBackingStore(
+        byte_length,
+        max_byte_length,
+      ))
+    }
+  }
+
   /// Returns a new standalone BackingStore that takes over the ownership of
   /// the given buffer.
   ///
@@ -622,6 +929,59 @@ impl ArrayBuffer {
       deleter_data,
     ))
   }
+
+  /// Returns a new standalone BackingStore that maps the contents of `file`
+  /// into memory instead of copying them. The mapping is owned by the
+  /// BackingStore and is released by its deleter. Once the store is attached
+  /// to an ArrayBuffer, V8 accounts for its size as external memory.
+  ///
+  /// Pages are loaded lazily, so large files can be exposed to JavaScript
+  /// without reading them up front. The file handle itself is not retained and
+  /// may be closed after this call.
+  ///
+  /// # Safety
+  ///
+  /// The file must not be truncated while the mapping is alive; accessing
+  /// pages past the end of the file terminates the process. Changes made to
+  /// the file by other processes may or may not become visible through the
+  /// buffer.
+  ///
+  /// With [`FileMapMode::ReadOnly`] the memory is mapped without write
+  /// access, so the caller must also ensure that nothing, including
+  /// JavaScript code, writes to the buffer.
+  pub unsafe fn new_backing_store_from_file(
+    file: &File,
+    mode: FileMapMode,
+  ) -> io::Result<UniqueRef<BackingStore>> {
+    let byte_length = usize::try_from(file.metadata()?.len())
+      .ok()
+      .filter(|&len| len <= Self::MAX_BYTE_LENGTH)
+      .ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "file is too large")
+      })?;
+    if byte_length == 0 {
+      return Ok(UniqueRef::from_raw(v8__BackingStore__EmptyBackingStore(
+        false,
+      )));
+    }
+
+    #[cfg(target_family = "unix")]
+    let raw_file = std::os::unix::io::AsRawFd::as_raw_fd(file) as isize;
+    #[cfg(target_family = "windows")]
+    let raw_file =
+      std::os::windows::io::AsRawHandle::as_raw_handle(file) as isize;
+
+    let backing_store = v8__ArrayBuffer__NewBackingStore__from_file(
+      raw_file,
+      byte_length,
+      mode == FileMapMode::CopyOnWrite,
+    );
+    if backing_store.is_null() {
+      Err(io::Error::last_os_error())
+    } else {
+      Ok(UniqueRef::from_raw(backing_store))
+    }
+  }
 }
 
 impl DataView {
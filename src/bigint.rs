@@ -5,6 +5,7 @@ use crate::HandleScope;
 use crate::Isolate;
 use crate::Local;
 
+use std::fmt::Write;
 use std::mem::MaybeUninit;
 
 extern "C" {
@@ -140,4 +141,259 @@ impl BigInt {
       },
     )
   }
+
+  /// Creates a BigInt from a signed 128-bit integer.
+  #[inline]
+  pub fn new_from_i128<'s>(
+    scope: &mut HandleScope<'s>,
+    value: i128,
+  ) -> Local<'s, BigInt> {
+    let magnitude = value.unsigned_abs();
+    let words = [magnitude as u64, (magnitude >> 64) as u64];
+    Self::new_from_words(scope, value < 0, &words).unwrap()
+  }
+
+  /// Creates a BigInt from an unsigned 128-bit integer.
+  #[inline]
+  pub fn new_from_u128<'s>(
+    scope: &mut HandleScope<'s>,
+    value: u128,
+  ) -> Local<'s, BigInt> {
+    let words = [value as u64, (value >> 64) as u64];
+    Self::new_from_words(scope, false, &words).unwrap()
+  }
+
+  /// Returns the value of this BigInt as an unsigned 128-bit integer, and a
+  /// `bool` indicating that the conversion was lossless when `true`.
+  /// Like `u64_value`, the value is truncated or wrapped around otherwise,
+  /// in particular if the BigInt is negative.
+  #[inline]
+  pub fn u128_value(&self) -> (u128, bool) {
+    let (sign_bit, magnitude) = self.low_u128();
+    let lossless = !sign_bit && self.word_count() <= 2;
+    if sign_bit {
+      (magnitude.wrapping_neg(), lossless)
+    } else {
+      (magnitude, lossless)
+    }
+  }
+
+  /// Returns the value of this BigInt as a signed 128-bit integer, and a
+  /// `bool` indicating that the conversion was lossless when `true`.
+  /// Like `i64_value`, the value is truncated or wrapped around otherwise.
+  #[inline]
+  pub fn i128_value(&self) -> (i128, bool) {
+    let (sign_bit, magnitude) = self.low_u128();
+    let fits = if sign_bit {
+      magnitude <= i128::MIN.unsigned_abs()
+    } else {
+      magnitude <= i128::MAX as u128
+    };
+    let lossless = fits && self.word_count() <= 2;
+    if sign_bit {
+      ((magnitude as i128).wrapping_neg(), lossless)
+    } else {
+      (magnitude as i128, lossless)
+    }
+  }
+
+  /// Returns the sign bit and the low 128 bits of the magnitude.
+  fn low_u128(&self) -> (bool, u128) {
+    let mut words = [0; 2];
+    let (sign_bit, words) = self.to_words_array(&mut words);
+    let magnitude = words
+      .iter()
+      .rev()
+      .fold(0, |acc, &word| (acc << 64) | word as u128);
+    (sign_bit, magnitude)
+  }
+
+  /// Creates a BigInt from a sign bit and the little-endian bytes of its
+  /// magnitude.
+  pub fn new_from_bytes_le<'s>(
+    scope: &mut HandleScope<'s>,
+    sign_bit: bool,
+    bytes: &[u8],
+  ) -> Option<Local<'s, BigInt>> {
+    let words = bytes
+      .chunks(8)
+      .map(|chunk| {
+        let mut word = [0; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        u64::from_le_bytes(word)
+      })
+      .collect::<Vec<_>>();
+    Self::new_from_words(scope, sign_bit, &words)
+  }
+
+  /// Creates a BigInt from a sign bit and the big-endian bytes of its
+  /// magnitude.
+  pub fn new_from_bytes_be<'s>(
+    scope: &mut HandleScope<'s>,
+    sign_bit: bool,
+    bytes: &[u8],
+  ) -> Option<Local<'s, BigInt>> {
+    let words = bytes
+      .rchunks(8)
+      .map(|chunk| {
+        let mut word = [0; 8];
+        word[8 - chunk.len()..].copy_from_slice(chunk);
+        u64::from_be_bytes(word)
+      })
+      .collect::<Vec<_>>();
+    Self::new_from_words(scope, sign_bit, &words)
+  }
+
+  /// Converts this BigInt to a (sign_bit, bytes) pair, where `bytes` is the
+  /// magnitude in little-endian order without trailing zeros. Zero is
+  /// represented by a single zero byte.
+  pub fn to_bytes_le(&self) -> (bool, Vec<u8>) {
+    let (sign_bit, words) = self.to_words();
+    let mut bytes = words
+      .iter()
+      .flat_map(|word| word.to_le_bytes())
+      .collect::<Vec<_>>();
+    while bytes.len() > 1 && bytes.last() == Some(&0) {
+      bytes.pop();
+    }
+    if bytes.is_empty() {
+      bytes.push(0);
+    }
+    (sign_bit, bytes)
+  }
+
+  /// Converts this BigInt to a (sign_bit, bytes) pair, where `bytes` is the
+  /// magnitude in big-endian order without leading zeros. Zero is represented
+  /// by a single zero byte.
+  pub fn to_bytes_be(&self) -> (bool, Vec<u8>) {
+    let (sign_bit, mut bytes) = self.to_bytes_le();
+    bytes.reverse();
+    (sign_bit, bytes)
+  }
+
+  /// Parses a BigInt from a decimal string, which may start with `-` or `+`.
+  /// Returns `None` if `value` contains anything but decimal digits.
+  pub fn new_from_decimal<'s>(
+    scope: &mut HandleScope<'s>,
+    value: &str,
+  ) -> Option<Local<'s, BigInt>> {
+    let (sign_bit, digits) = match value.as_bytes() {
+      [b'-', digits @ ..] => (true, digits),
+      [b'+', digits @ ..] => (false, digits),
+      digits => (false, digits),
+    };
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+      return None;
+    }
+
+    let mut words = Vec::with_capacity(digits.len() / DECIMAL_CHUNK_DIGITS + 1);
+    // Start with a shorter chunk so that every following chunk has exactly
+    // `DECIMAL_CHUNK_DIGITS` digits.
+    let first = match digits.len() % DECIMAL_CHUNK_DIGITS {
+      0 => DECIMAL_CHUNK_DIGITS,
+      n => n,
+    };
+    let (head, tail) = digits.split_at(first);
+    for chunk in std::iter::once(head).chain(tail.chunks(DECIMAL_CHUNK_DIGITS))
+    {
+      let value = chunk
+        .iter()
+        .fold(0, |acc, &digit| acc * 10 + (digit - b'0') as u64);
+      mul_add(&mut words, 10u64.pow(chunk.len() as u32), value);
+    }
+    Self::new_from_words(scope, sign_bit, &words)
+  }
+
+  /// Formats this BigInt as a decimal string, like `BigInt.prototype.toString`
+  /// does, without entering JavaScript.
+  pub fn to_decimal_string(&self) -> String {
+    let (sign_bit, mut words) = self.to_words();
+    while words.last() == Some(&0) {
+      words.pop();
+    }
+    if words.is_empty() {
+      return "0".to_owned();
+    }
+
+    // Chunks of `DECIMAL_CHUNK_DIGITS` digits, least significant first.
+    let mut chunks = Vec::new();
+    while !words.is_empty() {
+      chunks.push(div_rem(&mut words, DECIMAL_CHUNK));
+    }
+    let mut result =
+      String::with_capacity(chunks.len() * DECIMAL_CHUNK_DIGITS + 1);
+    if sign_bit {
+      result.push('-');
+    }
+    let mut chunks = chunks.iter().rev();
+    write!(result, "{}", chunks.next().unwrap()).unwrap();
+    for chunk in chunks {
+      write!(result, "{:019}", chunk).unwrap();
+    }
+    result
+  }
+
+  fn to_words(&self) -> (bool, Vec<u64>) {
+    let mut words = vec![0; self.word_count()];
+    let (sign_bit, _) = self.to_words_array(&mut words);
+    (sign_bit, words)
+  }
+
+  /// Creates a BigInt from a `num_bigint::BigInt`.
+  #[cfg(feature = "num-bigint")]
+  pub fn new_from_num_bigint<'s>(
+    scope: &mut HandleScope<'s>,
+    value: &num_bigint::BigInt,
+  ) -> Option<Local<'s, BigInt>> {
+    let (sign, words) = value.to_u64_digits();
+    Self::new_from_words(scope, sign == num_bigint::Sign::Minus, &words)
+  }
+
+  /// Converts this BigInt to a `num_bigint::BigInt`.
+  #[cfg(feature = "num-bigint")]
+  pub fn to_num_bigint(&self) -> num_bigint::BigInt {
+    let (sign_bit, words) = self.to_words();
+    let digits = words
+      .iter()
+      .flat_map(|&word| [word as u32, (word >> 32) as u32])
+      .collect();
+    let sign = if sign_bit {
+      num_bigint::Sign::Minus
+    } else {
+      num_bigint::Sign::Plus
+    };
+    num_bigint::BigInt::from_biguint(sign, num_bigint::BigUint::new(digits))
+  }
+}
+
+/// The largest power of ten that fits into a `u64`, and its exponent.
+const DECIMAL_CHUNK: u64 = 10_000_000_000_000_000_000;
+const DECIMAL_CHUNK_DIGITS: usize = 19;
+
+/// Computes `words = words * mul + add` on little-endian words.
+fn mul_add(words: &mut Vec<u64>, mul: u64, add: u64) {
+  let mut carry = add as u128;
+  for word in words.iter_mut() {
+    let value = *word as u128 * mul as u128 + carry;
+    *word = value as u64;
+    carry = value >> 64;
+  }
+  if carry != 0 {
+    words.push(carry as u64);
+  }
+}
+
+/// Divides little-endian `words` by `div` in place, dropping leading zero
+/// words, and returns the remainder.
+fn div_rem(words: &mut Vec<u64>, div: u64) -> u64 {
+  let mut rem = 0u128;
+  for word in words.iter_mut().rev() {
+    let value = (rem << 64) | *word as u128;
+    *word = (value / div as u128) as u64;
+    rem = value % div as u128;
+  }
+  while words.last() == Some(&0) {
+    words.pop();
+  }
+  rem as u64
 }
@@ -24,14 +24,25 @@
 #include "v8/src/api/api.h"
 #include "v8/src/base/debug/stack_trace.h"
 #include "v8/src/base/sys-info.h"
+#include "v8/src/debug/debug-interface.h"
+#include "v8/src/debug/interface-types.h"
 #include "v8/src/execution/isolate-utils-inl.h"
 #include "v8/src/execution/isolate-utils.h"
 #include "v8/src/flags/flags.h"
+#include "v8/src/init/v8.h"
 #include "v8/src/libplatform/default-platform.h"
+#include "v8/src/objects/backing-store.h"
+#include "v8/src/objects/js-array-buffer.h"
 #include "v8/src/objects/objects-inl.h"
 #include "v8/src/objects/objects.h"
 #include "v8/src/objects/smi.h"
 
+#ifdef _WIN32
+#include "v8/src/base/win32-headers.h"
+#else
+#include <sys/mman.h>
+#endif
+
 using namespace support;
 
 template <typename T>
@@ -904,6 +915,13 @@ v8::BackingStore* v8__ArrayBuffer__NewBackingStore__with_byte_length(
   return u.release();
 }
 
+v8::BackingStore* v8__ArrayBuffer__NewResizableBackingStore(
+    size_t byte_length, size_t max_byte_length) {
+  std::unique_ptr<v8::BackingStore> u =
+      v8::ArrayBuffer::NewResizableBackingStore(byte_length, max_byte_length);
+  return u.release();
+}
+
 v8::BackingStore* v8__ArrayBuffer__NewBackingStore__with_data(
     void* data, size_t byte_length, v8::BackingStore::DeleterCallback deleter,
     void* deleter_data) {
@@ -912,6 +930,42 @@ v8::BackingStore* v8__ArrayBuffer__NewBackingStore__with_data(
   return u.release();
 }
 
+// Maps the first `byte_length` bytes of an open file (a file descriptor, or a
+// HANDLE on Windows) privately into memory. The mapping is unmapped by the
+// deleter of the returned BackingStore. On failure nullptr is returned, with
+// errno (GetLastError() on Windows) describing the error.
+v8::BackingStore* v8__ArrayBuffer__NewBackingStore__from_file(
+    intptr_t file, size_t byte_length, bool copy_on_write) {
+#ifdef _WIN32
+  HANDLE mapping = CreateFileMappingW(
+      reinterpret_cast<HANDLE>(file), nullptr,
+      copy_on_write ? PAGE_WRITECOPY : PAGE_READONLY, 0, 0, nullptr);
+  if (mapping == nullptr) return nullptr;
+  void* data =
+      MapViewOfFile(mapping, copy_on_write ? FILE_MAP_COPY : FILE_MAP_READ, 0,
+                    0, byte_length);
+  // The view keeps the file mapping object alive on its own.
+  DWORD error = GetLastError();
+  CloseHandle(mapping);
+  if (data == nullptr) {
+    SetLastError(error);
+    return nullptr;
+  }
+  auto deleter = [](void* data, size_t, void*) { UnmapViewOfFile(data); };
+#else
+  int prot = copy_on_write ? PROT_READ | PROT_WRITE : PROT_READ;
+  void* data = mmap(nullptr, byte_length, prot, MAP_PRIVATE,
+                    static_cast<int>(file), 0);
+  if (data == MAP_FAILED) return nullptr;
+  auto deleter = [](void* data, size_t byte_length, void*) {
+    munmap(data, byte_length);
+  };
+#endif
+  std::unique_ptr<v8::BackingStore> u =
+      v8::ArrayBuffer::NewBackingStore(data, byte_length, deleter, nullptr);
+  return u.release();
+}
+
 two_pointers_t v8__ArrayBuffer__GetBackingStore(const v8::ArrayBuffer& self) {
   return make_pod<two_pointers_t>(ptr_to_local(&self)->GetBackingStore());
 }
@@ -922,6 +976,10 @@ v8::BackingStore* v8__BackingStore__EmptyBackingStore(bool shared) {
   return static_cast<v8::BackingStore*>(u.release());
 }
 
+size_t v8__BackingStore__MaxByteLength(const v8::BackingStore& self) {
+  return self.MaxByteLength();
+}
+
 bool v8__BackingStore__IsResizableByUserJavaScript(
     const v8::BackingStore& self) {
   return ptr_to_local(&self)->IsResizableByUserJavaScript();
@@ -1097,7 +1155,7 @@ class ExternalOneByteString : public v8::String::ExternalOneByteStringResource {
   ~ExternalOneByteString() override {
     (*rustDestroy_)(data_, length_);
     isolate_->AdjustAmountOfExternalAllocatedMemory(
-        -static_cast<int64_t>(-length_));
+        -static_cast<int64_t>(length_));
   }
 
   const char* data() const override { return data_; }
@@ -1197,6 +1255,72 @@ const v8::String* v8__String__NewExternalTwoByteStatic(v8::Isolate* isolate,
       isolate, new ExternalStaticStringResource(data, length)));
 }
 
+// An external string resource whose characters are owned by a Rust value.
+// Dropping the resource drops that value through `rustDrop`.
+template <typename Resource, typename Char>
+class ExternalOwnedString : public Resource {
+ public:
+  using RustDrop = void (*)(void*);
+  ExternalOwnedString(const Char* data, size_t length, void* owner,
+                      RustDrop rustDrop, v8::Isolate* isolate)
+      : data_(data),
+        length_(length),
+        owner_(owner),
+        rustDrop_(rustDrop),
+        isolate_(isolate) {
+    isolate_->AdjustAmountOfExternalAllocatedMemory(
+        static_cast<int64_t>(length_ * sizeof(Char)));
+  }
+  ~ExternalOwnedString() override {
+    (*rustDrop_)(owner_);
+    isolate_->AdjustAmountOfExternalAllocatedMemory(
+        -static_cast<int64_t>(length_ * sizeof(Char)));
+  }
+
+  const Char* data() const override { return data_; }
+
+  size_t length() const override { return length_; }
+
+ private:
+  const Char* data_;
+  const size_t length_;
+  void* owner_;
+  RustDrop rustDrop_;
+  v8::Isolate* isolate_;
+};
+
+using ExternalOwnedOneByteString =
+    ExternalOwnedString<v8::String::ExternalOneByteStringResource, char>;
+using ExternalOwnedTwoByteString =
+    ExternalOwnedString<v8::String::ExternalStringResource, uint16_t>;
+
+const v8::String* v8__String__NewExternalOneByteOwned(
+    v8::Isolate* isolate, const char* data, size_t length, void* owner,
+    ExternalOwnedOneByteString::RustDrop rustDrop) {
+  auto resource =
+      new ExternalOwnedOneByteString(data, length, owner, rustDrop, isolate);
+  v8::Local<v8::String> string;
+  // V8 doesn't take ownership of the resource if the string can't be created.
+  if (!v8::String::NewExternalOneByte(isolate, resource).ToLocal(&string)) {
+    delete resource;
+    return nullptr;
+  }
+  return local_to_ptr(string);
+}
+
+const v8::String* v8__String__NewExternalTwoByteOwned(
+    v8::Isolate* isolate, const uint16_t* data, size_t length, void* owner,
+    ExternalOwnedTwoByteString::RustDrop rustDrop) {
+  auto resource =
+      new ExternalOwnedTwoByteString(data, length, owner, rustDrop, isolate);
+  v8::Local<v8::String> string;
+  if (!v8::String::NewExternalTwoByte(isolate, resource).ToLocal(&string)) {
+    delete resource;
+    return nullptr;
+  }
+  return local_to_ptr(string);
+}
+
 bool v8__String__IsExternal(const v8::String& self) {
   return self.IsExternal();
 }
@@ -1693,6 +1817,21 @@ const v8::Array* v8__Array__New_with_elements(v8::Isolate* isolate,
 
 uint32_t v8__Array__Length(const v8::Array& self) { return self.Length(); }
 
+#define V(NAME, TYPE)                                                     \
+  bool v8__Array__TryToCopyAndConvertToCppBuffer__##NAME(                 \
+      const v8::Array& src, TYPE* dst, uint32_t max_length) {             \
+    return v8::TryToCopyAndConvertArrayToCppBuffer<                       \
+        v8::CTypeInfoBuilder<TYPE>::Build().GetId(), TYPE>(               \
+        ptr_to_local(&src), dst, max_length);                             \
+  }
+
+V(i32, int32_t)
+V(u32, uint32_t)
+V(f32, float)
+V(f64, double)
+
+#undef V
+
 const v8::Date* v8__Date__New(const v8::Context& context, double time) {
   // v8::Date::New() is kind of weird in that it returns a v8::Value,
   // not a v8::Date, even though the object is always a Date object.
@@ -1934,6 +2073,10 @@ size_t v8__ArrayBuffer__ByteLength(const v8::ArrayBuffer& self) {
   return self.ByteLength();
 }
 
+size_t v8__ArrayBuffer__MaxByteLength(const v8::ArrayBuffer& self) {
+  return self.MaxByteLength();
+}
+
 const v8::DataView* v8__DataView__New(const v8::ArrayBuffer& ab, size_t offset,
                                       size_t length) {
   return local_to_ptr(v8::DataView::New(ptr_to_local(&ab), offset, length));
@@ -2281,6 +2424,11 @@ void v8__FunctionTemplate__Inherit(const v8::FunctionTemplate& self,
   ptr_to_local(&self)->Inherit(ptr_to_local(&parent));
 }
 
+bool v8__FunctionTemplate__HasInstance(const v8::FunctionTemplate& self,
+                                       const v8::Value& object) {
+  return ptr_to_local(&self)->HasInstance(ptr_to_local(&object));
+}
+
 void v8__FunctionTemplate__ReadOnlyPrototype(const v8::FunctionTemplate& self) {
   ptr_to_local(&self)->ReadOnlyPrototype();
 }
@@ -2636,6 +2784,35 @@ size_t v8__SharedArrayBuffer__ByteLength(const v8::SharedArrayBuffer& self) {
   return self.ByteLength();
 }
 
+size_t v8__SharedArrayBuffer__MaxByteLength(
+    const v8::SharedArrayBuffer& self) {
+  return self.MaxByteLength();
+}
+
+// The public API can only create resizable backing stores for ArrayBuffers.
+// This mirrors v8::ArrayBuffer::NewResizableBackingStore for a growable
+// SharedArrayBuffer.
+v8::BackingStore* v8__SharedArrayBuffer__NewGrowableBackingStore(
+    size_t byte_length, size_t max_byte_length) {
+  size_t page_size, initial_pages, max_pages;
+  if (i::JSArrayBuffer::GetResizableBackingStorePageConfiguration(
+          nullptr, byte_length, max_byte_length, i::kDontThrow, &page_size,
+          &initial_pages, &max_pages)
+          .IsNothing()) {
+    i::V8::FatalProcessOutOfMemory(
+        nullptr, "v8::SharedArrayBuffer::NewGrowableBackingStore");
+  }
+  std::unique_ptr<i::BackingStoreBase> u =
+      i::BackingStore::TryAllocateAndPartiallyCommitMemory(
+          nullptr, byte_length, max_byte_length, page_size, initial_pages,
+          max_pages, i::WasmMemoryFlag::kNotWasm, i::SharedFlag::kShared);
+  if (!u) {
+    i::V8::FatalProcessOutOfMemory(
+        nullptr, "v8::SharedArrayBuffer::NewGrowableBackingStore");
+  }
+  return static_cast<v8::BackingStore*>(u.release());
+}
+
 two_pointers_t v8__SharedArrayBuffer__GetBackingStore(
     const v8::SharedArrayBuffer& self) {
   return make_pod<two_pointers_t>(ptr_to_local(&self)->GetBackingStore());
@@ -2727,6 +2904,15 @@ const v8::Promise* v8__Promise__Then2(const v8::Promise& self,
       ptr_to_local(&on_rejected)));
 }
 
+const v8::Function* v8__Promise__AggregateErrorFunction(
+    const v8::Context& context) {
+  // The intrinsic %AggregateError%, which scripts cannot replace.
+  i::Handle<i::NativeContext> native_context = v8::Utils::OpenHandle(&context);
+  i::Handle<i::JSFunction> function(native_context->aggregate_error_function(),
+                                    native_context->GetIsolate());
+  return local_to_ptr(v8::Utils::ToLocal(function));
+}
+
 v8::PromiseRejectEvent v8__PromiseRejectMessage__GetEvent(
     const v8::PromiseRejectMessage& self) {
   return self.GetEvent();
@@ -3425,6 +3611,82 @@ V(does_zap_garbage)  // Returns size_t, not bool like you'd expect.
 #undef V
 }  // extern "C"
 
+// v8::debug::ConsoleDelegate
+
+extern "C" {
+void v8__ConsoleDelegate__Call(v8::Local<v8::Context> context, int method,
+                               const v8::Value* const* args, size_t length);
+}
+
+// Forwards the calls of every console method to Rust. It has no state, so a
+// single instance serves all isolates.
+struct v8__ConsoleDelegate : public v8::debug::ConsoleDelegate {
+#define V(NAME, METHOD)                                      \
+  void NAME(const v8::debug::ConsoleCallArguments& args,     \
+            const v8::debug::ConsoleContext&) override {     \
+    Call(METHOD, args);                                      \
+  }
+  V(Debug, 0)
+  V(Error, 1)
+  V(Info, 2)
+  V(Log, 3)
+  V(Warn, 4)
+  V(Dir, 5)
+  V(DirXml, 6)
+  V(Table, 7)
+  V(Trace, 8)
+  V(StartGroup, 9)
+  V(StartGroupCollapsed, 10)
+  V(EndGroup, 11)
+  V(Clear, 12)
+  V(Count, 13)
+  V(CountReset, 14)
+  V(Assert, 15)
+  V(Profile, 16)
+  V(ProfileEnd, 17)
+  V(Time, 18)
+  V(TimeLog, 19)
+  V(TimeEnd, 20)
+  V(TimeStamp, 21)
+#undef V
+
+ private:
+  static void Call(int method, const v8::debug::ConsoleCallArguments& args) {
+    v8::Isolate* isolate = args.GetIsolate();
+    v8::HandleScope handle_scope(isolate);
+    v8::Local<v8::Context> context = isolate->GetCurrentContext();
+    if (context.IsEmpty()) {
+      return;
+    }
+    std::vector<const v8::Value*> values;
+    values.reserve(args.Length());
+    for (int i = 0; i < args.Length(); i++) {
+      values.push_back(local_to_ptr(args[i]));
+    }
+    v8__ConsoleDelegate__Call(context, method, values.data(), values.size());
+  }
+};
+
+static v8__ConsoleDelegate console_delegate;
+
+extern "C" {
+// Whether the isolate's console delegate is ours. A V8Inspector replaces it
+// when it is created, and clears it when it is destroyed.
+bool v8__Isolate__HasConsoleDelegate(const v8::Isolate* isolate) {
+  return reinterpret_cast<v8::internal::Isolate*>(
+             const_cast<v8::Isolate*>(isolate))
+             ->console_delegate() == &console_delegate;
+}
+
+void v8__Isolate__SetConsoleDelegate(v8::Isolate* isolate, bool enabled) {
+  if (enabled) {
+    v8::debug::SetConsoleDelegate(isolate, &console_delegate);
+  } else if (v8__Isolate__HasConsoleDelegate(isolate)) {
+    v8::debug::SetConsoleDelegate(isolate, nullptr);
+  }
+}
+}
+
 // v8::ValueSerializer::Delegate
 
 extern "C" {
@@ -22,6 +22,7 @@ static size_t v8__String__ValueView_SIZE = sizeof(v8::String::ValueView);
 
 static int v8__String__kMaxLength = v8::String::kMaxLength;
 
+static size_t v8__ArrayBuffer__kMaxByteLength = v8::ArrayBuffer::kMaxByteLength;
 static size_t v8__TypedArray__kMaxByteLength = v8::TypedArray::kMaxByteLength;
 
 #define TYPED_ARRAY_MAX_LENGTH(name) \
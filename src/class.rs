@@ -0,0 +1,222 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! JavaScript classes backed by Rust values.
+//!
+//! A type implementing [`Class`] describes a JavaScript class whose instances
+//! each own a value of that type. The implementation is usually generated by
+//! the `#[v8::class]` attribute (enabled by the `macros` feature), but it can
+//! also be written by hand with the functions in this module.
+//!
+//! The value of an instance is owned by the object through a [`Wrapped`],
+//! which can be used to retain the object from Rust. It is dropped after the
+//! object has been garbage collected, or when the isolate is disposed. Objects
+//! that own a value can't be serialized into a snapshot, but
+//! the class itself can; see [`Class::external_references`].
+
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use crate::support::MapFnFrom;
+use crate::support::ToCFn;
+use crate::support::UnitType;
+use crate::CallbackScope;
+use crate::ExternalReference;
+use crate::Function;
+use crate::FunctionCallback;
+use crate::FunctionCallbackArguments;
+use crate::FunctionCallbackInfo;
+use crate::FunctionTemplate;
+use crate::Global;
+use crate::HandleScope;
+use crate::JsError;
+use crate::Local;
+use crate::Object;
+use crate::ReturnValue;
+use crate::String;
+use crate::Value;
+use crate::Wrapped;
+
+/// A Rust type exposed to JavaScript as a class.
+pub trait Class: Sized + 'static {
+  /// The name of the class in JavaScript.
+  const NAME: &'static str;
+
+  /// Creates the function template of the class. Called once per isolate by
+  /// [`Class::template`]; the template must be set up with
+  /// [`init_template`].
+  fn build_template<'s>(
+    scope: &mut HandleScope<'s, ()>,
+  ) -> Local<'s, FunctionTemplate>;
+
+  /// Returns the callbacks used by the class, to be included in the external
+  /// references of isolates that create or load a snapshot containing it.
+  fn external_references() -> Vec<ExternalReference<'static>>;
+
+  /// Returns the function template of the class, creating it the first time
+  /// it is requested in the isolate.
+  fn template<'s>(
+    scope: &mut HandleScope<'s, ()>,
+  ) -> Local<'s, FunctionTemplate> {
+    let type_id = TypeId::of::<Self>();
+    if let Some(template) = scope
+      .get_slot::<ClassTemplates>()
+      .and_then(|templates| templates.0.get(&type_id))
+    {
+      let template = template.clone();
+      return Local::new(scope, template);
+    }
+    let template = Self::build_template(scope);
+    let global = Global::new(scope, template);
+    if scope.get_slot::<ClassTemplates>().is_none() {
+      scope.set_slot(ClassTemplates(HashMap::new()));
+    }
+    let templates = scope.get_slot_mut::<ClassTemplates>().unwrap();
+    templates.0.insert(type_id, global);
+    template
+  }
+
+  /// Returns the constructor of the class in the current context.
+  fn constructor<'s>(
+    scope: &mut HandleScope<'s>,
+  ) -> Option<Local<'s, Function>> {
+    Self::template(scope).get_function(scope)
+  }
+
+  /// Creates an instance of the class that owns `self`, without calling the
+  /// JavaScript constructor.
+  fn into_object<'s>(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Option<Local<'s, Object>> {
+    let object = Self::template(scope)
+      .instance_template(scope)
+      .new_instance(scope)?;
+    wrap(scope, object, self);
+    Some(object)
+  }
+
+  /// Returns the value owned by `object`, or `None` if it is not an instance
+  /// of the class. The value is shared with JavaScript, which may call its
+  /// methods at any time; use interior mutability to modify it.
+  fn from_object<'a>(
+    scope: &mut HandleScope<'_, ()>,
+    object: Local<'a, Object>,
+  ) -> Option<&'a Self> {
+    if !Self::template(scope).has_instance(object.into()) {
+      return None;
+    }
+    Wrapped::<Self>::unwrap(object).map(|wrapped| &**wrapped)
+  }
+}
+
+/// The templates of the classes used in an isolate, stored in an isolate
+/// slot.
+struct ClassTemplates(HashMap<TypeId, Global<FunctionTemplate>>);
+
+/// Prepares the function template of the class `T`: sets its class name and
+/// reserves the internal fields of its instances.
+pub fn init_template<T: Class>(
+  scope: &mut HandleScope<'_, ()>,
+  template: Local<FunctionTemplate>,
+) {
+  let name = String::new(scope, T::NAME).unwrap();
+  template.set_class_name(name);
+  template
+    .instance_template(scope)
+    .set_internal_field_count(Wrapped::<T>::INTERNAL_FIELD_COUNT);
+}
+
+/// Makes `object`, a new instance of the class `T`, own `value`.
+///
+/// # Panics
+///
+/// Panics if `object` is not an instance of the class or already owns a
+/// value.
+pub fn wrap<T: Class>(
+  scope: &mut HandleScope<'_, ()>,
+  object: Local<Object>,
+  value: T,
+) {
+  assert!(T::template(scope).has_instance(object.into()));
+  Wrapped::wrap(scope, object, value);
+}
+
+/// Returns the value owned by the receiver of a method of the class `T`, or
+/// a `TypeError` if the receiver is not an initialized instance.
+pub fn this<'s, T: Class>(
+  scope: &mut HandleScope<'s>,
+  args: &FunctionCallbackArguments<'s>,
+) -> Result<&'s T, JsError> {
+  T::from_object(scope, args.this())
+    .ok_or_else(|| JsError::type_error("Illegal invocation"))
+}
+
+/// Fails with a `TypeError` unless the constructor of the class `T` has been
+/// called with `new`.
+pub fn check_construct_call<T: Class>(
+  args: &FunctionCallbackArguments,
+) -> Result<(), JsError> {
+  if args.new_target().is_undefined() {
+    return Err(JsError::type_error(format!(
+      "Class constructor {} cannot be invoked without 'new'",
+      T::NAME
+    )));
+  }
+  Ok(())
+}
+
+/// Converts argument `index` of a call, naming the argument in errors.
+pub fn argument<'s, A: crate::FromV8<'s>>(
+  scope: &mut HandleScope<'s>,
+  args: &FunctionCallbackArguments<'s>,
+  index: i32,
+) -> Result<A, JsError> {
+  A::from_v8(scope, args.get(index)).map_err(|error| {
+    crate::convert::nested_error(error, format!("argument {}", index + 1))
+  })
+}
+
+/// The result of a constructor: the value itself or a `Result` of it.
+pub trait ConstructorResult<T> {
+  fn into_value(self) -> Result<T, JsError>;
+}
+
+impl<T: Class> ConstructorResult<T> for T {
+  fn into_value(self) -> Result<T, JsError> {
+    Ok(self)
+  }
+}
+
+impl<T: Class> ConstructorResult<T> for Result<T, JsError> {
+  fn into_value(self) -> Result<T, JsError> {
+    self
+  }
+}
+
+/// Selects the conversion of a fallible callback, which returns the result
+/// of the call or the error to throw, into a [`FunctionCallback`].
+#[derive(Debug)]
+pub struct FallibleCallbackTag;
+
+impl<F> MapFnFrom<F, FallibleCallbackTag> for FunctionCallback
+where
+  F: UnitType
+    + for<'s> Fn(
+      &mut HandleScope<'s>,
+      &FunctionCallbackArguments<'s>,
+    ) -> Result<Local<'s, Value>, JsError>,
+{
+  fn mapping() -> Self {
+    let f = |info: *const FunctionCallbackInfo| {
+      let info = unsafe { &*info };
+      let scope = &mut unsafe { CallbackScope::new(info) };
+      let args = FunctionCallbackArguments::from_function_callback_info(info);
+      let mut rv = ReturnValue::from_function_callback_info(info);
+      match (F::get())(scope, &args) {
+        Ok(value) => rv.set(value),
+        Err(error) => error.throw(scope),
+      }
+    };
+    f.to_c_fn()
+  }
+}
@@ -0,0 +1,607 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Conversions between JavaScript values and Rust types.
+//!
+//! [`FromV8`] and [`ToV8`] are implemented for primitives, strings, handles,
+//! `Option`s, `Vec`s, tuples and boxed slices of typed array elements. They
+//! also drive the argument and return value conversion of typed function
+//! callbacks; see [`FunctionBuilder::new_typed`](crate::FunctionBuilder::new_typed).
+
+use std::fmt::Display;
+use std::mem::size_of;
+use std::mem::size_of_val;
+use std::slice;
+
+use crate::scope::CallbackScope;
+use crate::support::MapFnFrom;
+use crate::support::ToCFn;
+use crate::support::UnitType;
+use crate::Array;
+use crate::ArrayBuffer;
+use crate::BigInt;
+use crate::Boolean;
+use crate::FunctionCallback;
+use crate::FunctionCallbackArguments;
+use crate::FunctionCallbackInfo;
+use crate::HandleScope;
+use crate::Integer;
+use crate::JsError;
+use crate::Local;
+use crate::Number;
+use crate::ReturnValue;
+use crate::Value;
+
+/// The largest integer that a JavaScript number represents exactly.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Conversion from a JavaScript value to a Rust value.
+///
+/// Conversions are strict: a value of the wrong type is rejected with a
+/// `TypeError` instead of being coerced, and numbers that don't fit the target
+/// integer type are rejected with a `RangeError`.
+pub trait FromV8<'s>: Sized {
+  fn from_v8(
+    scope: &mut HandleScope<'s>,
+    value: Local<'s, Value>,
+  ) -> Result<Self, JsError>;
+}
+
+/// Conversion from a Rust value to a JavaScript value.
+pub trait ToV8<'s> {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError>;
+}
+
+fn expected(what: &str) -> JsError {
+  JsError::type_error(format!("expected {what}"))
+}
+
+impl<'s> FromV8<'s> for Local<'s, Value> {
+  fn from_v8(
+    _scope: &mut HandleScope<'s>,
+    value: Local<'s, Value>,
+  ) -> Result<Self, JsError> {
+    Ok(value)
+  }
+}
+
+impl<'s> ToV8<'s> for Local<'s, Value> {
+  fn to_v8(self, _scope: &mut HandleScope<'s>) -> Result<Self, JsError> {
+    Ok(self)
+  }
+}
+
+macro_rules! handle_conversion {
+  ($($name:ident: $what:literal),* $(,)?) => {
+    $(
+      impl<'s> FromV8<'s> for Local<'s, crate::$name> {
+        fn from_v8(
+          _scope: &mut HandleScope<'s>,
+          value: Local<'s, Value>,
+        ) -> Result<Self, JsError> {
+          value.try_into().map_err(|_| expected($what))
+        }
+      }
+
+      impl<'s> ToV8<'s> for Local<'s, crate::$name> {
+        fn to_v8(
+          self,
+          _scope: &mut HandleScope<'s>,
+        ) -> Result<Local<'s, Value>, JsError> {
+          Ok(self.into())
+        }
+      }
+    )*
+  };
+}
+
+handle_conversion! {
+  Array: "an array",
+  ArrayBuffer: "an ArrayBuffer",
+  ArrayBufferView: "an ArrayBuffer view",
+  BigInt: "a bigint",
+  Boolean: "a boolean",
+  Function: "a function",
+  Number: "a number",
+  Object: "an object",
+  Promise: "a promise",
+  String: "a string",
+  Symbol: "a symbol",
+  Uint8Array: "a Uint8Array",
+}
+
+impl<'s> FromV8<'s> for () {
+  fn from_v8(
+    _scope: &mut HandleScope<'s>,
+    _value: Local<'s, Value>,
+  ) -> Result<Self, JsError> {
+    Ok(())
+  }
+}
+
+impl<'s> ToV8<'s> for () {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    Ok(crate::undefined(scope).into())
+  }
+}
+
+impl<'s> FromV8<'s> for bool {
+  fn from_v8(
+    _scope: &mut HandleScope<'s>,
+    value: Local<'s, Value>,
+  ) -> Result<Self, JsError> {
+    if value.is_boolean() {
+      Ok(value.is_true())
+    } else {
+      Err(expected("a boolean"))
+    }
+  }
+}
+
+impl<'s> ToV8<'s> for bool {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    Ok(Boolean::new(scope, self).into())
+  }
+}
+
+fn number_value(value: Local<Value>) -> Result<f64, JsError> {
+  Local::<Number>::try_from(value)
+    .map(|number| number.value())
+    .map_err(|_| expected("a number"))
+}
+
+fn integer_value(
+  value: Local<Value>,
+  min: f64,
+  max: f64,
+) -> Result<f64, JsError> {
+  let number = number_value(value)?;
+  if number.fract() == 0.0 && number >= min && number <= max {
+    Ok(number)
+  } else {
+    Err(JsError::range_error(format!(
+      "expected an integer between {min} and {max}"
+    )))
+  }
+}
+
+macro_rules! integer_conversion {
+  ($($ty:ty => $new:ident),* $(,)?) => {
+    $(
+      impl<'s> FromV8<'s> for $ty {
+        fn from_v8(
+          _scope: &mut HandleScope<'s>,
+          value: Local<'s, Value>,
+        ) -> Result<Self, JsError> {
+          integer_value(value, <$ty>::MIN as f64, <$ty>::MAX as f64)
+            .map(|number| number as $ty)
+        }
+      }
+
+      impl<'s> ToV8<'s> for $ty {
+        fn to_v8(
+          self,
+          scope: &mut HandleScope<'s>,
+        ) -> Result<Local<'s, Value>, JsError> {
+          Ok(Integer::$new(scope, self.into()).into())
+        }
+      }
+    )*
+  };
+}
+
+integer_conversion! {
+  i8 => new,
+  i16 => new,
+  i32 => new,
+  u8 => new_from_unsigned,
+  u16 => new_from_unsigned,
+  u32 => new_from_unsigned,
+}
+
+/// 64-bit integers are accepted as safe-integer numbers or as bigints that
+/// fit, and are converted to numbers when that is lossless and to bigints
+/// otherwise.
+impl<'s> FromV8<'s> for i64 {
+  fn from_v8(
+    _scope: &mut HandleScope<'s>,
+    value: Local<'s, Value>,
+  ) -> Result<Self, JsError> {
+    if let Ok(bigint) = Local::<BigInt>::try_from(value) {
+      return match bigint.i64_value() {
+        (value, true) => Ok(value),
+        _ => Err(JsError::range_error("bigint does not fit in 64 bits")),
+      };
+    }
+    let max = MAX_SAFE_INTEGER as f64;
+    integer_value(value, -max, max).map(|number| number as i64)
+  }
+}
+
+impl<'s> ToV8<'s> for i64 {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    if self.unsigned_abs() <= MAX_SAFE_INTEGER {
+      Ok(Number::new(scope, self as f64).into())
+    } else {
+      Ok(BigInt::new_from_i64(scope, self).into())
+    }
+  }
+}
+
+impl<'s> FromV8<'s> for u64 {
+  fn from_v8(
+    _scope: &mut HandleScope<'s>,
+    value: Local<'s, Value>,
+  ) -> Result<Self, JsError> {
+    if let Ok(bigint) = Local::<BigInt>::try_from(value) {
+      return match bigint.u64_value() {
+        (value, true) => Ok(value),
+        _ => Err(JsError::range_error("bigint does not fit in 64 bits")),
+      };
+    }
+    integer_value(value, 0.0, MAX_SAFE_INTEGER as f64)
+      .map(|number| number as u64)
+  }
+}
+
+impl<'s> ToV8<'s> for u64 {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    if self <= MAX_SAFE_INTEGER {
+      Ok(Number::new(scope, self as f64).into())
+    } else {
+      Ok(BigInt::new_from_u64(scope, self).into())
+    }
+  }
+}
+
+macro_rules! float_conversion {
+  ($($ty:ty),*) => {
+    $(
+      impl<'s> FromV8<'s> for $ty {
+        fn from_v8(
+          _scope: &mut HandleScope<'s>,
+          value: Local<'s, Value>,
+        ) -> Result<Self, JsError> {
+          number_value(value).map(|number| number as $ty)
+        }
+      }
+
+      impl<'s> ToV8<'s> for $ty {
+        fn to_v8(
+          self,
+          scope: &mut HandleScope<'s>,
+        ) -> Result<Local<'s, Value>, JsError> {
+          Ok(Number::new(scope, self.into()).into())
+        }
+      }
+    )*
+  };
+}
+
+float_conversion!(f32, f64);
+
+impl<'s> FromV8<'s> for String {
+  fn from_v8(
+    scope: &mut HandleScope<'s>,
+    value: Local<'s, Value>,
+  ) -> Result<Self, JsError> {
+    if value.is_string() {
+      Ok(value.to_rust_string_lossy(scope))
+    } else {
+      Err(expected("a string"))
+    }
+  }
+}
+
+impl<'s> ToV8<'s> for &str {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    crate::String::new(scope, self)
+      .map(Into::into)
+      .ok_or_else(|| JsError::range_error("string is too long"))
+  }
+}
+
+impl<'s> ToV8<'s> for String {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    self.as_str().to_v8(scope)
+  }
+}
+
+/// `undefined` and `null` convert to `None`, and `None` converts to `null`.
+impl<'s, T: FromV8<'s>> FromV8<'s> for Option<T> {
+  fn from_v8(
+    scope: &mut HandleScope<'s>,
+    value: Local<'s, Value>,
+  ) -> Result<Self, JsError> {
+    if value.is_null_or_undefined() {
+      Ok(None)
+    } else {
+      T::from_v8(scope, value).map(Some)
+    }
+  }
+}
+
+impl<'s, T: ToV8<'s>> ToV8<'s> for Option<T> {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    match self {
+      Some(value) => value.to_v8(scope),
+      None => Ok(crate::null(scope).into()),
+    }
+  }
+}
+
+/// Prefixes the message of a conversion error with the position of the value
+/// that failed to convert, e.g. "element 2: expected a string".
+pub(crate) fn nested_error(mut error: JsError, what: impl Display) -> JsError {
+  if let Some(message) = &mut error.message {
+    *message = format!("{what}: {message}");
+    let name = error.name.as_deref().unwrap_or("Error");
+    error.exception_message = format!("{name}: {message}");
+  }
+  error
+}
+
+fn array_elements<'s>(
+  scope: &mut HandleScope<'s>,
+  value: Local<'s, Value>,
+) -> Result<Vec<Local<'s, Value>>, JsError> {
+  let array =
+    Local::<Array>::try_from(value).map_err(|_| expected("an array"))?;
+  (0..array.length())
+    .map(|index| {
+      array.get_index(scope, index).ok_or_else(|| {
+        nested_error(
+          JsError::error("could not be read"),
+          format!("element {index}"),
+        )
+      })
+    })
+    .collect()
+}
+
+/// Arrays convert element by element.
+impl<'s, T: FromV8<'s>> FromV8<'s> for Vec<T> {
+  fn from_v8(
+    scope: &mut HandleScope<'s>,
+    value: Local<'s, Value>,
+  ) -> Result<Self, JsError> {
+    array_elements(scope, value)?
+      .into_iter()
+      .enumerate()
+      .map(|(index, element)| {
+        T::from_v8(scope, element)
+          .map_err(|error| nested_error(error, format!("element {index}")))
+      })
+      .collect()
+  }
+}
+
+impl<'s, T: ToV8<'s>> ToV8<'s> for Vec<T> {
+  fn to_v8(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    let elements = self
+      .into_iter()
+      .map(|element| element.to_v8(scope))
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Array::new_with_elements(scope, &elements).into())
+  }
+}
+
+/// Tuples convert to and from arrays of the same length.
+macro_rules! tuple_conversion {
+  ($len:literal; $($name:ident $index:tt),*) => {
+    impl<'s, $($name: FromV8<'s>),*> FromV8<'s> for ($($name,)*) {
+      fn from_v8(
+        scope: &mut HandleScope<'s>,
+        value: Local<'s, Value>,
+      ) -> Result<Self, JsError> {
+        let elements = array_elements(scope, value)?;
+        if elements.len() != $len {
+          return Err(expected(concat!("an array of length ", $len)));
+        }
+        Ok(($(
+          $name::from_v8(scope, elements[$index]).map_err(|error| {
+            nested_error(error, concat!("element ", $index))
+          })?,
+        )*))
+      }
+    }
+
+    impl<'s, $($name: ToV8<'s>),*> ToV8<'s> for ($($name,)*) {
+      fn to_v8(
+        self,
+        scope: &mut HandleScope<'s>,
+      ) -> Result<Local<'s, Value>, JsError> {
+        let elements = [$(self.$index.to_v8(scope)?),*];
+        Ok(Array::new_with_elements(scope, &elements).into())
+      }
+    }
+  };
+}
+
+tuple_conversion!(1; A 0);
+tuple_conversion!(2; A 0, B 1);
+tuple_conversion!(3; A 0, B 1, C 2);
+tuple_conversion!(4; A 0, B 1, C 2, D 3);
+tuple_conversion!(5; A 0, B 1, C 2, D 3, E 4);
+tuple_conversion!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// Boxed slices convert to and from typed arrays of the matching element
+/// type. The contents are copied.
+macro_rules! typed_array_conversion {
+  ($($element:ty => $name:ident),* $(,)?) => {
+    $(
+      impl<'s> FromV8<'s> for Box<[$element]> {
+        fn from_v8(
+          _scope: &mut HandleScope<'s>,
+          value: Local<'s, Value>,
+        ) -> Result<Self, JsError> {
+          let array = Local::<crate::$name>::try_from(value)
+            .map_err(|_| expected(concat!("a ", stringify!($name))))?;
+          let mut elements =
+            vec![<$element>::default(); array.byte_length() / size_of::<$element>()];
+          // SAFETY: Every bit pattern is a valid element.
+          let bytes = unsafe {
+            slice::from_raw_parts_mut(
+              elements.as_mut_ptr() as *mut u8,
+              size_of_val(&elements[..]),
+            )
+          };
+          array.copy_contents(bytes);
+          Ok(elements.into_boxed_slice())
+        }
+      }
+
+      impl<'s> ToV8<'s> for Box<[$element]> {
+        fn to_v8(
+          self,
+          scope: &mut HandleScope<'s>,
+        ) -> Result<Local<'s, Value>, JsError> {
+          let length = self.len();
+          if length > crate::$name::MAX_LENGTH {
+            return Err(JsError::range_error("typed array is too long"));
+          }
+          // The backing store is allocated by V8, which aligns it for any
+          // element type, unlike a `Vec<u8>`.
+          let byte_length = size_of_val(&self[..]);
+          let backing_store =
+            ArrayBuffer::new_backing_store(scope, byte_length);
+          match backing_store.data() {
+            // SAFETY: The backing store is new and has room for every
+            // element.
+            Some(data) => unsafe {
+              std::ptr::copy_nonoverlapping(
+                self.as_ptr(),
+                data.as_ptr() as *mut $element,
+                length,
+              )
+            },
+            None if byte_length == 0 => {}
+            None => {
+              return Err(JsError::range_error("Array buffer allocation failed"))
+            }
+          }
+          let backing_store = backing_store.make_shared();
+          let buffer = ArrayBuffer::with_backing_store(scope, &backing_store);
+          crate::$name::new(scope, buffer, 0, length)
+            .map(Into::into)
+            .ok_or_else(|| JsError::range_error("typed array is too long"))
+        }
+      }
+    )*
+  };
+}
+
+typed_array_conversion! {
+  u8 => Uint8Array,
+  i8 => Int8Array,
+  u16 => Uint16Array,
+  i16 => Int16Array,
+  u32 => Uint32Array,
+  i32 => Int32Array,
+  f32 => Float32Array,
+  f64 => Float64Array,
+  u64 => BigUint64Array,
+  i64 => BigInt64Array,
+}
+
+/// The return type of a typed function callback: a value that converts with
+/// [`ToV8`], or a `Result` whose error is thrown as an exception.
+pub trait TypedFunctionResult<'s> {
+  fn into_result(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError>;
+}
+
+impl<'s, T: ToV8<'s>> TypedFunctionResult<'s> for T {
+  fn into_result(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    self.to_v8(scope)
+  }
+}
+
+impl<'s, T: ToV8<'s>> TypedFunctionResult<'s> for Result<T, JsError> {
+  fn into_result(
+    self,
+    scope: &mut HandleScope<'s>,
+  ) -> Result<Local<'s, Value>, JsError> {
+    self?.to_v8(scope)
+  }
+}
+
+/// Selects the conversion of a typed function with the argument types `Args`
+/// into a [`FunctionCallback`].
+#[derive(Debug)]
+pub struct TypedFunctionTag<Args>(std::marker::PhantomData<Args>);
+
+macro_rules! typed_function {
+  ($($arg:ident $value:ident $index:tt),*) => {
+    impl<F, R, $($arg),*> MapFnFrom<F, TypedFunctionTag<($($arg,)*)>>
+      for FunctionCallback
+    where
+      F: UnitType + for<'s> Fn(&mut HandleScope<'s>, $($arg),*) -> R,
+      R: for<'s> TypedFunctionResult<'s>,
+      $($arg: for<'s> FromV8<'s>,)*
+    {
+      fn mapping() -> Self {
+        let f = |info: *const FunctionCallbackInfo| {
+          let info = unsafe { &*info };
+          let scope = &mut unsafe { CallbackScope::new(info) };
+          #[allow(unused_variables)]
+          let args = FunctionCallbackArguments::from_function_callback_info(info);
+          let mut rv = ReturnValue::from_function_callback_info(info);
+          $(
+            let $value = match $arg::from_v8(scope, args.get($index)) {
+              Ok(value) => value,
+              Err(error) => {
+                let what = format!("argument {}", $index + 1);
+                return nested_error(error, what).throw(scope);
+              }
+            };
+          )*
+          match (F::get())(scope, $($value),*).into_result(scope) {
+            Ok(value) => rv.set(value),
+            Err(error) => error.throw(scope),
+          }
+        };
+        f.to_c_fn()
+      }
+    }
+  };
+}
+
+typed_function!();
+typed_function!(A a 0);
+typed_function!(A a 0, B b 1);
+typed_function!(A a 0, B b 1, C c 2);
+typed_function!(A a 0, B b 1, C c 2, D d 3);
+typed_function!(A a 0, B b 1, C c 2, D d 3, E e 4);
+typed_function!(A a 0, B b 1, C c 2, D d 3, E e 4, G g 5);
@@ -1,10 +1,39 @@
 use crate::binding::*;
+use crate::support::UnitType;
+use crate::Array;
+use crate::ArrayBuffer;
+use crate::CallbackScope;
 use crate::Isolate;
+use crate::JsError;
 use crate::Local;
+use crate::Object;
 use crate::Value;
 use std::ffi::c_void;
 use std::marker::PhantomData;
 
+extern "C" {
+  fn v8__Array__TryToCopyAndConvertToCppBuffer__i32(
+    src: *const Array,
+    dst: *mut i32,
+    max_length: u32,
+  ) -> bool;
+  fn v8__Array__TryToCopyAndConvertToCppBuffer__u32(
+    src: *const Array,
+    dst: *mut u32,
+    max_length: u32,
+  ) -> bool;
+  fn v8__Array__TryToCopyAndConvertToCppBuffer__f32(
+    src: *const Array,
+    dst: *mut f32,
+    max_length: u32,
+  ) -> bool;
+  fn v8__Array__TryToCopyAndConvertToCppBuffer__f64(
+    src: *const Array,
+    dst: *mut f64,
+    max_length: u32,
+  ) -> bool;
+}
+
 #[derive(Clone, Copy)]
 #[repr(transparent)]
 pub struct CFunction(v8__CFunction);
@@ -26,6 +55,21 @@ impl CFunction {
     // the layout of CFunctionInfo is identical to v8_CFunctionInfo.
     unsafe { &*(self.0.type_info_ as *const CFunctionInfo) }
   }
+
+  /// Returns the number of JavaScript arguments the function takes, not
+  /// counting the receiver and the options. V8 only considers overloads whose
+  /// arity matches the number of arguments of a call.
+  pub fn arity(&self) -> usize {
+    let info = self.type_info();
+    let count = info.argument_count();
+    if count > 0
+      && info.argument_info(count - 1).r#type() == Type::CallbackOptions
+    {
+      count.saturating_sub(2)
+    } else {
+      count.saturating_sub(1)
+    }
+  }
 }
 
 #[repr(transparent)]
@@ -49,6 +93,25 @@ impl CFunctionInfo {
       return_info_: return_info.0,
     })
   }
+
+  /// Returns the type information of the return value.
+  pub const fn return_info(&self) -> CTypeInfo {
+    CTypeInfo(self.0.return_info_)
+  }
+
+  /// Returns the number of arguments, including the receiver and the
+  /// options.
+  pub const fn argument_count(&self) -> usize {
+    self.0.arg_count_ as usize
+  }
+
+  /// Returns the type information of argument `index`, where the receiver is
+  /// argument 0.
+  pub fn argument_info(&self, index: usize) -> CTypeInfo {
+    assert!(index < self.argument_count());
+    // SAFETY: `arg_info_` points to `arg_count_` elements.
+    CTypeInfo(unsafe { *self.0.arg_info_.add(index) })
+  }
 }
 
 #[derive(Clone, Copy)]
@@ -76,9 +139,44 @@ impl CTypeInfo {
       type_: r#type as _,
     })
   }
+
+  #[allow(non_upper_case_globals)]
+  pub const fn r#type(&self) -> Type {
+    match self.0.type_ {
+      v8_CTypeInfo_Type_kVoid => Type::Void,
+      v8_CTypeInfo_Type_kBool => Type::Bool,
+      v8_CTypeInfo_Type_kUint8 => Type::Uint8,
+      v8_CTypeInfo_Type_kInt32 => Type::Int32,
+      v8_CTypeInfo_Type_kUint32 => Type::Uint32,
+      v8_CTypeInfo_Type_kInt64 => Type::Int64,
+      v8_CTypeInfo_Type_kUint64 => Type::Uint64,
+      v8_CTypeInfo_Type_kFloat32 => Type::Float32,
+      v8_CTypeInfo_Type_kFloat64 => Type::Float64,
+      v8_CTypeInfo_Type_kPointer => Type::Pointer,
+      v8_CTypeInfo_Type_kV8Value => Type::V8Value,
+      v8_CTypeInfo_Type_kSeqOneByteString => Type::SeqOneByteString,
+      v8_CTypeInfo_Type_kApiObject => Type::ApiObject,
+      v8_CTypeInfo_Type_kAny => Type::Any,
+      _ => Type::CallbackOptions,
+    }
+  }
+
+  #[allow(non_upper_case_globals)]
+  pub const fn sequence_type(&self) -> SequenceType {
+    match self.0.sequence_type_ {
+      v8_CTypeInfo_SequenceType_kScalar => SequenceType::Scalar,
+      v8_CTypeInfo_SequenceType_kIsSequence => SequenceType::IsSequence,
+      v8_CTypeInfo_SequenceType_kIsTypedArray => SequenceType::IsTypedArray,
+      _ => SequenceType::IsArrayBuffer,
+    }
+  }
+
+  pub const fn flags(&self) -> Flags {
+    Flags::from_bits_truncate(self.0.flags_)
+  }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Type {
   Void = v8_CTypeInfo_Type_kVoid,
@@ -106,9 +204,13 @@ impl Type {
   pub const fn typed_array(self) -> CTypeInfo {
     CTypeInfo::new(self, SequenceType::IsTypedArray, Flags::empty())
   }
+
+  pub const fn sequence(self) -> CTypeInfo {
+    CTypeInfo::new(self, SequenceType::IsSequence, Flags::empty())
+  }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum SequenceType {
   Scalar = v8_CTypeInfo_SequenceType_kScalar,
@@ -116,7 +218,8 @@ pub enum SequenceType {
   IsSequence = v8_CTypeInfo_SequenceType_kIsSequence,
   /// TypedArray of T or any ArrayBufferView if T is void
   IsTypedArray = v8_CTypeInfo_SequenceType_kIsTypedArray,
-  /// ArrayBuffer
+  /// ArrayBuffer. Not supported by V8's optimizing compilers yet; see
+  /// [`FastApiArrayBuffer`].
   IsArrayBuffer = v8_CTypeInfo_SequenceType_kIsArrayBuffer,
 }
 
@@ -137,6 +240,11 @@ bitflags::bitflags! {
 /// ```c
 /// void FastMethodWithOptions(int param, FastApiCallbackOptions& options);
 /// ```
+///
+/// V8 no longer lets a fast call fall back to the slow callback once it has
+/// started. A fast call that rejects its arguments throws an exception with
+/// [`FastApiCallbackOptions::throw`] instead, or returns `Err` from a
+/// function whose [`FastResult`] is a `Result`.
 #[repr(C)]
 pub struct FastApiCallbackOptions<'a> {
   pub isolate: *mut Isolate,
@@ -144,6 +252,27 @@ pub struct FastApiCallbackOptions<'a> {
   pub data: Local<'a, Value>,
 }
 
+impl<'a> FastApiCallbackOptions<'a> {
+  /// Returns the isolate in which the fast call runs.
+  pub fn isolate(&mut self) -> &mut Isolate {
+    // SAFETY: V8 passes the isolate that is calling the function.
+    unsafe { &mut *self.isolate }
+  }
+
+  /// Returns the `data` passed to the FunctionTemplate constructor, or
+  /// `undefined`.
+  pub fn data(&self) -> Local<'a, Value> {
+    self.data
+  }
+
+  /// Throws `error` as an exception from the fast call. The fast function
+  /// should return right away; its return value is ignored.
+  pub fn throw(&mut self, error: JsError) {
+    let scope = &mut unsafe { CallbackScope::new(&*self) };
+    error.throw(scope);
+  }
+}
+
 #[allow(unused)] // only constructed by V8
 #[repr(transparent)]
 pub struct FastApiTypedArray<T: Default>(v8__FastApiTypedArray, PhantomData<T>);
@@ -203,3 +332,451 @@ impl FastApiOneByteString {
     unsafe { std::slice::from_raw_parts(self.data as _, self.length as usize) }
   }
 }
+
+/// An argument type of a fast function, whose type information is derived by
+/// [`FastFunction`].
+///
+/// # Safety
+///
+/// `TYPE_INFO` must describe `Raw`, the type in which V8 passes the argument,
+/// and `from_raw` must be sound for every value V8 passes.
+pub unsafe trait FastArg: Sized {
+  const TYPE_INFO: CTypeInfo;
+  /// The type in which V8 passes the argument.
+  type Raw;
+  /// The argument type of an equivalent typed slow callback; see
+  /// [`FunctionTemplate::new_fast`](crate::FunctionTemplate::new_fast).
+  type Slow;
+
+  unsafe fn from_raw(raw: Self::Raw) -> Self;
+}
+
+/// A return type of a fast function.
+///
+/// # Safety
+///
+/// `TYPE_INFO` must describe the type.
+pub unsafe trait FastReturn {
+  const TYPE_INFO: CTypeInfo;
+}
+
+macro_rules! fast_scalar {
+  ($($ty:ty => $type:ident),* $(,)?) => {
+    $(
+      unsafe impl FastArg for $ty {
+        const TYPE_INFO: CTypeInfo = Type::$type.scalar();
+        type Raw = Self;
+        type Slow = Self;
+
+        #[inline(always)]
+        unsafe fn from_raw(raw: Self) -> Self {
+          raw
+        }
+      }
+
+      unsafe impl FastReturn for $ty {
+        const TYPE_INFO: CTypeInfo = Type::$type.scalar();
+      }
+    )*
+  };
+}
+
+fast_scalar! {
+  bool => Bool,
+  i32 => Int32,
+  u32 => Uint32,
+  i64 => Int64,
+  u64 => Uint64,
+  f32 => Float32,
+  f64 => Float64,
+}
+
+unsafe impl FastReturn for () {
+  const TYPE_INFO: CTypeInfo = Type::Void.scalar();
+}
+
+/// Any value. Fast calls with such an argument have no typed slow callback
+/// equivalent.
+unsafe impl<'a> FastArg for Local<'a, Value> {
+  const TYPE_INFO: CTypeInfo = Type::V8Value.scalar();
+  type Raw = Self;
+  type Slow = Self;
+
+  #[inline(always)]
+  unsafe fn from_raw(raw: Self) -> Self {
+    raw
+  }
+}
+
+/// A sequential one-byte string. Other strings take the slow path.
+unsafe impl FastArg for &FastApiOneByteString {
+  const TYPE_INFO: CTypeInfo = Type::SeqOneByteString.scalar();
+  type Raw = *const FastApiOneByteString;
+  type Slow = std::string::String;
+
+  #[inline(always)]
+  unsafe fn from_raw(raw: Self::Raw) -> Self {
+    &*raw
+  }
+}
+
+/// An element type of typed arrays passed to fast functions.
+pub trait FastTypedArrayElement: Default {
+  const TYPE: Type;
+}
+
+macro_rules! fast_typed_array {
+  ($($ty:ty => $type:ident),* $(,)?) => {
+    $(
+      impl FastTypedArrayElement for $ty {
+        const TYPE: Type = Type::$type;
+      }
+    )*
+  };
+}
+
+fast_typed_array! {
+  u8 => Uint8,
+  i32 => Int32,
+  u32 => Uint32,
+  i64 => Int64,
+  u64 => Uint64,
+  f32 => Float32,
+  f64 => Float64,
+}
+
+/// A typed array with elements of type `T`.
+unsafe impl<T: FastTypedArrayElement> FastArg for &FastApiTypedArray<T> {
+  const TYPE_INFO: CTypeInfo = T::TYPE.typed_array();
+  type Raw = *const FastApiTypedArray<T>;
+  type Slow = Box<[T]>;
+
+  #[inline(always)]
+  unsafe fn from_raw(raw: Self::Raw) -> Self {
+    &*raw
+  }
+}
+
+/// An element type of sequences passed to fast functions.
+pub trait FastSequenceElement: Copy + Default {
+  #[doc(hidden)]
+  unsafe fn copy_from_array(
+    array: &Array,
+    dst: *mut Self,
+    max_length: u32,
+  ) -> bool;
+}
+
+macro_rules! fast_sequence {
+  ($($ty:ty => $copy:ident),* $(,)?) => {
+    $(
+      impl FastSequenceElement for $ty {
+        #[inline(always)]
+        unsafe fn copy_from_array(
+          array: &Array,
+          dst: *mut Self,
+          max_length: u32,
+        ) -> bool {
+          $copy(array, dst, max_length)
+        }
+      }
+    )*
+  };
+}
+
+fast_sequence! {
+  i32 => v8__Array__TryToCopyAndConvertToCppBuffer__i32,
+  u32 => v8__Array__TryToCopyAndConvertToCppBuffer__u32,
+  f32 => v8__Array__TryToCopyAndConvertToCppBuffer__f32,
+  f64 => v8__Array__TryToCopyAndConvertToCppBuffer__f64,
+}
+
+/// A JavaScript array passed to a fast function, whose elements are read as
+/// `T`. V8 only checks that the argument is an array; its elements are
+/// converted by [`FastApiSequence::copy_to`] and [`FastApiSequence::to_vec`],
+/// without calling into JavaScript.
+#[repr(transparent)]
+pub struct FastApiSequence<'a, T: FastSequenceElement> {
+  array: Local<'a, Array>,
+  _element: PhantomData<T>,
+}
+
+impl<'a, T: FastSequenceElement> FastApiSequence<'a, T> {
+  /// Returns the array.
+  pub fn as_array(&self) -> Local<'a, Array> {
+    self.array
+  }
+
+  /// Returns the length of the array.
+  pub fn length(&self) -> u32 {
+    self.array.length()
+  }
+
+  /// Copies the elements of the array to the start of `dst`. Returns `false`,
+  /// leaving `dst` in an unspecified state, if the array is longer than `dst`
+  /// or has an element that is not a number.
+  pub fn copy_to(&self, dst: &mut [T]) -> bool {
+    let max_length = dst.len().min(u32::MAX as usize) as u32;
+    unsafe { T::copy_from_array(&self.array, dst.as_mut_ptr(), max_length) }
+  }
+
+  /// Returns the elements of the array, or `None` if an element is not a
+  /// number.
+  pub fn to_vec(&self) -> Option<Vec<T>> {
+    let mut elements = vec![T::default(); self.length() as usize];
+    self.copy_to(&mut elements).then_some(elements)
+  }
+}
+
+/// A sequence with elements of type `T`. An overload taking a sequence can
+/// share its arity with one taking a typed array at the same position.
+unsafe impl<'a, T: FastSequenceElement> FastArg for FastApiSequence<'a, T> {
+  const TYPE_INFO: CTypeInfo = Type::Void.sequence();
+  type Raw = Local<'a, Array>;
+  type Slow = Vec<T>;
+
+  #[inline(always)]
+  unsafe fn from_raw(raw: Self::Raw) -> Self {
+    Self {
+      array: raw,
+      _element: PhantomData,
+    }
+  }
+}
+
+/// An `ArrayBuffer` passed to a fast function.
+///
+/// V8's optimizing compilers don't support [`SequenceType::IsArrayBuffer`]
+/// arguments yet, so the argument is declared as a [`Type::V8Value`] and V8
+/// calls the fast function with any value; [`FastApiArrayBuffer::get`] checks
+/// it. Fast functions with such an argument have no typed slow callback
+/// equivalent.
+#[repr(transparent)]
+pub struct FastApiArrayBuffer<'a>(Local<'a, Value>);
+
+impl<'a> FastApiArrayBuffer<'a> {
+  /// Returns the buffer, or `None` if the argument is not an `ArrayBuffer`.
+  pub fn get(&self) -> Option<Local<'a, ArrayBuffer>> {
+    self.0.try_into().ok()
+  }
+
+  /// Returns the argument.
+  pub fn as_value(&self) -> Local<'a, Value> {
+    self.0
+  }
+}
+
+unsafe impl<'a> FastArg for FastApiArrayBuffer<'a> {
+  const TYPE_INFO: CTypeInfo = Type::V8Value.scalar();
+  type Raw = Local<'a, Value>;
+  type Slow = Local<'a, ArrayBuffer>;
+
+  #[inline(always)]
+  unsafe fn from_raw(raw: Self::Raw) -> Self {
+    Self(raw)
+  }
+}
+
+/// Checks that V8 can tell apart the overloads of a fast function.
+///
+/// V8 picks the overload whose [arity](CFunction::arity) matches the number
+/// of arguments at the call site. Two overloads may have the same arity only
+/// if they differ in exactly one argument, which is a sequence in one and a
+/// typed array in the other; V8 then picks one by the type of that argument.
+/// Calls matching no overload take the slow path.
+pub fn check_overloads(overloads: &[CFunction]) -> Result<(), &'static str> {
+  for (i, a) in overloads.iter().enumerate() {
+    for b in &overloads[i + 1..] {
+      let arity = a.arity();
+      if b.arity() != arity {
+        continue;
+      }
+      if overloads.iter().filter(|c| c.arity() == arity).count() > 2 {
+        return Err("more than two overloads have the same arity");
+      }
+      let (a, b) = (a.type_info(), b.type_info());
+      let differences = (1..=arity)
+        .filter(|&index| {
+          let (a, b) = (a.argument_info(index), b.argument_info(index));
+          (a.r#type(), a.sequence_type()) != (b.r#type(), b.sequence_type())
+        })
+        .collect::<Vec<_>>();
+      let [index] = differences[..] else {
+        return Err(
+          "overloads with the same arity must differ in one argument",
+        );
+      };
+      let mut sequence_types = [
+        a.argument_info(index).sequence_type(),
+        b.argument_info(index).sequence_type(),
+      ];
+      sequence_types.sort_by_key(|sequence_type| *sequence_type as u8);
+      if sequence_types
+        != [SequenceType::IsSequence, SequenceType::IsTypedArray]
+      {
+        return Err(
+          "overloads with the same arity must take a sequence and a typed array",
+        );
+      }
+    }
+  }
+  Ok(())
+}
+
+/// The result of a fast function that takes [`FastApiCallbackOptions`]: a
+/// [`FastReturn`] value, or a `Result` of one whose error is thrown as an
+/// exception.
+pub trait FastResult {
+  type Ok: FastReturn + Default;
+
+  fn into_result(self) -> Result<Self::Ok, JsError>;
+}
+
+impl<R: FastReturn + Default> FastResult for R {
+  type Ok = R;
+
+  #[inline(always)]
+  fn into_result(self) -> Result<R, JsError> {
+    Ok(self)
+  }
+}
+
+impl<R: FastReturn + Default> FastResult for Result<R, JsError> {
+  type Ok = R;
+
+  #[inline(always)]
+  fn into_result(self) -> Result<R, JsError> {
+    self
+  }
+}
+
+/// The arguments of a fast function, as a tuple.
+pub trait FastArgs {
+  /// The arguments of an equivalent typed slow callback.
+  type Slow;
+}
+
+/// Marks the arguments of a fast function that takes
+/// [`FastApiCallbackOptions`] after `Args`.
+#[derive(Debug)]
+pub struct WithOptions<Args>(PhantomData<Args>);
+
+impl<Args: FastArgs> FastArgs for WithOptions<Args> {
+  type Slow = Args::Slow;
+}
+
+/// A Rust function that can be called by V8 as a fast API function.
+///
+/// It is implemented for functions taking the receiver as a
+/// `Local<Object>`, followed by up to 6 [`FastArg`]s, and optionally a
+/// `&mut FastApiCallbackOptions`, and returning a [`FastReturn`], or a
+/// [`FastResult`] if it takes options. `Args` is the tuple of the argument
+/// types, wrapped in [`WithOptions`] if the function takes options. The
+/// function is called through a generated `extern "C"` trampoline, whose
+/// type information is derived from the signature.
+pub trait FastFunction<Args>: UnitType {
+  /// The type information of the function.
+  const INFO: &'static CFunctionInfo;
+
+  /// Returns the address of the function to be called by V8.
+  fn address() -> *const c_void;
+}
+
+macro_rules! fast_function {
+  ($($arg:ident $raw:ident),*) => {
+    impl<$($arg: FastArg),*> FastArgs for ($($arg,)*) {
+      type Slow = ($($arg::Slow,)*);
+    }
+
+    impl<F, R, $($arg),*> FastFunction<($($arg,)*)> for F
+    where
+      F: UnitType + Fn(Local<Object>, $($arg),*) -> R,
+      R: FastReturn,
+      $($arg: FastArg,)*
+    {
+      const INFO: &'static CFunctionInfo = &CFunctionInfo::new(
+        R::TYPE_INFO,
+        &[Type::V8Value.scalar(), $($arg::TYPE_INFO),*],
+        Int64Representation::Number,
+      );
+
+      fn address() -> *const c_void {
+        extern "C" fn trampoline<F, R, $($arg),*>(
+          receiver: Local<Object>,
+          $($raw: $arg::Raw),*
+        ) -> R
+        where
+          F: UnitType + Fn(Local<Object>, $($arg),*) -> R,
+          $($arg: FastArg,)*
+        {
+          (F::get())(receiver, $(unsafe { $arg::from_raw($raw) }),*)
+        }
+        trampoline::<F, R, $($arg),*> as *const c_void
+      }
+    }
+
+    impl<F, R, $($arg),*> FastFunction<WithOptions<($($arg,)*)>> for F
+    where
+      F: UnitType
+        + Fn(Local<Object>, $($arg,)* &mut FastApiCallbackOptions) -> R,
+      R: FastResult,
+      $($arg: FastArg,)*
+    {
+      const INFO: &'static CFunctionInfo = &CFunctionInfo::new(
+        <R::Ok as FastReturn>::TYPE_INFO,
+        &[
+          Type::V8Value.scalar(),
+          $($arg::TYPE_INFO,)*
+          Type::CallbackOptions.scalar(),
+        ],
+        Int64Representation::Number,
+      );
+
+      fn address() -> *const c_void {
+        extern "C" fn trampoline<F, R, $($arg),*>(
+          receiver: Local<Object>,
+          $($raw: $arg::Raw,)*
+          options: *mut FastApiCallbackOptions,
+        ) -> R::Ok
+        where
+          F: UnitType
+            + Fn(Local<Object>, $($arg,)* &mut FastApiCallbackOptions) -> R,
+          R: FastResult,
+          $($arg: FastArg,)*
+        {
+          let options = unsafe { &mut *options };
+          let result = (F::get())(
+            receiver,
+            $(unsafe { $arg::from_raw($raw) },)*
+            options,
+          );
+          match result.into_result() {
+            Ok(value) => value,
+            Err(error) => {
+              options.throw(error);
+              Default::default()
+            }
+          }
+        }
+        trampoline::<F, R, $($arg),*> as *const c_void
+      }
+    }
+  };
+}
+
+fast_function!();
+fast_function!(A a);
+fast_function!(A a, B b);
+fast_function!(A a, B b, C c);
+fast_function!(A a, B b, C c, D d);
+fast_function!(A a, B b, C c, D d, E e);
+fast_function!(A a, B b, C c, D d, E e, G g);
+
+impl CFunction {
+  /// Creates a fast function from a Rust function, deriving its type
+  /// information from the signature. See [`FastFunction`].
+  #[inline(always)]
+  pub fn from_fn<Args, F: FastFunction<Args>>(_function: F) -> Self {
+    Self::new(F::address(), F::INFO)
+  }
+}
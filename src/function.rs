@@ -1,4 +1,5 @@
 use std::convert::TryFrom;
+use std::ffi::c_void;
 use std::marker::PhantomData;
 use std::ptr::null;
 use std::ptr::NonNull;
@@ -14,6 +15,7 @@ use crate::template::Intercepted;
 use crate::Array;
 use crate::Boolean;
 use crate::Context;
+use crate::External;
 use crate::Function;
 use crate::HandleScope;
 use crate::Integer;
@@ -24,8 +26,10 @@ use crate::Object;
 use crate::PropertyDescriptor;
 use crate::Signature;
 use crate::String;
+use crate::TypedFunctionTag;
 use crate::UniqueRef;
 use crate::Value;
+use crate::Weak;
 use crate::{undefined, ScriptOrigin};
 
 extern "C" {
@@ -896,6 +900,107 @@ where
   }
 }
 
+/// Heap-allocated state of a closure-backed callback. See [`closure_data`].
+struct ClosureState<C> {
+  closure: C,
+  // Keeps the finalizer that frees this state installed.
+  _weak: Option<Weak<External>>,
+}
+
+/// Moves `closure` to the heap and returns an `External` pointing to it, to be
+/// used as the data of a function or accessor. The closure is dropped by a
+/// weak finalizer once the `External`, and with it every function or accessor
+/// that refers to it, has been garbage collected.
+pub(crate) fn closure_data<'s, C: 'static>(
+  scope: &mut HandleScope<'s, ()>,
+  closure: C,
+) -> Local<'s, External> {
+  let state = Box::into_raw(Box::new(ClosureState {
+    closure,
+    _weak: None,
+  }));
+  let data = External::new(scope, state as *mut c_void);
+  let weak = Weak::with_guaranteed_finalizer(
+    scope,
+    data,
+    // SAFETY: The state is freed exactly once, after `data` is gone.
+    Box::new(move || drop(unsafe { Box::from_raw(state) })),
+  );
+  unsafe { (*state)._weak = Some(weak) };
+  data
+}
+
+/// Returns the closure stored by [`closure_data`].
+///
+/// SAFETY: `data` must have been created by `closure_data::<C>`.
+pub(crate) unsafe fn closure_from_data<'a, C>(data: Local<'a, Value>) -> &'a C {
+  let data = Local::<External>::cast_unchecked(data);
+  &(*(data.value() as *const ClosureState<C>)).closure
+}
+
+pub(crate) fn closure_function_callback<'s, C>(
+  scope: &mut HandleScope<'s>,
+  args: FunctionCallbackArguments<'s>,
+  rv: ReturnValue<Value>,
+) where
+  C: for<'a> Fn(
+    &mut HandleScope<'a>,
+    FunctionCallbackArguments<'a>,
+    ReturnValue<Value>,
+  ),
+{
+  // SAFETY: This callback is only installed together with its closure data.
+  let closure = unsafe { closure_from_data::<C>(args.data()) };
+  closure(scope, args, rv)
+}
+
+/// The closures of a closure-backed accessor. Accessors without a setter use
+/// `()` as the setter type.
+pub(crate) struct AccessorClosures<G, S> {
+  pub(crate) getter: G,
+  pub(crate) setter: S,
+}
+
+pub(crate) fn closure_accessor_getter<'s, G, S>(
+  scope: &mut HandleScope<'s>,
+  key: Local<'s, Name>,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Value>,
+) where
+  G: for<'a> Fn(
+    &mut HandleScope<'a>,
+    Local<'a, Name>,
+    PropertyCallbackArguments<'a>,
+    ReturnValue<Value>,
+  ),
+{
+  // SAFETY: This callback is only installed together with its closure data.
+  let closures =
+    unsafe { closure_from_data::<AccessorClosures<G, S>>(args.data()) };
+  (closures.getter)(scope, key, args, rv)
+}
+
+pub(crate) fn closure_accessor_setter<'s, G, S>(
+  scope: &mut HandleScope<'s>,
+  key: Local<'s, Name>,
+  value: Local<'s, Value>,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<()>,
+) where
+  S: for<'a> Fn(
+    &mut HandleScope<'a>,
+    Local<'a, Name>,
+    Local<'a, Value>,
+    PropertyCallbackArguments<'a>,
+    ReturnValue<()>,
+  ),
+{
+  // SAFETY: This callback is only installed together with its closure data.
+  let closures =
+    unsafe { closure_from_data::<AccessorClosures<G, S>>(args.data()) };
+  (closures.setter)(scope, key, value, args, rv)
+}
+
 /// A builder to construct the properties of a Function or FunctionTemplate.
 pub struct FunctionBuilder<'s, T> {
   pub(crate) callback: FunctionCallback,
@@ -914,6 +1019,20 @@ impl<'s, T> FunctionBuilder<'s, T> {
     Self::new_raw(callback.map_fn_to())
   }
 
+  /// Create a new FunctionBuilder from a typed Rust function, such as
+  /// `fn(&mut HandleScope, String, u32) -> Result<f64, JsError>`.
+  ///
+  /// Arguments are converted with [`FromV8`](crate::FromV8) and the return
+  /// value with [`ToV8`](crate::ToV8). If an argument fails to convert, or the function returns an
+  /// `Err`, the error is thrown as an exception. Argument and return types
+  /// can't borrow from the scope; take a raw callback to work with handles.
+  #[inline(always)]
+  pub fn new_typed<Args>(
+    callback: impl MapFnTo<FunctionCallback, TypedFunctionTag<Args>>,
+  ) -> Self {
+    Self::new_raw(callback.map_fn_to())
+  }
+
   #[inline(always)]
   pub fn new_raw(callback: FunctionCallback) -> Self {
     Self {
@@ -1016,6 +1135,38 @@ impl Function {
     Self::builder_raw(callback).build(scope)
   }
 
+  /// Creates a function from a typed Rust function. See
+  /// [`FunctionBuilder::new_typed`].
+  #[inline(always)]
+  pub fn new_typed<'s, Args>(
+    scope: &mut HandleScope<'s>,
+    callback: impl MapFnTo<FunctionCallback, TypedFunctionTag<Args>>,
+  ) -> Option<Local<'s, Function>> {
+    FunctionBuilder::<Function>::new_typed(callback).build(scope)
+  }
+
+  /// Creates a function from a closure, which unlike the callbacks accepted
+  /// by [`Function::new`] may capture state. The closure is stored behind an
+  /// `External` that is passed as the function's data, and it is dropped
+  /// after the function has been garbage collected.
+  #[inline(always)]
+  pub fn new_closure<'s, C>(
+    scope: &mut HandleScope<'s>,
+    callback: C,
+  ) -> Option<Local<'s, Function>>
+  where
+    C: for<'a> Fn(
+        &mut HandleScope<'a>,
+        FunctionCallbackArguments<'a>,
+        ReturnValue<Value>,
+      ) + 'static,
+  {
+    let data = closure_data(scope, callback);
+    Self::builder(closure_function_callback::<C>)
+      .data(data.into())
+      .build(scope)
+  }
+
   /// Call a function in a context scope.
   #[inline]
   pub fn call<'s>(
@@ -0,0 +1,513 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Exotic JavaScript objects whose properties are provided by Rust code.
+//!
+//! A [`HostObject`] answers property requests for the JavaScript object it is
+//! attached to. [`ObjectTemplate::set_host_object_handler`] installs the
+//! interceptors on a template, and
+//! [`ObjectTemplate::new_host_object_instance`] creates an object that owns a
+//! Rust value implementing the trait. The value is stored in internal field 1
+//! of the object, marked by a tag in internal field 0, and dropped after the
+//! object has been garbage collected.
+
+use std::any::TypeId;
+use std::ffi::c_void;
+
+use crate::object_tag::ObjectTag;
+use crate::Array;
+use crate::Boolean;
+use crate::HandleScope;
+use crate::IndexedPropertyHandlerConfiguration;
+use crate::Integer;
+use crate::Intercepted;
+use crate::Local;
+use crate::Name;
+use crate::NamedPropertyHandlerConfiguration;
+use crate::Object;
+use crate::ObjectTemplate;
+use crate::PropertyAttribute;
+use crate::PropertyCallbackArguments;
+use crate::PropertyDescriptor;
+use crate::ReturnValue;
+use crate::String;
+use crate::Value;
+use crate::Weak;
+
+/// The key of a property request intercepted on a host object.
+///
+/// Keys that are array indices are passed as `Index`; all other keys,
+/// including symbols, are passed as `Name`.
+#[derive(Debug, Clone, Copy)]
+pub enum PropertyKey<'s> {
+  Name(Local<'s, Name>),
+  Index(u32),
+}
+
+impl<'s> PropertyKey<'s> {
+  /// Converts the key to a JavaScript value: the name itself, or the index as
+  /// a number.
+  pub fn to_value(&self, scope: &mut HandleScope<'s>) -> Local<'s, Value> {
+    match *self {
+      Self::Name(name) => name.into(),
+      Self::Index(index) => Integer::new_from_unsigned(scope, index).into(),
+    }
+  }
+
+  /// Returns the key as a string, or `None` if it is a symbol. Indices are
+  /// converted to their decimal representation.
+  pub fn to_rust_string(
+    &self,
+    scope: &mut HandleScope<'s>,
+  ) -> Option<std::string::String> {
+    match *self {
+      Self::Name(name) => {
+        let name = Local::<String>::try_from(name).ok()?;
+        Some(name.to_rust_string_lossy(scope))
+      }
+      Self::Index(index) => Some(index.to_string()),
+    }
+  }
+}
+
+/// Provides the properties of a host object.
+///
+/// Every method may decline a request by returning `None` (or `false`), in
+/// which case it is handled by the ordinary object as if no host object were
+/// present. The methods take `&self` because JavaScript can re-enter the
+/// object while a request is in progress; use interior mutability to keep
+/// mutable state.
+pub trait HostObject: 'static {
+  /// Returns the value of the property `key`.
+  fn get<'s>(
+    &self,
+    _scope: &mut HandleScope<'s>,
+    _key: PropertyKey<'s>,
+  ) -> Option<Local<'s, Value>> {
+    None
+  }
+
+  /// Sets the property `key` to `value`. Returns `true` if the assignment was
+  /// handled.
+  fn set<'s>(
+    &self,
+    _scope: &mut HandleScope<'s>,
+    _key: PropertyKey<'s>,
+    _value: Local<'s, Value>,
+  ) -> bool {
+    false
+  }
+
+  /// Returns the attributes of the property `key` if it exists. Used for the
+  /// `in` operator, `hasOwnProperty()` and similar queries.
+  ///
+  /// The default implementation reports a writable, enumerable property for
+  /// every key that [`HostObject::get`] provides.
+  fn has<'s>(
+    &self,
+    scope: &mut HandleScope<'s>,
+    key: PropertyKey<'s>,
+  ) -> Option<PropertyAttribute> {
+    self.get(scope, key).map(|_| PropertyAttribute::NONE)
+  }
+
+  /// Deletes the property `key`. Returns the result of the `delete`
+  /// expression if the request was handled.
+  fn delete<'s>(
+    &self,
+    _scope: &mut HandleScope<'s>,
+    _key: PropertyKey<'s>,
+  ) -> Option<bool> {
+    None
+  }
+
+  /// Returns the keys of the properties provided by the host object, as seen
+  /// by `Object.keys()`, `for..in` and similar enumerations. This may be
+  /// called more than once per enumeration.
+  fn own_keys<'s>(&self, _scope: &mut HandleScope<'s>) -> Vec<PropertyKey<'s>> {
+    vec![]
+  }
+
+  /// Defines the property `key` as described by `descriptor`. Returns `true`
+  /// if the definition was handled.
+  fn define<'s>(
+    &self,
+    _scope: &mut HandleScope<'s>,
+    _key: PropertyKey<'s>,
+    _descriptor: &PropertyDescriptor,
+  ) -> bool {
+    false
+  }
+
+  /// Returns the property descriptor of `key`, as an object suitable for
+  /// `Object.defineProperty()`.
+  fn descriptor<'s>(
+    &self,
+    _scope: &mut HandleScope<'s>,
+    _key: PropertyKey<'s>,
+  ) -> Option<Local<'s, Object>> {
+    None
+  }
+}
+
+/// Tags the instances of host object templates, whose cell is their value.
+static HOST_OBJECT_TAG: ObjectTag = ObjectTag::new();
+
+struct HostObjectCell {
+  type_id: TypeId,
+  object: Box<dyn HostObject>,
+  // Keeps the finalizer that drops this cell installed.
+  _weak: Option<Weak<Object>>,
+}
+
+/// Returns the cell of an object created by `new_host_object_instance`. The
+/// caller chooses `'a`, which must not outlive the object.
+fn host_object_cell<'a>(object: &Object) -> Option<&'a HostObjectCell> {
+  let cell = HOST_OBJECT_TAG.value(object)? as *const HostObjectCell;
+  // SAFETY: Tagged objects hold a cell, which is not dropped before the
+  // object has been collected. The object is kept alive by the handle that
+  // `object` is borrowed from.
+  unsafe { cell.as_ref() }
+}
+
+fn host_object_of<'a>(holder: Local<'a, Object>) -> Option<&'a dyn HostObject> {
+  host_object_cell(&holder).map(|cell| &*cell.object)
+}
+
+fn intercepted(handled: bool) -> Intercepted {
+  if handled {
+    Intercepted::Yes
+  } else {
+    Intercepted::No
+  }
+}
+
+fn get<'s>(
+  scope: &mut HandleScope<'s>,
+  key: PropertyKey<'s>,
+  args: PropertyCallbackArguments<'s>,
+  mut rv: ReturnValue<Value>,
+) -> Intercepted {
+  let Some(host) = host_object_of(args.holder()) else {
+    return Intercepted::No;
+  };
+  match host.get(scope, key) {
+    Some(value) => {
+      rv.set(value);
+      Intercepted::Yes
+    }
+    None => Intercepted::No,
+  }
+}
+
+fn set<'s>(
+  scope: &mut HandleScope<'s>,
+  key: PropertyKey<'s>,
+  value: Local<'s, Value>,
+  args: PropertyCallbackArguments<'s>,
+) -> Intercepted {
+  let Some(host) = host_object_of(args.holder()) else {
+    return Intercepted::No;
+  };
+  intercepted(host.set(scope, key, value))
+}
+
+fn query<'s>(
+  scope: &mut HandleScope<'s>,
+  key: PropertyKey<'s>,
+  args: PropertyCallbackArguments<'s>,
+  mut rv: ReturnValue<Integer>,
+) -> Intercepted {
+  let Some(host) = host_object_of(args.holder()) else {
+    return Intercepted::No;
+  };
+  match host.has(scope, key) {
+    Some(attributes) => {
+      rv.set_uint32(attributes.as_u32());
+      Intercepted::Yes
+    }
+    None => Intercepted::No,
+  }
+}
+
+fn delete<'s>(
+  scope: &mut HandleScope<'s>,
+  key: PropertyKey<'s>,
+  args: PropertyCallbackArguments<'s>,
+  mut rv: ReturnValue<Boolean>,
+) -> Intercepted {
+  let Some(host) = host_object_of(args.holder()) else {
+    return Intercepted::No;
+  };
+  match host.delete(scope, key) {
+    Some(deleted) => {
+      rv.set_bool(deleted);
+      Intercepted::Yes
+    }
+    None => Intercepted::No,
+  }
+}
+
+fn enumerate<'s>(
+  scope: &mut HandleScope<'s>,
+  args: PropertyCallbackArguments<'s>,
+  mut rv: ReturnValue<Array>,
+  indices: bool,
+) {
+  let Some(host) = host_object_of(args.holder()) else {
+    return;
+  };
+  let keys = host
+    .own_keys(scope)
+    .into_iter()
+    .filter_map(|key| match key {
+      PropertyKey::Name(name) if !indices => Some(name.into()),
+      PropertyKey::Index(index) if indices => {
+        Some(Integer::new_from_unsigned(scope, index).into())
+      }
+      _ => None,
+    })
+    .collect::<Vec<Local<Value>>>();
+  rv.set(Array::new_with_elements(scope, &keys));
+}
+
+fn define<'s>(
+  scope: &mut HandleScope<'s>,
+  key: PropertyKey<'s>,
+  descriptor: &PropertyDescriptor,
+  args: PropertyCallbackArguments<'s>,
+) -> Intercepted {
+  let Some(host) = host_object_of(args.holder()) else {
+    return Intercepted::No;
+  };
+  intercepted(host.define(scope, key, descriptor))
+}
+
+fn descriptor<'s>(
+  scope: &mut HandleScope<'s>,
+  key: PropertyKey<'s>,
+  args: PropertyCallbackArguments<'s>,
+  mut rv: ReturnValue<Value>,
+) -> Intercepted {
+  let Some(host) = host_object_of(args.holder()) else {
+    return Intercepted::No;
+  };
+  match host.descriptor(scope, key) {
+    Some(descriptor) => {
+      rv.set(descriptor.into());
+      Intercepted::Yes
+    }
+    None => Intercepted::No,
+  }
+}
+
+// Adapters from the named and indexed interceptor signatures to the
+// functions above.
+
+fn named_getter<'s>(
+  scope: &mut HandleScope<'s>,
+  key: Local<'s, Name>,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Value>,
+) -> Intercepted {
+  get(scope, PropertyKey::Name(key), args, rv)
+}
+
+fn named_setter<'s>(
+  scope: &mut HandleScope<'s>,
+  key: Local<'s, Name>,
+  value: Local<'s, Value>,
+  args: PropertyCallbackArguments<'s>,
+  _rv: ReturnValue<()>,
+) -> Intercepted {
+  set(scope, PropertyKey::Name(key), value, args)
+}
+
+fn named_query<'s>(
+  scope: &mut HandleScope<'s>,
+  key: Local<'s, Name>,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Integer>,
+) -> Intercepted {
+  query(scope, PropertyKey::Name(key), args, rv)
+}
+
+fn named_deleter<'s>(
+  scope: &mut HandleScope<'s>,
+  key: Local<'s, Name>,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Boolean>,
+) -> Intercepted {
+  delete(scope, PropertyKey::Name(key), args, rv)
+}
+
+fn named_enumerator<'s>(
+  scope: &mut HandleScope<'s>,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Array>,
+) {
+  enumerate(scope, args, rv, false)
+}
+
+fn named_definer<'s>(
+  scope: &mut HandleScope<'s>,
+  key: Local<'s, Name>,
+  desc: &PropertyDescriptor,
+  args: PropertyCallbackArguments<'s>,
+  _rv: ReturnValue<()>,
+) -> Intercepted {
+  define(scope, PropertyKey::Name(key), desc, args)
+}
+
+fn named_descriptor<'s>(
+  scope: &mut HandleScope<'s>,
+  key: Local<'s, Name>,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Value>,
+) -> Intercepted {
+  descriptor(scope, PropertyKey::Name(key), args, rv)
+}
+
+fn indexed_getter<'s>(
+  scope: &mut HandleScope<'s>,
+  index: u32,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Value>,
+) -> Intercepted {
+  get(scope, PropertyKey::Index(index), args, rv)
+}
+
+fn indexed_setter<'s>(
+  scope: &mut HandleScope<'s>,
+  index: u32,
+  value: Local<'s, Value>,
+  args: PropertyCallbackArguments<'s>,
+  _rv: ReturnValue<()>,
+) -> Intercepted {
+  set(scope, PropertyKey::Index(index), value, args)
+}
+
+fn indexed_query<'s>(
+  scope: &mut HandleScope<'s>,
+  index: u32,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Integer>,
+) -> Intercepted {
+  query(scope, PropertyKey::Index(index), args, rv)
+}
+
+fn indexed_deleter<'s>(
+  scope: &mut HandleScope<'s>,
+  index: u32,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Boolean>,
+) -> Intercepted {
+  delete(scope, PropertyKey::Index(index), args, rv)
+}
+
+fn indexed_enumerator<'s>(
+  scope: &mut HandleScope<'s>,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Array>,
+) {
+  enumerate(scope, args, rv, true)
+}
+
+fn indexed_definer<'s>(
+  scope: &mut HandleScope<'s>,
+  index: u32,
+  desc: &PropertyDescriptor,
+  args: PropertyCallbackArguments<'s>,
+  _rv: ReturnValue<()>,
+) -> Intercepted {
+  define(scope, PropertyKey::Index(index), desc, args)
+}
+
+fn indexed_descriptor<'s>(
+  scope: &mut HandleScope<'s>,
+  index: u32,
+  args: PropertyCallbackArguments<'s>,
+  rv: ReturnValue<Value>,
+) -> Intercepted {
+  descriptor(scope, PropertyKey::Index(index), args, rv)
+}
+
+impl ObjectTemplate {
+  /// Installs named and indexed property handlers that forward every
+  /// property request on instances of this template to their
+  /// [`HostObject`]. Ensures that instances have at least two internal
+  /// fields; fields 0 and 1 are reserved for the host object.
+  ///
+  /// Instances that were not created with
+  /// [`ObjectTemplate::new_host_object_instance`] behave like ordinary
+  /// objects.
+  pub fn set_host_object_handler(&self) {
+    if self.internal_field_count() < ObjectTag::FIELD_COUNT {
+      self.set_internal_field_count(ObjectTag::FIELD_COUNT);
+    }
+    self.set_named_property_handler(
+      NamedPropertyHandlerConfiguration::new()
+        .getter(named_getter)
+        .setter(named_setter)
+        .query(named_query)
+        .deleter(named_deleter)
+        .enumerator(named_enumerator)
+        .definer(named_definer)
+        .descriptor(named_descriptor),
+    );
+    self.set_indexed_property_handler(
+      IndexedPropertyHandlerConfiguration::new()
+        .getter(indexed_getter)
+        .setter(indexed_setter)
+        .query(indexed_query)
+        .deleter(indexed_deleter)
+        .enumerator(indexed_enumerator)
+        .definer(indexed_definer)
+        .descriptor(indexed_descriptor),
+    );
+  }
+
+  /// Creates a new instance of this template backed by `host_object`. The
+  /// template must have been set up with
+  /// [`ObjectTemplate::set_host_object_handler`].
+  ///
+  /// `host_object` is dropped once the returned object has been garbage
+  /// collected, or when the isolate is disposed.
+  pub fn new_host_object_instance<'s, T: HostObject>(
+    &self,
+    scope: &mut HandleScope<'s>,
+    host_object: T,
+  ) -> Option<Local<'s, Object>> {
+    assert!(
+      self.internal_field_count() >= ObjectTag::FIELD_COUNT,
+      "template has no internal fields for the host object"
+    );
+    let instance = self.new_instance(scope)?;
+
+    let cell = Box::into_raw(Box::new(HostObjectCell {
+      type_id: TypeId::of::<T>(),
+      object: Box::new(host_object),
+      _weak: None,
+    }));
+    HOST_OBJECT_TAG.tag(&instance, cell as *const c_void);
+    let weak = Weak::with_guaranteed_finalizer(
+      scope,
+      instance,
+      // SAFETY: The cell is dropped exactly once, after its holder is gone.
+      Box::new(move || drop(unsafe { Box::from_raw(cell) })),
+    );
+    unsafe { (*cell)._weak = Some(weak) };
+    Some(instance)
+  }
+}
+
+impl Object {
+  /// Returns the host object of an object created by
+  /// [`ObjectTemplate::new_host_object_instance`], if it is of type `T`.
+  pub fn get_host_object<T: HostObject>(&self) -> Option<&T> {
+    let cell = host_object_cell(self)?;
+    if cell.type_id != TypeId::of::<T>() {
+      return None;
+    }
+    Some(unsafe { &*(&*cell.object as *const dyn HostObject as *const T) })
+  }
+}
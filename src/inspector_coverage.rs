@@ -0,0 +1,362 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! JavaScript code coverage, enabled by the `inspector_protocol` feature.
+//!
+//! [`CoverageCollector`] connects a [`ProtocolSession`] to an inspector and
+//! starts V8's precise block coverage, which counts how often each function
+//! and block runs. [`CoverageCollector::collect`] gathers the counts of the
+//! scripts named by their [`ScriptOrigin`](crate::ScriptOrigin), together
+//! with their sources, into a [`CoverageReport`] that can be written as LCOV
+//! or as Istanbul's JSON coverage format.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::inspector::V8Inspector;
+use crate::inspector_protocol::debugger;
+use crate::inspector_protocol::profiler;
+use crate::inspector_protocol::ProtocolError;
+use crate::inspector_protocol::ProtocolSession;
+
+/// Collects the coverage of the scripts of a context group.
+pub struct CoverageCollector {
+  session: ProtocolSession,
+}
+
+impl CoverageCollector {
+  /// Starts collecting coverage for the contexts of `context_group_id`.
+  /// Only code that runs afterwards is counted, and only scripts compiled
+  /// afterwards are reported, so this should be called before any script is
+  /// compiled. The collector must be dropped before `inspector`.
+  pub fn start(
+    inspector: &mut V8Inspector,
+    context_group_id: i32,
+  ) -> Result<Self, ProtocolError> {
+    let session = ProtocolSession::connect(inspector, context_group_id);
+    // Nothing reads the notifications, such as `Debugger.scriptParsed`.
+    session.set_event_filter(|_| false);
+    // The debugger keeps the sources of the scripts compiled while it is
+    // enabled, which are needed to map offsets to lines.
+    session.call(&debugger::Enable::default())?;
+    session.call(&debugger::SetSkipAllPauses { skip: true })?;
+    session.call(&profiler::Enable {})?;
+    session.call(&profiler::StartPreciseCoverage {
+      call_count: Some(true),
+      detailed: Some(true),
+      ..Default::default()
+    })?;
+    Ok(Self { session })
+  }
+
+  /// Returns the counts of all scripts since the previous call, or since
+  /// coverage started, and resets them.
+  pub fn take_coverage(
+    &self,
+  ) -> Result<Vec<profiler::ScriptCoverage>, ProtocolError> {
+    let response = self.session.call(&profiler::TakePreciseCoverage {})?;
+    Ok(response.result)
+  }
+
+  /// Returns a report of the scripts that have a URL, with the counts since
+  /// the previous call, or since coverage started, and resets them. Scripts
+  /// without a URL, such as those compiled by `eval`, are left out, as are
+  /// those whose source is no longer available, e.g. because they were
+  /// collected as garbage.
+  pub fn collect(&self) -> Result<CoverageReport, ProtocolError> {
+    let mut scripts = Vec::new();
+    for script in self.take_coverage()? {
+      if script.url.is_empty() {
+        continue;
+      }
+      let Ok(response) = self.session.call(&debugger::GetScriptSource {
+        script_id: script.script_id,
+      }) else {
+        continue;
+      };
+      let source = response.script_source;
+      scripts.push(ScriptCoverageReport {
+        url: script.url,
+        source,
+        functions: script.functions,
+      });
+    }
+    Ok(CoverageReport { scripts })
+  }
+}
+
+/// The coverage of a set of scripts.
+#[derive(Clone, Debug, Default)]
+pub struct CoverageReport {
+  pub scripts: Vec<ScriptCoverageReport>,
+}
+
+impl CoverageReport {
+  /// Formats the report as an LCOV tracefile, with the counts of lines,
+  /// functions and blocks.
+  pub fn to_lcov(&self) -> String {
+    let mut lcov = String::new();
+    for script in &self.scripts {
+      script.write_lcov(&mut lcov);
+    }
+    lcov
+  }
+
+  /// Formats the report in Istanbul's JSON coverage format, as read by
+  /// `nyc report` and similar tools. Each line counts as a statement and
+  /// each block as a branch.
+  pub fn to_istanbul_json(&self) -> String {
+    let files = self
+      .scripts
+      .iter()
+      .map(|script| (script.url.clone(), script.istanbul()))
+      .collect::<Map<_, _>>();
+    Value::Object(files).to_string()
+  }
+
+  /// Writes [`CoverageReport::to_lcov`] to a file.
+  pub fn write_lcov(&self, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, self.to_lcov())
+  }
+
+  /// Writes [`CoverageReport::to_istanbul_json`] to a file.
+  pub fn write_istanbul_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, self.to_istanbul_json())
+  }
+}
+
+/// The coverage of a script.
+#[derive(Clone, Debug)]
+pub struct ScriptCoverageReport {
+  /// The resource name of the script's origin.
+  pub url: String,
+  pub source: String,
+  pub functions: Vec<profiler::FunctionCoverage>,
+}
+
+impl ScriptCoverageReport {
+  /// Returns the 1-based number and the count of each line that has code.
+  /// The count of a line is that of the innermost block containing its
+  /// first non-whitespace character.
+  pub fn line_counts(&self) -> Vec<(usize, i64)> {
+    let lines = LineIndex::new(&self.source);
+    self
+      .source
+      .lines()
+      .enumerate()
+      .filter_map(|(index, line)| {
+        let indent = line.len() - line.trim_start().len();
+        if indent == line.len() {
+          return None;
+        }
+        let offset = lines.line_starts[index] + utf16_len(&line[..indent]);
+        Some((index + 1, self.count_at(offset)))
+      })
+      .collect()
+  }
+
+  /// Returns the count of the innermost range containing a UTF-16 offset.
+  fn count_at(&self, offset: usize) -> i64 {
+    let offset = offset as i64;
+    self
+      .functions
+      .iter()
+      .flat_map(|function| &function.ranges)
+      .filter(|range| range.start_offset <= offset && offset < range.end_offset)
+      .min_by_key(|range| range.end_offset - range.start_offset)
+      .map_or(0, |range| range.count)
+  }
+
+  /// Returns the functions defined by the script, which leaves out the
+  /// top-level code, with unique names.
+  fn named_functions(&self) -> Vec<(String, &profiler::FunctionCoverage)> {
+    self
+      .functions
+      .iter()
+      .filter(|function| {
+        !(function.function_name.is_empty()
+          && function.ranges.first().map(|range| range.start_offset) == Some(0))
+      })
+      .enumerate()
+      .map(|(index, function)| {
+        let name = if function.function_name.is_empty() {
+          format!("(anonymous_{})", index)
+        } else {
+          function.function_name.clone()
+        };
+        (name, function)
+      })
+      .collect()
+  }
+
+  /// Returns the blocks of the functions, i.e. their ranges after the first,
+  /// as the index of the function, the index of the block and the range.
+  fn blocks(&self) -> Vec<(usize, usize, &profiler::CoverageRange)> {
+    self
+      .functions
+      .iter()
+      .enumerate()
+      .flat_map(|(function_index, function)| {
+        function.ranges.iter().enumerate().skip(1).map(
+          move |(block_index, range)| (function_index, block_index - 1, range),
+        )
+      })
+      .collect()
+  }
+
+  fn write_lcov(&self, lcov: &mut String) {
+    use std::fmt::Write;
+
+    let lines = LineIndex::new(&self.source);
+    lcov.push_str("TN:\n");
+    let _ = writeln!(lcov, "SF:{}", self.url);
+
+    let functions = self.named_functions();
+    for (name, function) in &functions {
+      let (line, _) = lines.position(function.ranges[0].start_offset as usize);
+      let _ = writeln!(lcov, "FN:{},{}", line + 1, name);
+    }
+    for (name, function) in &functions {
+      let _ = writeln!(lcov, "FNDA:{},{}", function.ranges[0].count, name);
+    }
+    let _ = writeln!(lcov, "FNF:{}", functions.len());
+    let hit = functions
+      .iter()
+      .filter(|(_, function)| function.ranges[0].count > 0)
+      .count();
+    let _ = writeln!(lcov, "FNH:{}", hit);
+
+    let blocks = self.blocks();
+    for (function_index, block_index, range) in &blocks {
+      let (line, _) = lines.position(range.start_offset as usize);
+      let _ = writeln!(
+        lcov,
+        "BRDA:{},{},{},{}",
+        line + 1,
+        function_index,
+        block_index,
+        range.count
+      );
+    }
+    let _ = writeln!(lcov, "BRF:{}", blocks.len());
+    let hit = blocks
+      .iter()
+      .filter(|(_, _, range)| range.count > 0)
+      .count();
+    let _ = writeln!(lcov, "BRH:{}", hit);
+
+    let line_counts = self.line_counts();
+    for (line, count) in &line_counts {
+      let _ = writeln!(lcov, "DA:{},{}", line, count);
+    }
+    let _ = writeln!(lcov, "LF:{}", line_counts.len());
+    let hit = line_counts.iter().filter(|(_, count)| *count > 0).count();
+    let _ = writeln!(lcov, "LH:{}", hit);
+    lcov.push_str("end_of_record\n");
+  }
+
+  fn istanbul(&self) -> Value {
+    let lines = LineIndex::new(&self.source);
+    let location = |start: usize, end: usize| {
+      let (start_line, start_column) = lines.position(start);
+      let (end_line, end_column) = lines.position(end);
+      json!({
+        "start": { "line": start_line + 1, "column": start_column },
+        "end": { "line": end_line + 1, "column": end_column },
+      })
+    };
+
+    let mut statement_map = Map::new();
+    let mut statements = Map::new();
+    for (index, (line, count)) in self.line_counts().into_iter().enumerate() {
+      let start = lines.line_starts[line - 1];
+      let end = lines
+        .line_starts
+        .get(line)
+        .map_or(lines.len, |next| next - 1);
+      statement_map.insert(index.to_string(), location(start, end));
+      statements.insert(index.to_string(), count.into());
+    }
+
+    let mut function_map = Map::new();
+    let mut functions = Map::new();
+    for (index, (name, function)) in self.named_functions().iter().enumerate() {
+      let range = &function.ranges[0];
+      let loc =
+        location(range.start_offset as usize, range.end_offset as usize);
+      function_map.insert(
+        index.to_string(),
+        json!({
+          "name": name,
+          "decl": loc,
+          "loc": loc,
+          "line": lines.position(range.start_offset as usize).0 + 1,
+        }),
+      );
+      functions.insert(index.to_string(), range.count.into());
+    }
+
+    let mut branch_map = Map::new();
+    let mut branches = Map::new();
+    for (index, (_, _, range)) in self.blocks().iter().enumerate() {
+      let loc =
+        location(range.start_offset as usize, range.end_offset as usize);
+      branch_map.insert(
+        index.to_string(),
+        json!({
+          "type": "block",
+          "line": lines.position(range.start_offset as usize).0 + 1,
+          "loc": loc,
+          "locations": [loc],
+        }),
+      );
+      branches.insert(index.to_string(), json!([range.count]));
+    }
+
+    json!({
+      "path": self.url,
+      "statementMap": statement_map,
+      "s": statements,
+      "fnMap": function_map,
+      "f": functions,
+      "branchMap": branch_map,
+      "b": branches,
+    })
+  }
+}
+
+/// Maps the UTF-16 offsets used by V8 to 0-based lines and columns.
+struct LineIndex {
+  line_starts: Vec<usize>,
+  len: usize,
+}
+
+impl LineIndex {
+  fn new(source: &str) -> Self {
+    let mut line_starts = vec![0];
+    let mut offset = 0;
+    for c in source.chars() {
+      offset += c.len_utf16();
+      if c == '\n' {
+        line_starts.push(offset);
+      }
+    }
+    Self {
+      line_starts,
+      len: offset,
+    }
+  }
+
+  fn position(&self, offset: usize) -> (usize, usize) {
+    let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+    (line, offset - self.line_starts[line])
+  }
+}
+
+fn utf16_len(s: &str) -> usize {
+  s.chars().map(char::len_utf16).sum()
+}
@@ -0,0 +1,417 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! A JavaScript debugger for Rust code, enabled by the `inspector_protocol`
+//! feature.
+//!
+//! [`Debugger`] drives the inspector through a [`ProtocolSession`], so that
+//! tests and tools can set breakpoints and step through code without
+//! writing protocol messages. When JavaScript pauses, the callback given to
+//! [`Debugger::on_paused`] runs on the isolate's thread. It can look at the
+//! call frames and their variables, evaluate expressions, and decide how
+//! execution continues.
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::inspector::V8Inspector;
+use crate::inspector_host::InspectorHost;
+use crate::inspector_host::PauseHandler;
+use crate::inspector_host::CONTEXT_GROUP_ID;
+use crate::inspector_protocol::debugger;
+use crate::inspector_protocol::runtime;
+use crate::inspector_protocol::Event;
+use crate::inspector_protocol::ProtocolError;
+use crate::inspector_protocol::ProtocolSession;
+use crate::Context;
+use crate::Isolate;
+use crate::Local;
+
+/// The group of the remote objects created while paused, which are released
+/// when execution continues.
+const OBJECT_GROUP: &str = "rusty_v8_debugger";
+
+/// A debugger attached to an isolate.
+pub struct Debugger {
+  state: Rc<DebuggerState>,
+  host: InspectorHost,
+}
+
+/// Which exceptions pause execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseOnExceptions {
+  /// No exception pauses execution. This is the default.
+  None,
+  /// Exceptions that are caught pause execution.
+  Caught,
+  /// Exceptions that are not caught pause execution.
+  Uncaught,
+  /// All exceptions pause execution.
+  All,
+}
+
+impl PauseOnExceptions {
+  fn as_str(self) -> &'static str {
+    match self {
+      Self::None => "none",
+      Self::Caught => "caught",
+      Self::Uncaught => "uncaught",
+      Self::All => "all",
+    }
+  }
+}
+
+impl Debugger {
+  /// Attaches a debugger to `isolate`. Only contexts reported with
+  /// [`Debugger::context_created`] can be debugged.
+  pub fn new(isolate: &mut Isolate) -> Result<Self, ProtocolError> {
+    let state = Rc::new(DebuggerState {
+      session: RefCell::new(None),
+      on_paused: RefCell::new(None),
+      paused: Cell::new(false),
+    });
+    let mut host = InspectorHost::new(isolate, state.clone());
+    let session = ProtocolSession::connect(host.inspector(), CONTEXT_GROUP_ID);
+    // Only `Debugger.paused` is read, by the pause loop.
+    session.set_event_filter(|method| method == "Debugger.paused");
+    session.call(&debugger::Enable::default())?;
+    *state.session.borrow_mut() = Some(Rc::new(session));
+    Ok(Self { state, host })
+  }
+
+  /// Returns the inspector, e.g. to report exceptions.
+  pub fn inspector(&mut self) -> &mut V8Inspector {
+    self.host.inspector()
+  }
+
+  /// Reports a new context to the debugger.
+  pub fn context_created(&mut self, context: Local<Context>, name: &str) {
+    self.host.context_created(context, name);
+  }
+
+  /// Reports to the debugger that a context is gone.
+  pub fn context_destroyed(&mut self, context: Local<Context>) {
+    self.host.context_destroyed(context);
+  }
+
+  /// Sets a breakpoint on a line of the scripts whose resource name, as
+  /// given by their [`ScriptOrigin`](crate::ScriptOrigin), is `script_url`.
+  /// It also applies to matching scripts compiled later. `line_number` is
+  /// 0-based, like the locations of [`debugger::CallFrame`].
+  pub fn set_breakpoint(
+    &self,
+    script_url: &str,
+    line_number: u32,
+  ) -> Result<debugger::BreakpointId, ProtocolError> {
+    self.set_conditional_breakpoint(script_url, line_number, None)
+  }
+
+  /// Like [`Debugger::set_breakpoint`], but only pauses when `condition`,
+  /// evaluated in the scope of the breakpoint, is truthy.
+  pub fn set_conditional_breakpoint(
+    &self,
+    script_url: &str,
+    line_number: u32,
+    condition: Option<&str>,
+  ) -> Result<debugger::BreakpointId, ProtocolError> {
+    let response = self.session().call(&debugger::SetBreakpointByUrl {
+      line_number: line_number.into(),
+      url: Some(script_url.to_owned()),
+      condition: condition.map(str::to_owned),
+      ..Default::default()
+    })?;
+    Ok(response.breakpoint_id)
+  }
+
+  /// Removes a breakpoint set by [`Debugger::set_breakpoint`].
+  pub fn remove_breakpoint(
+    &self,
+    breakpoint_id: &str,
+  ) -> Result<(), ProtocolError> {
+    self.session().call(&debugger::RemoveBreakpoint {
+      breakpoint_id: breakpoint_id.to_owned(),
+    })?;
+    Ok(())
+  }
+
+  /// Sets which exceptions pause execution.
+  pub fn set_pause_on_exceptions(
+    &self,
+    state: PauseOnExceptions,
+  ) -> Result<(), ProtocolError> {
+    self.session().call(&debugger::SetPauseOnExceptions {
+      state: state.as_str().to_owned(),
+    })?;
+    Ok(())
+  }
+
+  /// Pauses on the next JavaScript statement.
+  pub fn pause_on_next_statement(&self) {
+    self
+      .session()
+      .schedule_pause_on_next_statement("debugCommand");
+  }
+
+  /// Sets the callback that runs when execution pauses, replacing the
+  /// previous one. Execution resumes after the callback returns, unless it
+  /// called one of the stepping methods of [`Paused`]. Without a callback,
+  /// execution resumes right away.
+  pub fn on_paused(&self, callback: impl FnMut(&mut Paused) + 'static) {
+    *self.state.on_paused.borrow_mut() = Some(Box::new(callback));
+  }
+
+  fn session(&self) -> Rc<ProtocolSession> {
+    self.state.session.borrow().clone().unwrap()
+  }
+}
+
+impl Drop for Debugger {
+  fn drop(&mut self) {
+    // The session must be disconnected before the inspector is dropped.
+    self.state.session.borrow_mut().take();
+  }
+}
+
+/// How execution continues after a pause.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+  Resume,
+  StepOver,
+  StepInto,
+  StepOut,
+}
+
+/// The state of a paused program, given to the [`Debugger::on_paused`]
+/// callback.
+pub struct Paused<'a> {
+  session: &'a ProtocolSession,
+  event: debugger::Paused,
+  action: Action,
+}
+
+impl Paused<'_> {
+  /// Returns why execution paused, e.g. `"other"` for breakpoints and steps,
+  /// or `"exception"`.
+  pub fn reason(&self) -> &str {
+    &self.event.reason
+  }
+
+  /// Returns the breakpoints that were hit.
+  pub fn hit_breakpoints(&self) -> &[debugger::BreakpointId] {
+    self.event.hit_breakpoints.as_deref().unwrap_or_default()
+  }
+
+  /// Returns the call stack, innermost frame first.
+  pub fn call_frames(&self) -> &[debugger::CallFrame] {
+    &self.event.call_frames
+  }
+
+  /// Returns the exception or promise rejection that execution paused on.
+  pub fn exception(&self) -> Option<runtime::RemoteObject> {
+    match self.event.reason.as_str() {
+      "exception" | "promiseRejection" => {
+        serde_json::from_value(self.event.data.clone()?).ok()
+      }
+      _ => None,
+    }
+  }
+
+  /// Returns the scopes of the call frame at `frame_index` with their
+  /// variables, innermost first. The global scope is left out.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `frame_index` is out of bounds.
+  pub fn scopes(
+    &self,
+    frame_index: usize,
+  ) -> Result<Vec<Scope>, ProtocolError> {
+    let frame = &self.event.call_frames[frame_index];
+    let mut scopes = Vec::new();
+    for scope in &frame.scope_chain {
+      if scope.r#type == "global" {
+        continue;
+      }
+      let mut variables = Vec::new();
+      if let Some(object_id) = &scope.object.object_id {
+        let response = self.session.call(&runtime::GetProperties {
+          object_id: object_id.clone(),
+          own_properties: Some(true),
+          ..Default::default()
+        })?;
+        for property in response.result {
+          if let Some(value) = property.value {
+            variables.push(Variable {
+              name: property.name,
+              value,
+            });
+          }
+        }
+      }
+      scopes.push(Scope {
+        r#type: scope.r#type.clone(),
+        name: scope.name.clone(),
+        variables,
+      });
+    }
+    Ok(scopes)
+  }
+
+  /// Returns the variable `name` as seen from the call frame at
+  /// `frame_index`, or `None` if no scope but the global one defines it.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `frame_index` is out of bounds.
+  pub fn variable(
+    &self,
+    frame_index: usize,
+    name: &str,
+  ) -> Result<Option<runtime::RemoteObject>, ProtocolError> {
+    for scope in self.scopes(frame_index)? {
+      if let Some(variable) = scope
+        .variables
+        .into_iter()
+        .find(|variable| variable.name == name)
+      {
+        return Ok(Some(variable.value));
+      }
+    }
+    Ok(None)
+  }
+
+  /// Evaluates `expression` in the scope of the call frame at `frame_index`.
+  /// Exceptions thrown by the expression are returned in
+  /// [`debugger::EvaluateOnCallFrameResponse::exception_details`].
+  ///
+  /// # Panics
+  ///
+  /// Panics if `frame_index` is out of bounds.
+  pub fn evaluate(
+    &self,
+    frame_index: usize,
+    expression: &str,
+  ) -> Result<debugger::EvaluateOnCallFrameResponse, ProtocolError> {
+    let frame = &self.event.call_frames[frame_index];
+    self.session.call(&debugger::EvaluateOnCallFrame {
+      call_frame_id: frame.call_frame_id.clone(),
+      expression: expression.to_owned(),
+      object_group: Some(OBJECT_GROUP.to_owned()),
+      silent: Some(true),
+      ..Default::default()
+    })
+  }
+
+  /// Continues execution once the callback returns. This is the default.
+  pub fn resume(&mut self) {
+    self.action = Action::Resume;
+  }
+
+  /// Pauses again at the next statement of the current function, or of its
+  /// caller once it returns.
+  pub fn step_over(&mut self) {
+    self.action = Action::StepOver;
+  }
+
+  /// Pauses again at the next statement, entering function calls.
+  pub fn step_into(&mut self) {
+    self.action = Action::StepInto;
+  }
+
+  /// Pauses again once the current function returns.
+  pub fn step_out(&mut self) {
+    self.action = Action::StepOut;
+  }
+}
+
+/// A scope of a paused call frame.
+#[derive(Clone, Debug)]
+pub struct Scope {
+  /// `"local"`, `"closure"`, `"block"`, `"catch"`, `"script"`, `"with"`,
+  /// `"eval"` or `"module"`.
+  pub r#type: String,
+  pub name: Option<String>,
+  pub variables: Vec<Variable>,
+}
+
+/// A variable of a scope.
+#[derive(Clone, Debug)]
+pub struct Variable {
+  pub name: String,
+  pub value: runtime::RemoteObject,
+}
+
+/// The state shared by the debugger and the client it implements for V8.
+struct DebuggerState {
+  session: RefCell<Option<Rc<ProtocolSession>>>,
+  #[allow(clippy::type_complexity)]
+  on_paused: RefCell<Option<Box<dyn FnMut(&mut Paused)>>>,
+  paused: Cell<bool>,
+}
+
+impl DebuggerState {
+  fn call_on_paused(
+    &self,
+    session: &ProtocolSession,
+    event: debugger::Paused,
+  ) -> Action {
+    let mut paused = Paused {
+      session,
+      event,
+      action: Action::Resume,
+    };
+    // The callback is taken out while it runs, so that it can't be
+    // re-entered if it makes JavaScript pause again.
+    let callback = self.on_paused.borrow_mut().take();
+    if let Some(mut callback) = callback {
+      callback(&mut paused);
+      self.on_paused.borrow_mut().get_or_insert(callback);
+    }
+    paused.action
+  }
+}
+
+impl PauseHandler for DebuggerState {
+  fn run_message_loop_on_pause(&self) {
+    let Some(session) = self.session.borrow().clone() else {
+      return;
+    };
+    self.paused.set(true);
+    while self.paused.get() {
+      let action = match take_paused_event(&session) {
+        Some(event) => self.call_on_paused(&session, event),
+        None => Action::Resume,
+      };
+      let _ = session.call(&runtime::ReleaseObjectGroup {
+        object_group: OBJECT_GROUP.to_owned(),
+      });
+      // V8 quits the loop when it continues.
+      let result = match action {
+        Action::Resume => session.call(&debugger::Resume::default()),
+        Action::StepOver => session.call(&debugger::StepOver::default()),
+        Action::StepInto => session.call(&debugger::StepInto::default()),
+        Action::StepOut => session.call(&debugger::StepOut::default()),
+      };
+      if result.is_err() {
+        break;
+      }
+    }
+    self.paused.set(false);
+  }
+
+  fn quit_message_loop_on_pause(&self) {
+    self.paused.set(false);
+  }
+}
+
+/// Returns the latest `Debugger.paused` notification, discarding any
+/// earlier ones.
+fn take_paused_event(session: &ProtocolSession) -> Option<debugger::Paused> {
+  let mut paused = None;
+  while let Some(event) = session.next_event() {
+    if let Ok(Event::Debugger(debugger::Event::Paused(event))) = event {
+      paused = Some(event);
+    }
+  }
+  paused
+}
@@ -0,0 +1,106 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! An inspector embedded in the process, shared by
+//! [`inspector_server`](crate::inspector_server) and
+//! [`inspector_debugger`](crate::inspector_debugger).
+
+use std::ptr::addr_of;
+use std::rc::Rc;
+
+use crate::inspector::StringView;
+use crate::inspector::V8Inspector;
+use crate::inspector::V8InspectorClientBase;
+use crate::inspector::V8InspectorClientImpl;
+use crate::support::UniqueRef;
+use crate::Context;
+use crate::Isolate;
+use crate::Local;
+
+/// The context group of the contexts reported to the inspector.
+pub(crate) const CONTEXT_GROUP_ID: i32 = 1;
+
+/// What the inspector asks of its embedder while JavaScript is paused.
+pub(crate) trait PauseHandler {
+  /// Dispatches protocol messages until
+  /// [`PauseHandler::quit_message_loop_on_pause`] is called.
+  fn run_message_loop_on_pause(&self);
+
+  fn quit_message_loop_on_pause(&self);
+
+  /// Called when a session sends `Runtime.runIfWaitingForDebugger`.
+  fn run_if_waiting_for_debugger(&self) {}
+}
+
+/// An inspector for an isolate, and the client that it calls.
+pub(crate) struct InspectorHost {
+  // Dropped before the client it calls.
+  inspector: UniqueRef<V8Inspector>,
+  _client: Box<HostClient>,
+}
+
+impl InspectorHost {
+  pub fn new(isolate: &mut Isolate, handler: Rc<dyn PauseHandler>) -> Self {
+    let mut client = Box::new(HostClient {
+      base: V8InspectorClientBase::new::<HostClient>(),
+      handler,
+    });
+    let inspector = V8Inspector::create(isolate, &mut *client);
+    Self {
+      inspector,
+      _client: client,
+    }
+  }
+
+  pub fn inspector(&mut self) -> &mut V8Inspector {
+    &mut self.inspector
+  }
+
+  /// Reports a new context as the default context of its group.
+  pub fn context_created(&mut self, context: Local<Context>, name: &str) {
+    let aux_data = br#"{"isDefault":true}"#;
+    self.inspector.context_created(
+      context,
+      CONTEXT_GROUP_ID,
+      StringView::from(name.as_bytes()),
+      StringView::from(&aux_data[..]),
+    );
+  }
+
+  pub fn context_destroyed(&mut self, context: Local<Context>) {
+    self.inspector.context_destroyed(context);
+  }
+}
+
+struct HostClient {
+  base: V8InspectorClientBase,
+  handler: Rc<dyn PauseHandler>,
+}
+
+impl V8InspectorClientImpl for HostClient {
+  fn base(&self) -> &V8InspectorClientBase {
+    &self.base
+  }
+
+  fn base_mut(&mut self) -> &mut V8InspectorClientBase {
+    &mut self.base
+  }
+
+  unsafe fn base_ptr(this: *const Self) -> *const V8InspectorClientBase
+  where
+    Self: Sized,
+  {
+    unsafe { addr_of!((*this).base) }
+  }
+
+  fn run_message_loop_on_pause(&mut self, _context_group_id: i32) {
+    self.handler.run_message_loop_on_pause();
+  }
+
+  fn quit_message_loop_on_pause(&mut self) {
+    self.handler.quit_message_loop_on_pause();
+  }
+
+  fn run_if_waiting_for_debugger(&mut self, _context_group_id: i32) {
+    self.handler.run_if_waiting_for_debugger();
+  }
+}
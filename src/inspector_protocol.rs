@@ -0,0 +1,470 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Typed Chrome DevTools Protocol messages, enabled by the
+//! `inspector_protocol` feature.
+//!
+//! The domain modules, e.g. [`runtime`], [`debugger`] and [`profiler`],
+//! describe the commands, types and events of the protocol domains that V8
+//! implements, following
+//! <https://chromedevtools.github.io/devtools-protocol/v8/>. They are
+//! generated when the crate is built from V8's protocol definition,
+//! `v8/include/js_protocol.json`, so they match the bundled V8. Strings that
+//! the protocol restricts to a set of values are plain [`String`]s, whose
+//! documentation lists the values.
+//!
+//! A [`ProtocolSession`] sends commands to a [`V8InspectorSession`], matches
+//! responses to them by id, and parses notifications into [`Event`]s. At
+//! most [`MAX_BUFFERED_EVENTS`] notifications and
+//! [`MAX_BUFFERED_RESPONSES`] unclaimed responses are buffered, and
+//! [`ProtocolSession::set_event_filter`] chooses which notifications are.
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::addr_of;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::inspector::ChannelBase;
+use crate::inspector::ChannelImpl;
+use crate::inspector::StringBuffer;
+use crate::inspector::StringView;
+use crate::inspector::V8Inspector;
+use crate::inspector::V8InspectorClientTrustLevel;
+use crate::inspector::V8InspectorSession;
+use crate::support::UniquePtr;
+use crate::support::UniqueRef;
+
+/// A protocol command, whose parameters are the fields of the type.
+pub trait Command: Serialize {
+  /// The name of the command, e.g. `"Runtime.evaluate"`.
+  const METHOD: &'static str;
+  /// The result of the command.
+  type Response: DeserializeOwned;
+}
+
+/// The result of commands that return nothing.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct Empty {}
+
+macro_rules! commands {
+  ($($command:ty => $method:literal -> $response:ty),* $(,)?) => {
+    $(
+      impl $crate::inspector_protocol::Command for $command {
+        const METHOD: &'static str = $method;
+        type Response = $response;
+      }
+    )*
+  };
+}
+
+macro_rules! events {
+  ($($variant:ident $(($params:ty))? => $method:literal),* $(,)?) => {
+    events!(@enum [] $($variant $(($params))? => $method,)*);
+
+    impl Event {
+      /// The names of the notifications of this domain.
+      pub const METHODS: &'static [&'static str] = &[$($method),*];
+
+      /// Returns the name of the notification.
+      pub fn method(&self) -> &'static str {
+        match self {
+          $(events!(@pattern $variant $(($params))?) => $method,)*
+        }
+      }
+    }
+  };
+  (@enum [$($variants:tt)*]) => {
+    /// A notification of this domain.
+    #[derive(Clone, Debug, ::serde::Deserialize)]
+    #[serde(tag = "method", content = "params")]
+    #[allow(clippy::large_enum_variant)]
+    pub enum Event {
+      $($variants)*
+    }
+  };
+  (@enum [$($variants:tt)*]
+    $variant:ident ($params:ty) => $method:literal, $($rest:tt)*) => {
+    events!(@enum [
+      $($variants)*
+      #[serde(rename = $method)]
+      $variant($params),
+    ] $($rest)*);
+  };
+  // V8 sends `{}` as the parameters of notifications that have none.
+  (@enum [$($variants:tt)*]
+    $variant:ident => $method:literal, $($rest:tt)*) => {
+    events!(@enum [
+      $($variants)*
+      #[serde(rename = $method, deserialize_with = "super::ignore_params")]
+      $variant,
+    ] $($rest)*);
+  };
+  (@pattern $variant:ident ($params:ty)) => { Self::$variant(_) };
+  (@pattern $variant:ident) => { Self::$variant };
+}
+
+fn ignore_params<'de, D: Deserializer<'de>>(params: D) -> Result<(), D::Error> {
+  IgnoredAny::deserialize(params).map(|_| ())
+}
+
+macro_rules! domains {
+  ($($variant:ident $module:ident),* $(,)?) => {
+    /// A notification from the inspector.
+    #[derive(Clone, Debug)]
+    #[allow(clippy::large_enum_variant)]
+    pub enum Event {
+      $($variant($module::Event),)*
+      /// A notification that the protocol definition doesn't describe.
+      Other {
+        method: String,
+        params: Value,
+      },
+    }
+
+    impl Event {
+      /// Parses a notification message. Fails if the message isn't JSON, or
+      /// if the parameters of a notification that the protocol definition
+      /// describes don't match it.
+      pub fn parse(message: &str) -> Result<Self, ProtocolError> {
+        #[derive(Deserialize)]
+        struct Notification {
+          method: String,
+          #[serde(default)]
+          params: Value,
+        }
+        let Notification { method, params } = serde_json::from_str(message)?;
+        $(
+          if $module::Event::METHODS.contains(&method.as_str()) {
+            let message =
+              serde_json::json!({ "method": method, "params": params });
+            return Ok(Self::$variant(serde_json::from_value(message)?));
+          }
+        )*
+        Ok(Self::Other { method, params })
+      }
+
+      /// Returns the name of the notification, e.g. `"Debugger.paused"`.
+      pub fn method(&self) -> &str {
+        match self {
+          $(Self::$variant(event) => event.method(),)*
+          Self::Other { method, .. } => method,
+        }
+      }
+    }
+  };
+}
+
+#[derive(Debug)]
+pub enum ProtocolError {
+  /// The inspector rejected the command.
+  Response {
+    code: i64,
+    message: String,
+    data: Option<String>,
+  },
+  /// The response to the command has not been sent yet. Commands such as
+  /// `Runtime.evaluate` with `awaitPromise` are answered later; use
+  /// [`ProtocolSession::send`] and [`ProtocolSession::take_response`].
+  NoResponse,
+  /// A message could not be converted from or to JSON.
+  Json(serde_json::Error),
+}
+
+impl fmt::Display for ProtocolError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Response { code, message, .. } => {
+        write!(f, "protocol error {}: {}", code, message)
+      }
+      Self::NoResponse => f.write_str("the command has not been answered"),
+      Self::Json(error) => write!(f, "invalid protocol message: {}", error),
+    }
+  }
+}
+
+impl Error for ProtocolError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      Self::Json(error) => Some(error),
+      _ => None,
+    }
+  }
+}
+
+impl From<serde_json::Error> for ProtocolError {
+  fn from(error: serde_json::Error) -> Self {
+    Self::Json(error)
+  }
+}
+
+/// The number of notifications a [`ProtocolSession`] buffers. Once it is
+/// reached, the oldest notification is dropped for each new one.
+pub const MAX_BUFFERED_EVENTS: usize = 1024;
+
+/// The number of responses a [`ProtocolSession`] buffers until they are
+/// taken. Once it is reached, the oldest response is dropped for each new
+/// one, e.g. when the responses to [`ProtocolSession::send`] or
+/// [`ProtocolSession::dispatch_raw`] are never taken.
+pub const MAX_BUFFERED_RESPONSES: usize = 1024;
+
+/// The id of a command sent by [`ProtocolSession::send`].
+#[derive(Debug)]
+pub struct CommandId<C> {
+  id: i32,
+  _command: PhantomData<fn() -> C>,
+}
+
+impl<C> CommandId<C> {
+  pub fn id(&self) -> i32 {
+    self.id
+  }
+}
+
+impl<C> Clone for CommandId<C> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<C> Copy for CommandId<C> {}
+
+/// A session that exchanges typed messages with the inspector.
+///
+/// The methods take `&self`, so a session can be used again while a command
+/// is being dispatched, e.g. by the pause loop of a
+/// [`V8InspectorClientImpl`](crate::inspector::V8InspectorClientImpl) when a
+/// `Runtime.evaluate` hits a breakpoint.
+pub struct ProtocolSession {
+  // Dropped before the channel it sends messages to.
+  session: UnsafeCell<UniqueRef<V8InspectorSession>>,
+  _channel: Box<ProtocolChannel>,
+  inbox: Rc<RefCell<Inbox>>,
+  next_id: Cell<i32>,
+}
+
+impl ProtocolSession {
+  /// Connects a session to `inspector`, for the contexts of
+  /// `context_group_id`.
+  pub fn connect(inspector: &mut V8Inspector, context_group_id: i32) -> Self {
+    let inbox = Rc::new(RefCell::new(Inbox::default()));
+    let mut channel = Box::new(ProtocolChannel {
+      base: ChannelBase::new::<ProtocolChannel>(),
+      inbox: inbox.clone(),
+    });
+    let session = inspector.connect(
+      context_group_id,
+      &mut *channel,
+      StringView::empty(),
+      V8InspectorClientTrustLevel::FullyTrusted,
+    );
+    Self {
+      session: UnsafeCell::new(session),
+      _channel: channel,
+      inbox,
+      next_id: Cell::new(1),
+    }
+  }
+
+  /// Sends a command without waiting for its response.
+  pub fn send<C: Command>(&self, command: &C) -> CommandId<C> {
+    let id = self.next_id.get();
+    self.next_id.set(id + 1);
+    let message = serde_json::json!({
+      "id": id,
+      "method": C::METHOD,
+      "params": command,
+    });
+    self.dispatch_raw(&message.to_string());
+    CommandId {
+      id,
+      _command: PhantomData,
+    }
+  }
+
+  /// Sends a command and returns its response, which most commands send
+  /// before they return.
+  pub fn call<C: Command>(
+    &self,
+    command: &C,
+  ) -> Result<C::Response, ProtocolError> {
+    let id = self.send(command);
+    self
+      .take_response(id)
+      .unwrap_or(Err(ProtocolError::NoResponse))
+  }
+
+  /// Returns the response to a command sent earlier, or `None` if it has not
+  /// arrived yet.
+  pub fn take_response<C: Command>(
+    &self,
+    id: CommandId<C>,
+  ) -> Option<Result<C::Response, ProtocolError>> {
+    let message = self.take_raw_response(id.id)?;
+    Some(parse_response(&message))
+  }
+
+  /// Only buffers the notifications whose method, e.g. `"Debugger.paused"`,
+  /// `filter` accepts. The others are dropped without being parsed. By
+  /// default, all notifications are buffered.
+  ///
+  /// `filter` runs while V8 sends a notification, so it must not use the
+  /// session.
+  pub fn set_event_filter(&self, filter: impl Fn(&str) -> bool + 'static) {
+    self.inbox.borrow_mut().filter = Some(Rc::new(filter));
+  }
+
+  /// Returns the next notification received, if any, or the error it
+  /// couldn't be parsed with.
+  pub fn next_event(&self) -> Option<Result<Event, ProtocolError>> {
+    self.inbox.borrow_mut().events.pop_front()
+  }
+
+  /// Returns the notifications received since the last call.
+  pub fn take_events(&self) -> Vec<Result<Event, ProtocolError>> {
+    self.inbox.borrow_mut().events.drain(..).collect()
+  }
+
+  /// Dispatches a raw JSON message. Its response is only available to
+  /// [`ProtocolSession::take_raw_response`].
+  pub fn dispatch_raw(&self, message: &str) {
+    let session = self.session.get();
+    // SAFETY: The session is only accessed through raw pointers, which stay
+    // valid while `self` is alive.
+    unsafe {
+      (**session)
+        .dispatch_protocol_message(StringView::from(message.as_bytes()))
+    };
+  }
+
+  /// Returns the raw JSON response to a command, or `None` if it has not
+  /// arrived yet.
+  pub fn take_raw_response(&self, id: i32) -> Option<String> {
+    let mut inbox = self.inbox.borrow_mut();
+    let index = inbox
+      .responses
+      .iter()
+      .position(|(call_id, _)| *call_id == id)?;
+    inbox.responses.remove(index).map(|(_, message)| message)
+  }
+
+  /// Pauses on the next JavaScript statement.
+  pub fn schedule_pause_on_next_statement(&self, reason: &str) {
+    let session = self.session.get();
+    unsafe {
+      (**session).schedule_pause_on_next_statement(
+        StringView::from(reason.as_bytes()),
+        StringView::empty(),
+      )
+    };
+  }
+}
+
+fn parse_response<T: DeserializeOwned>(
+  message: &str,
+) -> Result<T, ProtocolError> {
+  #[derive(Deserialize)]
+  struct ErrorObject {
+    code: i64,
+    message: String,
+    data: Option<String>,
+  }
+  #[derive(Deserialize)]
+  struct Response {
+    result: Option<Value>,
+    error: Option<ErrorObject>,
+  }
+  let response: Response = serde_json::from_str(message)?;
+  if let Some(ErrorObject {
+    code,
+    message,
+    data,
+  }) = response.error
+  {
+    return Err(ProtocolError::Response {
+      code,
+      message,
+      data,
+    });
+  }
+  let result = response.result.unwrap_or_else(|| serde_json::json!({}));
+  Ok(serde_json::from_value(result)?)
+}
+
+#[derive(Default)]
+struct Inbox {
+  responses: VecDeque<(i32, String)>,
+  events: VecDeque<Result<Event, ProtocolError>>,
+  #[allow(clippy::type_complexity)]
+  filter: Option<Rc<dyn Fn(&str) -> bool>>,
+}
+
+struct ProtocolChannel {
+  base: ChannelBase,
+  inbox: Rc<RefCell<Inbox>>,
+}
+
+impl ChannelImpl for ProtocolChannel {
+  fn base(&self) -> &ChannelBase {
+    &self.base
+  }
+
+  fn base_mut(&mut self) -> &mut ChannelBase {
+    &mut self.base
+  }
+
+  unsafe fn base_ptr(this: *const Self) -> *const ChannelBase
+  where
+    Self: Sized,
+  {
+    unsafe { addr_of!((*this).base) }
+  }
+
+  fn send_response(&mut self, call_id: i32, message: UniquePtr<StringBuffer>) {
+    let message = message.unwrap().string().to_string();
+    let mut inbox = self.inbox.borrow_mut();
+    if inbox.responses.len() == MAX_BUFFERED_RESPONSES {
+      inbox.responses.pop_front();
+    }
+    inbox.responses.push_back((call_id, message));
+  }
+
+  fn send_notification(&mut self, message: UniquePtr<StringBuffer>) {
+    #[derive(Deserialize)]
+    struct Notification<'a> {
+      method: &'a str,
+    }
+    let message = message.unwrap().string().to_string();
+    let filter = self.inbox.borrow().filter.clone();
+    if let Some(filter) = filter {
+      // V8 only sends well-formed notifications, whose methods have no
+      // escapes.
+      match serde_json::from_str::<Notification>(&message) {
+        Ok(notification) if filter(notification.method) => {}
+        _ => return,
+      }
+    }
+    let event = Event::parse(&message);
+    let mut inbox = self.inbox.borrow_mut();
+    if inbox.events.len() == MAX_BUFFERED_EVENTS {
+      inbox.events.pop_front();
+    }
+    inbox.events.push_back(event);
+  }
+
+  fn flush_protocol_notifications(&mut self) {}
+}
+
+// The domain modules, generated by `tools/inspector_protocol.rs` from V8's
+// protocol definition.
+include!(concat!(env!("OUT_DIR"), "/inspector_protocol.rs"));
@@ -0,0 +1,721 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! A Chrome DevTools server for the inspector, enabled by the
+//! `inspector_server` feature.
+//!
+//! [`InspectorServer`] listens on a loopback port. It serves the discovery
+//! endpoints that DevTools polls (`/json/list` and `/json/version`) and a
+//! WebSocket endpoint that connects each client to a [`V8InspectorSession`].
+//! Network I/O happens on background threads, but protocol messages are
+//! dispatched on the isolate's thread: by [`InspectorServer::poll`], by
+//! [`InspectorServer::wait_for_session`], and while JavaScript is paused, by
+//! the message loop the server runs for V8.
+//!
+//! Only connections to `localhost`, `127.0.0.1` or `[::1]` are accepted, so
+//! that web pages can't reach the server through DNS rebinding. Anyone who
+//! can connect to the port on this machine can run code in the isolate.
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::Ipv4Addr;
+use std::net::Shutdown;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::ptr::addr_of;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::inspector::ChannelBase;
+use crate::inspector::ChannelImpl;
+use crate::inspector::StringBuffer;
+use crate::inspector::StringView;
+use crate::inspector::V8Inspector;
+use crate::inspector::V8InspectorClientTrustLevel;
+use crate::inspector::V8InspectorSession;
+use crate::inspector_host::InspectorHost;
+use crate::inspector_host::PauseHandler;
+use crate::inspector_host::CONTEXT_GROUP_ID;
+use crate::support::UniquePtr;
+use crate::support::UniqueRef;
+use crate::Context;
+use crate::Isolate;
+use crate::Local;
+
+/// The largest protocol message accepted from a client.
+const MAX_MESSAGE_SIZE: u64 = 256 << 20;
+/// The largest HTTP request line and headers accepted from a client.
+const MAX_HEADER_SIZE: u64 = 64 << 10;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+#[derive(Clone, Debug)]
+pub struct InspectorServerOptions {
+  /// The loopback port to listen on. Port 0 picks a free port.
+  pub port: u16,
+  /// The title of the target in DevTools.
+  pub title: String,
+  /// The URL of the target in DevTools, e.g. that of the main script.
+  pub url: String,
+}
+
+impl Default for InspectorServerOptions {
+  fn default() -> Self {
+    Self {
+      port: 9229,
+      title: "rusty_v8".to_owned(),
+      url: String::new(),
+    }
+  }
+}
+
+/// A DevTools server for an isolate. See the [module documentation](self).
+///
+/// The server must be created and used on the isolate's thread, and dropped
+/// before the isolate.
+pub struct InspectorServer {
+  state: Rc<ServerState>,
+  host: InspectorHost,
+  address: SocketAddr,
+  target_id: String,
+  shutdown: Arc<AtomicBool>,
+}
+
+impl InspectorServer {
+  /// Starts a server for `isolate` on `127.0.0.1:<options.port>`.
+  pub fn new(
+    isolate: &mut Isolate,
+    options: InspectorServerOptions,
+  ) -> io::Result<Self> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, options.port))?;
+    let address = listener.local_addr()?;
+    let (sender, receiver) = mpsc::channel();
+    let target = Arc::new(Target {
+      id: random_id(),
+      title: options.title,
+      url: options.url,
+      address,
+    });
+    let shutdown = Arc::new(AtomicBool::new(false));
+    {
+      let target = target.clone();
+      let shutdown = shutdown.clone();
+      thread::Builder::new()
+        .name("v8-inspector-server".to_owned())
+        .spawn(move || {
+          accept_connections(listener, target, sender, shutdown)
+        })?;
+    }
+
+    let state = Rc::new(ServerState {
+      incoming: receiver,
+      inspector: Cell::new(std::ptr::null_mut()),
+      sessions: RefCell::new(HashMap::new()),
+      closed_sessions: RefCell::new(Vec::new()),
+      dispatch_depth: Cell::new(0),
+      paused: Cell::new(false),
+      waiting_for_session: Cell::new(false),
+    });
+    let mut host = InspectorHost::new(isolate, state.clone());
+    state.inspector.set(host.inspector());
+    Ok(Self {
+      state,
+      host,
+      address,
+      target_id: target.id.clone(),
+      shutdown,
+    })
+  }
+
+  /// Returns the address the server listens on.
+  pub fn local_addr(&self) -> SocketAddr {
+    self.address
+  }
+
+  /// Returns the URL of the WebSocket endpoint, as listed by `/json/list`.
+  pub fn websocket_url(&self) -> String {
+    format!("ws://{}/{}", self.address, self.target_id)
+  }
+
+  /// Returns the inspector, e.g. to report exceptions to the clients.
+  pub fn inspector(&mut self) -> &mut V8Inspector {
+    self.host.inspector()
+  }
+
+  /// Reports a new context to the clients. Code running in contexts that
+  /// have not been reported can't be debugged.
+  pub fn context_created(&mut self, context: Local<Context>, name: &str) {
+    self.host.context_created(context, name);
+  }
+
+  /// Reports to the clients that a context is gone.
+  pub fn context_destroyed(&mut self, context: Local<Context>) {
+    self.host.context_destroyed(context);
+  }
+
+  /// Returns whether a client is connected.
+  pub fn has_sessions(&self) -> bool {
+    !self.state.sessions.borrow().is_empty()
+  }
+
+  /// Dispatches the messages received from clients, without blocking.
+  pub fn poll(&mut self) {
+    while let Ok(incoming) = self.state.incoming.try_recv() {
+      self.state.handle(incoming);
+    }
+    self.state.drop_closed_sessions();
+  }
+
+  /// Blocks until a client is ready for the program to run, which DevTools
+  /// signals with `Runtime.runIfWaitingForDebugger` once it has set up its
+  /// breakpoints. Messages are dispatched meanwhile.
+  pub fn wait_for_session(&mut self) {
+    self.wait_for_session_until(None);
+  }
+
+  /// Like [`InspectorServer::wait_for_session`], but gives up after
+  /// `timeout`. Returns whether a client is ready.
+  pub fn wait_for_session_timeout(&mut self, timeout: Duration) -> bool {
+    self.wait_for_session_until(Some(Instant::now() + timeout))
+  }
+
+  fn wait_for_session_until(&mut self, deadline: Option<Instant>) -> bool {
+    self.state.waiting_for_session.set(true);
+    while self.state.waiting_for_session.get() {
+      let incoming = match deadline {
+        Some(deadline) => self
+          .state
+          .incoming
+          .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+          .ok(),
+        None => self.state.incoming.recv().ok(),
+      };
+      match incoming {
+        Some(incoming) => self.state.handle(incoming),
+        None => break,
+      }
+    }
+    let ready = !self.state.waiting_for_session.replace(false);
+    self.state.drop_closed_sessions();
+    ready
+  }
+
+  /// Pauses on the next JavaScript statement, if a client is connected.
+  pub fn break_on_next_statement(&mut self) {
+    let reason = b"debugCommand";
+    for session in self.state.sessions.borrow_mut().values_mut() {
+      session.session.schedule_pause_on_next_statement(
+        StringView::from(&reason[..]),
+        StringView::empty(),
+      );
+    }
+  }
+}
+
+impl Drop for InspectorServer {
+  fn drop(&mut self) {
+    self.shutdown.store(true, Ordering::SeqCst);
+    // Wake up the accepting thread so that it sees the flag.
+    let _ = TcpStream::connect(self.address);
+    // Sessions must be disconnected before the inspector is dropped.
+    self.state.sessions.borrow_mut().clear();
+    self.state.closed_sessions.borrow_mut().clear();
+  }
+}
+
+/// A message from the network threads to the isolate's thread.
+enum Incoming {
+  Connected {
+    id: u32,
+    writer: Arc<Mutex<TcpStream>>,
+  },
+  Message {
+    id: u32,
+    text: String,
+  },
+  Disconnected {
+    id: u32,
+  },
+}
+
+/// The state shared by the server and the client it implements for V8,
+/// which is only used on the isolate's thread.
+struct ServerState {
+  incoming: mpsc::Receiver<Incoming>,
+  inspector: Cell<*mut V8Inspector>,
+  sessions: RefCell<HashMap<u32, ServerSession>>,
+  /// Sessions disconnected while a message was being dispatched, which may
+  /// still be in use further up the stack.
+  closed_sessions: RefCell<Vec<ServerSession>>,
+  dispatch_depth: Cell<usize>,
+  paused: Cell<bool>,
+  waiting_for_session: Cell<bool>,
+}
+
+impl ServerState {
+  fn handle(&self, incoming: Incoming) {
+    match incoming {
+      Incoming::Connected { id, writer } => {
+        let mut channel = Box::new(ServerChannel {
+          base: ChannelBase::new::<ServerChannel>(),
+          writer,
+        });
+        // SAFETY: The inspector outlives the state's use by the server.
+        let inspector = unsafe { &mut *self.inspector.get() };
+        let session = inspector.connect(
+          CONTEXT_GROUP_ID,
+          &mut *channel,
+          StringView::empty(),
+          V8InspectorClientTrustLevel::FullyTrusted,
+        );
+        let session = ServerSession {
+          session,
+          _channel: channel,
+        };
+        self.sessions.borrow_mut().insert(id, session);
+      }
+      Incoming::Message { id, text } => {
+        let session = match self.sessions.borrow_mut().get_mut(&id) {
+          Some(session) => &mut *session.session as *mut V8InspectorSession,
+          None => return,
+        };
+        // The session may be disconnected while the message is dispatched,
+        // but it is only dropped once no message is being dispatched.
+        self.dispatch_depth.set(self.dispatch_depth.get() + 1);
+        unsafe {
+          (*session)
+            .dispatch_protocol_message(StringView::from(text.as_bytes()))
+        };
+        self.dispatch_depth.set(self.dispatch_depth.get() - 1);
+      }
+      Incoming::Disconnected { id } => {
+        if let Some(session) = self.sessions.borrow_mut().remove(&id) {
+          self.closed_sessions.borrow_mut().push(session);
+        }
+        if self.sessions.borrow().is_empty() {
+          // Nobody is left to resume the program.
+          self.paused.set(false);
+        }
+        self.drop_closed_sessions();
+      }
+    }
+  }
+
+  fn drop_closed_sessions(&self) {
+    if self.dispatch_depth.get() == 0 {
+      let closed = std::mem::take(&mut *self.closed_sessions.borrow_mut());
+      drop(closed);
+    }
+  }
+}
+
+impl PauseHandler for ServerState {
+  fn run_message_loop_on_pause(&self) {
+    self.paused.set(true);
+    while self.paused.get() {
+      match self.incoming.recv() {
+        Ok(incoming) => self.handle(incoming),
+        Err(_) => break,
+      }
+    }
+  }
+
+  fn quit_message_loop_on_pause(&self) {
+    self.paused.set(false);
+  }
+
+  fn run_if_waiting_for_debugger(&self) {
+    self.waiting_for_session.set(false);
+  }
+}
+
+struct ServerSession {
+  // Dropped before the channel it sends messages to.
+  session: UniqueRef<V8InspectorSession>,
+  _channel: Box<ServerChannel>,
+}
+
+/// Sends the responses and notifications of a session to its client.
+struct ServerChannel {
+  base: ChannelBase,
+  writer: Arc<Mutex<TcpStream>>,
+}
+
+impl ServerChannel {
+  fn send(&self, message: UniquePtr<StringBuffer>) {
+    let text = message.unwrap().string().to_string();
+    let mut writer = self.writer.lock().unwrap();
+    // A client that went away is reported by its reading thread.
+    let _ = write_frame(&mut *writer, OPCODE_TEXT, text.as_bytes());
+  }
+}
+
+impl ChannelImpl for ServerChannel {
+  fn base(&self) -> &ChannelBase {
+    &self.base
+  }
+
+  fn base_mut(&mut self) -> &mut ChannelBase {
+    &mut self.base
+  }
+
+  unsafe fn base_ptr(this: *const Self) -> *const ChannelBase
+  where
+    Self: Sized,
+  {
+    unsafe { addr_of!((*this).base) }
+  }
+
+  fn send_response(&mut self, _call_id: i32, message: UniquePtr<StringBuffer>) {
+    self.send(message);
+  }
+
+  fn send_notification(&mut self, message: UniquePtr<StringBuffer>) {
+    self.send(message);
+  }
+
+  fn flush_protocol_notifications(&mut self) {}
+}
+
+impl Drop for ServerChannel {
+  fn drop(&mut self) {
+    // Ends the thread reading from the client.
+    let _ = self.writer.lock().unwrap().shutdown(Shutdown::Both);
+  }
+}
+
+/// The debugging target described to DevTools.
+struct Target {
+  id: String,
+  title: String,
+  url: String,
+  address: SocketAddr,
+}
+
+impl Target {
+  fn list_json(&self) -> String {
+    let host = format!("{}/{}", self.address, self.id);
+    format!(
+      concat!(
+        r#"[{{"description":"rusty_v8 instance","#,
+        r#""devtoolsFrontendUrl":"devtools://devtools/bundled/js_app.html"#,
+        r#"?experiments=true&v8only=true&ws={}","#,
+        r#""id":{},"title":{},"type":"node","url":{},"#,
+        r#""webSocketDebuggerUrl":"ws://{}"}}]"#
+      ),
+      host,
+      json_string(&self.id),
+      json_string(&self.title),
+      json_string(&self.url),
+      host,
+    )
+  }
+
+  fn version_json(&self) -> String {
+    format!(
+      r#"{{"Browser":"rusty_v8/{}","Protocol-Version":"1.3","V8-Version":{}}}"#,
+      env!("CARGO_PKG_VERSION"),
+      json_string(crate::V8::get_version()),
+    )
+  }
+}
+
+fn accept_connections(
+  listener: TcpListener,
+  target: Arc<Target>,
+  sender: mpsc::Sender<Incoming>,
+  shutdown: Arc<AtomicBool>,
+) {
+  let mut next_id = 0;
+  for stream in listener.incoming() {
+    if shutdown.load(Ordering::SeqCst) {
+      break;
+    }
+    let Ok(stream) = stream else {
+      continue;
+    };
+    next_id += 1;
+    let id = next_id;
+    let target = target.clone();
+    let sender = sender.clone();
+    thread::spawn(move || {
+      // Errors only affect this connection.
+      let _ = handle_connection(stream, id, &target, &sender);
+    });
+  }
+}
+
+fn handle_connection(
+  stream: TcpStream,
+  id: u32,
+  target: &Target,
+  sender: &mpsc::Sender<Incoming>,
+) -> io::Result<()> {
+  let mut reader = BufReader::new(stream.try_clone()?);
+  let mut writer = stream;
+  // Reading stops at the limit, which ends the headers early.
+  let mut head = (&mut reader).take(MAX_HEADER_SIZE);
+  let mut request_line = String::new();
+  head.read_line(&mut request_line)?;
+  let path = request_line.split(' ').nth(1).unwrap_or("").to_owned();
+  let mut headers = HashMap::new();
+  loop {
+    let mut line = String::new();
+    if head.read_line(&mut line)? == 0 {
+      return Ok(());
+    }
+    let line = line.trim_end();
+    if line.is_empty() {
+      break;
+    }
+    if let Some((name, value)) = line.split_once(':') {
+      headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+    }
+  }
+
+  if headers
+    .get("host")
+    .is_some_and(|host| !is_loopback_host(host))
+  {
+    return respond(&mut writer, "403 Forbidden", "text/plain", "Forbidden");
+  }
+  let upgrade = headers
+    .get("upgrade")
+    .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+  match (path.as_str(), headers.get("sec-websocket-key")) {
+    ("/json" | "/json/list", _) => respond(
+      &mut writer,
+      "200 OK",
+      "application/json; charset=UTF-8",
+      &target.list_json(),
+    ),
+    ("/json/version", _) => respond(
+      &mut writer,
+      "200 OK",
+      "application/json; charset=UTF-8",
+      &target.version_json(),
+    ),
+    (path, Some(key))
+      if upgrade && path.strip_prefix('/') == Some(&target.id) =>
+    {
+      write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        websocket_accept(key)
+      )?;
+      let writer = Arc::new(Mutex::new(writer));
+      let connected = Incoming::Connected {
+        id,
+        writer: writer.clone(),
+      };
+      if sender.send(connected).is_err() {
+        return Ok(());
+      }
+      let result = read_messages(&mut reader, &writer, id, sender);
+      let _ = sender.send(Incoming::Disconnected { id });
+      result
+    }
+    _ => respond(&mut writer, "404 Not Found", "text/plain", "Not Found"),
+  }
+}
+
+/// Forwards the messages of a WebSocket client until it disconnects.
+fn read_messages(
+  reader: &mut impl Read,
+  writer: &Mutex<TcpStream>,
+  id: u32,
+  sender: &mpsc::Sender<Incoming>,
+) -> io::Result<()> {
+  let mut message = Vec::new();
+  loop {
+    let (fin, opcode, payload) = read_frame(reader)?;
+    match opcode {
+      OPCODE_TEXT | OPCODE_CONTINUATION => {
+        message.extend_from_slice(&payload);
+        if message.len() as u64 > MAX_MESSAGE_SIZE {
+          return Err(io::ErrorKind::InvalidData.into());
+        }
+        if fin {
+          let text = String::from_utf8(std::mem::take(&mut message))
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+          if sender.send(Incoming::Message { id, text }).is_err() {
+            return Ok(());
+          }
+        }
+      }
+      OPCODE_PING => {
+        write_frame(&mut *writer.lock().unwrap(), OPCODE_PONG, &payload)?
+      }
+      OPCODE_PONG => {}
+      OPCODE_CLOSE => {
+        let _ = write_frame(&mut *writer.lock().unwrap(), OPCODE_CLOSE, &[]);
+        return Ok(());
+      }
+      // Binary messages are not part of the protocol.
+      _ => return Err(io::ErrorKind::InvalidData.into()),
+    }
+  }
+}
+
+fn respond(
+  writer: &mut impl Write,
+  status: &str,
+  content_type: &str,
+  body: &str,
+) -> io::Result<()> {
+  write!(
+    writer,
+    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+     Connection: close\r\n\r\n{}",
+    status,
+    content_type,
+    body.len(),
+    body
+  )?;
+  writer.flush()
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<(bool, u8, Vec<u8>)> {
+  let mut head = [0; 2];
+  reader.read_exact(&mut head)?;
+  let fin = head[0] & 0x80 != 0;
+  let opcode = head[0] & 0x0f;
+  let length = match head[1] & 0x7f {
+    126 => {
+      let mut length = [0; 2];
+      reader.read_exact(&mut length)?;
+      u16::from_be_bytes(length) as u64
+    }
+    127 => {
+      let mut length = [0; 8];
+      reader.read_exact(&mut length)?;
+      u64::from_be_bytes(length)
+    }
+    length => length as u64,
+  };
+  if length > MAX_MESSAGE_SIZE {
+    return Err(io::ErrorKind::InvalidData.into());
+  }
+  let mut mask = [0; 4];
+  if head[1] & 0x80 != 0 {
+    reader.read_exact(&mut mask)?;
+  }
+  let mut payload = vec![0; length as usize];
+  reader.read_exact(&mut payload)?;
+  for (i, byte) in payload.iter_mut().enumerate() {
+    *byte ^= mask[i % 4];
+  }
+  Ok((fin, opcode, payload))
+}
+
+fn write_frame(
+  writer: &mut impl Write,
+  opcode: u8,
+  payload: &[u8],
+) -> io::Result<()> {
+  let mut head = vec![0x80 | opcode];
+  match payload.len() {
+    length if length < 126 => head.push(length as u8),
+    length if length <= u16::MAX as usize => {
+      head.push(126);
+      head.extend_from_slice(&(length as u16).to_be_bytes());
+    }
+    length => {
+      head.push(127);
+      head.extend_from_slice(&(length as u64).to_be_bytes());
+    }
+  }
+  writer.write_all(&head)?;
+  writer.write_all(payload)?;
+  writer.flush()
+}
+
+/// Computes the `Sec-WebSocket-Accept` header for a `Sec-WebSocket-Key`.
+fn websocket_accept(key: &str) -> String {
+  let mut sha1 = sha1_smol::Sha1::new();
+  sha1.update(key.as_bytes());
+  sha1.update(b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11");
+  encode_base64(&sha1.digest().bytes())
+}
+
+fn encode_base64(input: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+  let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+  for chunk in input.chunks(3) {
+    let bytes = [
+      chunk[0],
+      *chunk.get(1).unwrap_or(&0),
+      *chunk.get(2).unwrap_or(&0),
+    ];
+    let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+    for i in 0..4 {
+      if i <= chunk.len() {
+        output.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+      } else {
+        output.push('=');
+      }
+    }
+  }
+  output
+}
+
+fn is_loopback_host(host: &str) -> bool {
+  let name = match host.strip_prefix('[') {
+    Some(rest) => rest.split(']').next().unwrap_or(""),
+    None => host.split(':').next().unwrap_or(""),
+  };
+  matches!(name, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn json_string(value: &str) -> String {
+  let mut output = String::with_capacity(value.len() + 2);
+  output.push('"');
+  for c in value.chars() {
+    match c {
+      '"' => output.push_str("\\\""),
+      '\\' => output.push_str("\\\\"),
+      c if (c as u32) < 0x20 => {
+        output.push_str(&format!("\\u{:04x}", c as u32))
+      }
+      c => output.push(c),
+    }
+  }
+  output.push('"');
+  output
+}
+
+/// Returns a random UUID-like target id.
+fn random_id() -> String {
+  let [a, b] = [0, 1].map(|i: u64| RandomState::new().hash_one(i));
+  format!(
+    "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+    a >> 32,
+    (a >> 16) & 0xffff,
+    a & 0xfff,
+    (b >> 48) & 0x3fff | 0x8000,
+    b & 0xffff_ffff_ffff,
+  )
+}
@@ -37,6 +37,7 @@ use crate::Module;
 use crate::Object;
 use crate::Promise;
 use crate::PromiseResolver;
+use crate::StackTrace;
 use crate::StartupData;
 use crate::String;
 use crate::Value;
@@ -351,6 +352,122 @@ where
 pub type HostCreateShadowRealmContextCallback =
   for<'s> fn(scope: &mut HandleScope<'s>) -> Option<Local<'s, Context>>;
 
+/// A method of the `console` object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConsoleMethod {
+  Debug,
+  Error,
+  Info,
+  Log,
+  Warn,
+  Dir,
+  DirXml,
+  Table,
+  Trace,
+  Group,
+  GroupCollapsed,
+  GroupEnd,
+  Clear,
+  Count,
+  CountReset,
+  Assert,
+  Profile,
+  ProfileEnd,
+  Time,
+  TimeLog,
+  TimeEnd,
+  TimeStamp,
+}
+
+impl ConsoleMethod {
+  /// Converts the number that `binding.cc` gives a method.
+  fn from_raw(method: i32) -> Option<Self> {
+    Some(match method {
+      0 => Self::Debug,
+      1 => Self::Error,
+      2 => Self::Info,
+      3 => Self::Log,
+      4 => Self::Warn,
+      5 => Self::Dir,
+      6 => Self::DirXml,
+      7 => Self::Table,
+      8 => Self::Trace,
+      9 => Self::Group,
+      10 => Self::GroupCollapsed,
+      11 => Self::GroupEnd,
+      12 => Self::Clear,
+      13 => Self::Count,
+      14 => Self::CountReset,
+      15 => Self::Assert,
+      16 => Self::Profile,
+      17 => Self::ProfileEnd,
+      18 => Self::Time,
+      19 => Self::TimeLog,
+      20 => Self::TimeEnd,
+      21 => Self::TimeStamp,
+      _ => return None,
+    })
+  }
+
+  /// Returns the name of the method, e.g. `"log"` or `"groupEnd"`.
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Debug => "debug",
+      Self::Error => "error",
+      Self::Info => "info",
+      Self::Log => "log",
+      Self::Warn => "warn",
+      Self::Dir => "dir",
+      Self::DirXml => "dirxml",
+      Self::Table => "table",
+      Self::Trace => "trace",
+      Self::Group => "group",
+      Self::GroupCollapsed => "groupCollapsed",
+      Self::GroupEnd => "groupEnd",
+      Self::Clear => "clear",
+      Self::Count => "count",
+      Self::CountReset => "countReset",
+      Self::Assert => "assert",
+      Self::Profile => "profile",
+      Self::ProfileEnd => "profileEnd",
+      Self::Time => "time",
+      Self::TimeLog => "timeLog",
+      Self::TimeEnd => "timeEnd",
+      Self::TimeStamp => "timeStamp",
+    }
+  }
+}
+
+/// The number of frames captured by [`ConsoleMessage::stack_trace`].
+const CONSOLE_STACK_TRACE_LIMIT: usize = 10;
+
+/// A call of a `console` method, as given to a [`ConsoleCallback`].
+pub struct ConsoleMessage<'s> {
+  pub method: ConsoleMethod,
+  /// The arguments of the call, as passed by JavaScript. V8 does not format
+  /// them, nor check them: e.g. the first argument of `console.assert` is
+  /// the condition, and `console.count` may be called without a label.
+  pub args: &'s [Local<'s, Value>],
+  /// The context that called the method.
+  pub context: Local<'s, Context>,
+}
+
+impl ConsoleMessage<'_> {
+  /// Captures the innermost 10 frames of the JavaScript stack that called
+  /// the method. Returns `None` if it was not called from JavaScript.
+  pub fn stack_trace<'t>(
+    &self,
+    scope: &mut HandleScope<'t>,
+  ) -> Option<Local<'t, StackTrace>> {
+    StackTrace::current_stack_trace(scope, CONSOLE_STACK_TRACE_LIMIT)
+  }
+}
+
+/// `ConsoleCallback` is called for every call of a `console` method, on the
+/// thread of the isolate and before the method returns.
+pub type ConsoleCallback =
+  for<'s> fn(scope: &mut HandleScope<'s>, message: ConsoleMessage<'s>);
+
 pub type GcCallbackWithData = extern "C" fn(
   isolate: *mut Isolate,
   r#type: GCType,
@@ -408,6 +525,34 @@ pub type PrepareStackTraceCallback<'s> =
     Local<'s, Array>,
   ) -> PrepareStackTraceCallbackRet;
 
+/// The callback set with [`Isolate::set_prepare_stack_trace_callback`].
+#[cfg(feature = "source_map")]
+struct PrepareStackTraceCallbackSlot(PrepareStackTraceCallback<'static>);
+
+/// Calls a callback returned by [`Isolate::get_prepare_stack_trace_callback`].
+#[cfg(feature = "source_map")]
+pub(crate) fn call_prepare_stack_trace_callback<'s>(
+  callback: PrepareStackTraceCallback<'static>,
+  context: Local<'s, Context>,
+  error: Local<'s, Value>,
+  sites: Local<'s, Array>,
+) -> Option<Local<'s, Value>> {
+  let callback = unsafe {
+    std::mem::transmute::<
+      PrepareStackTraceCallback<'static>,
+      PrepareStackTraceCallback<'s>,
+    >(callback)
+  };
+  #[cfg(target_os = "windows")]
+  let value = {
+    let mut value = std::ptr::null();
+    unsafe { *callback(&mut value, context, error, sites) }
+  };
+  #[cfg(not(target_os = "windows"))]
+  let value = callback(context, error, sites).0;
+  unsafe { Local::from_raw(value) }
+}
+
 pub type UseCounterFeature = v8__Isolate__UseCounterFeature;
 pub type UseCounterCallback = extern "C" fn(&mut Isolate, UseCounterFeature);
 
@@ -495,6 +640,8 @@ extern "C" {
     isolate: *mut Isolate,
     callback: RawHostImportModuleDynamicallyCallback,
   );
+  fn v8__Isolate__HasConsoleDelegate(isolate: *const Isolate) -> bool;
+  fn v8__Isolate__SetConsoleDelegate(isolate: *mut Isolate, enabled: bool);
   #[cfg(not(target_os = "windows"))]
   fn v8__Isolate__SetHostCreateShadowRealmContextCallback(
     isolate: *mut Isolate,
@@ -1020,9 +1167,27 @@ impl Isolate {
     // Note: the C++ API returns a MaybeLocal but V8 asserts at runtime when
     // it's empty. That is, you can't return None and that's why the Rust API
     // expects Local<Value> instead of Option<Local<Value>>.
-    unsafe {
-      v8__Isolate__SetPrepareStackTraceCallback(self, callback.map_fn_to())
-    };
+    let callback = callback.map_fn_to();
+    // Recorded so that a callback installed later can chain to this one.
+    #[cfg(feature = "source_map")]
+    self.set_slot(PrepareStackTraceCallbackSlot(unsafe {
+      std::mem::transmute::<
+        PrepareStackTraceCallback<'s>,
+        PrepareStackTraceCallback<'static>,
+      >(callback)
+    }));
+    unsafe { v8__Isolate__SetPrepareStackTraceCallback(self, callback) };
+  }
+
+  /// Returns the callback set with
+  /// [`Isolate::set_prepare_stack_trace_callback`], if any.
+  #[cfg(feature = "source_map")]
+  pub(crate) fn get_prepare_stack_trace_callback(
+    &self,
+  ) -> Option<PrepareStackTraceCallback<'static>> {
+    self
+      .get_slot::<PrepareStackTraceCallbackSlot>()
+      .map(|slot| slot.0)
   }
 
   /// Set the PromiseHook callback for various promise lifecycle
@@ -1137,6 +1302,36 @@ impl Isolate {
     }
   }
 
+  /// Sets the callback that receives the calls of `console` methods, with
+  /// their arguments, context and stack trace. Without one, the methods do
+  /// nothing unless an inspector is attached.
+  ///
+  /// An isolate has a single console delegate: creating a
+  /// [`V8Inspector`](crate::inspector::V8Inspector) replaces this callback,
+  /// dropping the inspector removes it too, and setting it while an
+  /// inspector exists stops the inspector from reporting console messages.
+  /// [`Isolate::has_console_delegate`] tells whether the callback is in use.
+  pub fn set_console_delegate(&mut self, callback: ConsoleCallback) {
+    self.set_slot(callback);
+    unsafe { v8__Isolate__SetConsoleDelegate(self, true) };
+  }
+
+  /// Removes the callback set by [`Isolate::set_console_delegate`]. The
+  /// console delegate of an inspector is left in place.
+  pub fn remove_console_delegate(&mut self) {
+    self.remove_slot::<ConsoleCallback>();
+    unsafe { v8__Isolate__SetConsoleDelegate(self, false) };
+  }
+
+  /// Returns whether the callback set by [`Isolate::set_console_delegate`]
+  /// receives the calls of `console` methods, i.e. whether no
+  /// [`V8Inspector`](crate::inspector::V8Inspector) has replaced or removed
+  /// it since.
+  pub fn has_console_delegate(&self) -> bool {
+    self.get_slot::<ConsoleCallback>().is_some()
+      && unsafe { v8__Isolate__HasConsoleDelegate(self) }
+  }
+
   /// Sets a callback for counting the number of times a feature of V8 is used.
   #[inline(always)]
   pub fn set_use_counter_callback(&mut self, callback: UseCounterCallback) {
@@ -1474,6 +1669,35 @@ impl Isolate {
   }
 }
 
+#[no_mangle]
+unsafe extern "C" fn v8__ConsoleDelegate__Call(
+  context: Local<Context>,
+  method: i32,
+  args: *const Local<Value>,
+  length: usize,
+) {
+  let Some(method) = ConsoleMethod::from_raw(method) else {
+    return;
+  };
+  let scope = &mut CallbackScope::new(context);
+  let Some(&callback) = scope.get_slot::<ConsoleCallback>() else {
+    return;
+  };
+  let args = if length == 0 {
+    &[]
+  } else {
+    std::slice::from_raw_parts(args, length)
+  };
+  callback(
+    scope,
+    ConsoleMessage {
+      method,
+      args,
+      context,
+    },
+  );
+}
+
 pub(crate) struct IsolateAnnex {
   create_param_allocations: Box<dyn Any>,
   slots: HashMap<TypeId, RawSlot, BuildTypeIdHasher>,
@@ -0,0 +1,408 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::Exception;
+use crate::HandleScope;
+use crate::Local;
+use crate::Message;
+use crate::Object;
+use crate::StackFrame;
+use crate::StackTrace;
+use crate::TryCatch;
+use crate::Value;
+
+/// The maximum number of `cause` and `AggregateError` levels that are
+/// converted. Deeper errors are left out.
+const MAX_NESTING_DEPTH: usize = 16;
+
+/// A JavaScript exception converted into an owned Rust error.
+///
+/// Stack frames are only available if V8 captured a detailed stack trace for
+/// the exception, which requires enabling
+/// [`Isolate::set_capture_stack_trace_for_uncaught_exceptions`](crate::Isolate::set_capture_stack_trace_for_uncaught_exceptions)
+/// before the error object is created. The JavaScript `stack` property is
+/// captured regardless.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsError {
+  /// The `name` property of the exception, e.g. `"TypeError"`.
+  pub name: Option<String>,
+  /// The `message` property of the exception.
+  pub message: Option<String>,
+  /// The message V8 generated for the exception, e.g.
+  /// `"Uncaught TypeError: foo is not a function"`.
+  pub exception_message: String,
+  /// Where the exception was thrown. It is boxed so that a
+  /// `Result<_, JsError>` stays small.
+  pub location: Box<JsErrorLocation>,
+  /// The error stored in the `cause` property of the exception.
+  pub cause: Option<Box<JsError>>,
+  /// The errors wrapped by an `AggregateError`.
+  pub aggregated: Option<Vec<JsError>>,
+}
+
+/// Where a [`JsError`] was thrown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsErrorLocation {
+  /// The `stack` property of the exception.
+  pub stack: Option<String>,
+  pub frames: Vec<JsStackFrame>,
+  /// The line of source code where the exception was thrown.
+  pub source_line: Option<String>,
+  pub script_resource_name: Option<String>,
+  /// The 1-based line number where the exception was thrown.
+  pub line_number: Option<usize>,
+  /// The 0-based column range within `source_line` that caused the exception.
+  pub start_column: Option<usize>,
+  pub end_column: Option<usize>,
+}
+
+/// A single frame of the stack trace of a [`JsError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsStackFrame {
+  pub function_name: Option<String>,
+  /// The script name, or the `//# sourceURL` if the script has no name.
+  pub script_name: Option<String>,
+  /// The 1-based line number.
+  pub line_number: Option<usize>,
+  /// The 1-based column number.
+  pub column_number: Option<usize>,
+  pub is_eval: bool,
+  pub is_constructor: bool,
+  pub is_wasm: bool,
+  pub is_user_javascript: bool,
+}
+
+impl JsStackFrame {
+  pub fn from_stack_frame(
+    scope: &mut HandleScope,
+    frame: Local<StackFrame>,
+  ) -> Self {
+    let function_name = frame
+      .get_function_name(scope)
+      .map(|name| name.to_rust_string_lossy(scope))
+      .filter(|name| !name.is_empty());
+    let script_name = frame
+      .get_script_name_or_source_url(scope)
+      .map(|name| name.to_rust_string_lossy(scope))
+      .filter(|name| !name.is_empty());
+    // V8 reports `Message::kNoLineNumberInfo` and `Message::kNoColumnInfo`,
+    // which are both 0, when the location is unknown.
+    let line_number = Some(frame.get_line_number()).filter(|&n| n > 0);
+    let column_number = Some(frame.get_column()).filter(|&n| n > 0);
+    Self {
+      function_name,
+      script_name,
+      line_number,
+      column_number,
+      is_eval: frame.is_eval(),
+      is_constructor: frame.is_constructor(),
+      is_wasm: frame.is_wasm(),
+      is_user_javascript: frame.is_user_javascript(),
+    }
+  }
+
+  /// Converts every frame of `stack_trace`.
+  pub fn from_stack_trace(
+    scope: &mut HandleScope,
+    stack_trace: Local<StackTrace>,
+  ) -> Vec<Self> {
+    let mut frames = Vec::with_capacity(stack_trace.get_frame_count());
+    for index in 0..stack_trace.get_frame_count() {
+      if let Some(frame) = stack_trace.get_frame(scope, index) {
+        frames.push(Self::from_stack_frame(scope, frame));
+      }
+    }
+    frames
+  }
+}
+
+impl fmt::Display for JsStackFrame {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let location = match (&self.script_name, self.line_number) {
+      (Some(script_name), Some(line)) => match self.column_number {
+        Some(column) => format!("{}:{}:{}", script_name, line, column),
+        None => format!("{}:{}", script_name, line),
+      },
+      (Some(script_name), None) => script_name.clone(),
+      (None, _) => "<anonymous>".to_owned(),
+    };
+    match &self.function_name {
+      Some(name) if self.is_constructor => {
+        write!(f, "new {} ({})", name, location)
+      }
+      Some(name) => write!(f, "{} ({})", name, location),
+      None => f.write_str(&location),
+    }
+  }
+}
+
+impl JsError {
+  /// Converts an exception value, as returned by [`TryCatch::exception`] or
+  /// passed to a promise rejection handler, into a `JsError`.
+  ///
+  /// This reads properties of the exception, which may run JavaScript
+  /// getters. Exceptions thrown while doing so are ignored.
+  pub fn from_exception<'s>(
+    scope: &mut HandleScope<'s>,
+    exception: Local<'s, Value>,
+  ) -> Self {
+    let scope = &mut TryCatch::new(scope);
+    Self::from_exception_inner(scope, exception, &mut Vec::new())
+  }
+
+  /// Creates a `JsError` from an exception and the `Message` V8 created for
+  /// it, e.g. in a message listener. Unlike [`Self::from_exception`], this
+  /// reports the location information stored in `message`.
+  pub fn from_message<'s>(
+    scope: &mut HandleScope<'s>,
+    exception: Local<'s, Value>,
+    message: Local<'s, Message>,
+  ) -> Self {
+    let scope = &mut TryCatch::new(scope);
+    let mut error =
+      Self::from_exception_inner(scope, exception, &mut Vec::new());
+    error.set_message_info(scope, message);
+    error
+  }
+
+  /// Creates an error to be thrown into JavaScript, e.g. by returning it from
+  /// a typed function callback. `name` selects the error constructor; see
+  /// [`Self::throw`].
+  pub fn new(name: impl Into<String>, message: impl Into<String>) -> Self {
+    let name = name.into();
+    let message = message.into();
+    let mut error = Self::with_exception_message(format!("{name}: {message}"));
+    error.name = Some(name);
+    error.message = Some(message);
+    error
+  }
+
+  /// Creates an `Error` with the given message.
+  pub fn error(message: impl Into<String>) -> Self {
+    Self::new("Error", message)
+  }
+
+  /// Creates a `RangeError` with the given message.
+  pub fn range_error(message: impl Into<String>) -> Self {
+    Self::new("RangeError", message)
+  }
+
+  /// Creates a `TypeError` with the given message.
+  pub fn type_error(message: impl Into<String>) -> Self {
+    Self::new("TypeError", message)
+  }
+
+  /// Creates a new exception object from this error and throws it.
+  ///
+  /// Errors named `RangeError`, `ReferenceError`, `SyntaxError` or
+  /// `TypeError` are created with the corresponding constructor. Any other
+  /// error is created as an `Error` whose `name` property is set to the name
+  /// of this error.
+  pub fn throw(&self, scope: &mut HandleScope) {
+    let exception = self.to_exception(scope);
+    scope.throw_exception(exception);
+  }
+
+  fn to_exception<'s>(&self, scope: &mut HandleScope<'s>) -> Local<'s, Value> {
+    let message = self.message.as_deref().unwrap_or(&self.exception_message);
+    let message = crate::String::new(scope, message)
+      .unwrap_or_else(|| crate::String::empty(scope));
+    let name = self.name.as_deref().unwrap_or("Error");
+    match name {
+      "RangeError" => Exception::range_error(scope, message),
+      "ReferenceError" => Exception::reference_error(scope, message),
+      "SyntaxError" => Exception::syntax_error(scope, message),
+      "TypeError" => Exception::type_error(scope, message),
+      "Error" => Exception::error(scope, message),
+      _ => {
+        let exception = Exception::error(scope, message);
+        if let (Ok(object), Some(key), Some(value)) = (
+          Local::<Object>::try_from(exception),
+          crate::String::new(scope, "name"),
+          crate::String::new(scope, name),
+        ) {
+          object.set(scope, key.into(), value.into());
+        }
+        exception
+      }
+    }
+  }
+
+  fn with_exception_message(exception_message: String) -> Self {
+    Self {
+      name: None,
+      message: None,
+      exception_message,
+      location: Default::default(),
+      cause: None,
+      aggregated: None,
+    }
+  }
+
+  fn from_exception_inner<'s>(
+    scope: &mut HandleScope<'s>,
+    exception: Local<'s, Value>,
+    seen: &mut Vec<Local<'s, Value>>,
+  ) -> Self {
+    seen.push(exception);
+
+    let message = Exception::create_message(scope, exception);
+    let mut error = Self::with_exception_message(String::new());
+    error.set_message_info(scope, message);
+
+    if let Ok(object) = Local::<Object>::try_from(exception) {
+      error.name = get_string_property(scope, object, "name");
+      error.message = get_string_property(scope, object, "message");
+      error.location.stack = get_string_property(scope, object, "stack");
+
+      let depth = seen.len();
+      error.cause = get_property(scope, object, "cause")
+        .filter(|cause| !cause.is_undefined())
+        .filter(|cause| depth < MAX_NESTING_DEPTH && !is_seen(seen, *cause))
+        .map(|cause| Box::new(Self::from_exception_inner(scope, cause, seen)));
+
+      // `AggregateError` instances store the wrapped errors in an own `errors`
+      // array.
+      if error.name.as_deref() == Some("AggregateError") {
+        if let Some(errors) = get_property(scope, object, "errors")
+          .and_then(|errors| Local::<crate::Array>::try_from(errors).ok())
+        {
+          let mut aggregated = Vec::new();
+          for index in 0..errors.length() {
+            let Some(inner) = errors.get_index(scope, index) else {
+              continue;
+            };
+            if depth < MAX_NESTING_DEPTH && !is_seen(seen, inner) {
+              aggregated.push(Self::from_exception_inner(scope, inner, seen));
+            }
+          }
+          error.aggregated = Some(aggregated);
+        }
+      }
+    }
+
+    seen.pop();
+    error
+  }
+
+  fn set_message_info(
+    &mut self,
+    scope: &mut HandleScope,
+    message: Local<Message>,
+  ) {
+    self.exception_message = message.get(scope).to_rust_string_lossy(scope);
+    let location = &mut *self.location;
+    location.source_line = message
+      .get_source_line(scope)
+      .map(|line| line.to_rust_string_lossy(scope));
+    location.script_resource_name = message
+      .get_script_resource_name(scope)
+      .filter(|name| !name.is_null_or_undefined())
+      .map(|name| name.to_rust_string_lossy(scope));
+    location.line_number = message.get_line_number(scope);
+    if location.line_number.is_some() {
+      location.start_column = Some(message.get_start_column());
+      location.end_column = Some(message.get_end_column());
+    }
+    if let Some(stack_trace) = message.get_stack_trace(scope) {
+      location.frames = JsStackFrame::from_stack_trace(scope, stack_trace);
+    }
+  }
+}
+
+fn is_seen(seen: &[Local<Value>], value: Local<Value>) -> bool {
+  seen.iter().any(|other| other.strict_equals(value))
+}
+
+fn get_property<'s>(
+  scope: &mut HandleScope<'s>,
+  object: Local<Object>,
+  key: &str,
+) -> Option<Local<'s, Value>> {
+  let key = crate::String::new(scope, key)?;
+  object.get(scope, key.into())
+}
+
+fn get_string_property(
+  scope: &mut HandleScope,
+  object: Local<Object>,
+  key: &str,
+) -> Option<String> {
+  get_property(scope, object, key)
+    .filter(|value| value.is_string())
+    .map(|value| value.to_rust_string_lossy(scope))
+}
+
+impl fmt::Display for JsError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    // The `stack` property already starts with "<name>: <message>".
+    match &self.location.stack {
+      Some(stack) if !stack.is_empty() => f.write_str(stack)?,
+      _ => {
+        f.write_str(&self.exception_message)?;
+        for frame in &self.location.frames {
+          write!(f, "\n    at {}", frame)?;
+        }
+      }
+    }
+    if let Some(aggregated) = &self.aggregated {
+      for error in aggregated {
+        let error = error.to_string().replace('\n', "\n    ");
+        write!(f, "\n    {}", error)?;
+      }
+    }
+    if let Some(cause) = &self.cause {
+      write!(f, "\nCaused by: {}", cause)?;
+    }
+    Ok(())
+  }
+}
+
+impl Error for JsError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    self.cause.as_deref().map(|cause| cause as _)
+  }
+}
+
+impl<'s, 'p: 's, P> TryCatch<'s, P>
+where
+  Self: AsMut<HandleScope<'p, ()>> + AsMut<HandleScope<'p>>,
+{
+  /// Turns the result of an operation performed inside this `TryCatch` into a
+  /// `Result`, converting the caught exception into a [`JsError`] if the
+  /// operation failed. If execution was terminated, or the operation failed
+  /// without throwing, there is no exception to convert and the error only
+  /// carries an `exception_message` saying so.
+  ///
+  /// ```ignore
+  /// let scope = &mut v8::TryCatch::new(scope);
+  /// let value = script.run(scope);
+  /// let value = scope.result(value)?;
+  /// ```
+  pub fn result<T>(
+    &mut self,
+    value: Option<Local<'p, T>>,
+  ) -> Result<Local<'p, T>, JsError> {
+    if let Some(value) = value {
+      return Ok(value);
+    }
+    let terminated = self.has_terminated();
+    let exception = self.exception().filter(|_| !terminated);
+    let message = self.message();
+    let scope: &mut HandleScope<'p> = self.as_mut();
+    Err(match (exception, message) {
+      (Some(exception), Some(message)) => {
+        JsError::from_message(scope, exception, message)
+      }
+      (Some(exception), None) => JsError::from_exception(scope, exception),
+      (None, _) if terminated => {
+        JsError::with_exception_message("execution terminated".to_owned())
+      }
+      (None, _) => JsError::with_exception_message(
+        "operation failed without an exception".to_owned(),
+      ),
+    })
+  }
+}
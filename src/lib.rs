@@ -31,7 +31,9 @@ mod array_buffer;
 mod array_buffer_view;
 mod bigint;
 mod binding;
+pub mod class;
 mod context;
+mod convert;
 pub use context::ContextOptions;
 pub mod cppgc;
 mod data;
@@ -45,19 +47,25 @@ mod function;
 mod gc;
 mod get_property_names_args_builder;
 mod handle;
+mod host_object;
 pub mod icu;
+#[cfg(any(feature = "inspector_protocol", feature = "inspector_server"))]
+mod inspector_host;
 mod isolate;
 mod isolate_create_params;
+mod js_error;
 mod microtask;
 mod module;
 mod name;
 mod number;
 mod object;
+mod object_tag;
 mod platform;
 mod primitive_array;
 mod primitives;
 mod private;
 mod promise;
+mod promise_future;
 mod property_attribute;
 mod property_descriptor;
 mod property_filter;
@@ -79,16 +87,32 @@ mod value;
 mod value_deserializer;
 mod value_serializer;
 mod wasm;
+mod wrapped;
 
 pub mod inspector;
+#[cfg(feature = "inspector_protocol")]
+pub mod inspector_coverage;
+#[cfg(feature = "inspector_protocol")]
+pub mod inspector_debugger;
+#[cfg(feature = "inspector_protocol")]
+pub mod inspector_protocol;
+#[cfg(feature = "inspector_server")]
+pub mod inspector_server;
 pub mod json;
 pub mod script_compiler;
+#[cfg(feature = "source_map")]
+pub mod source_map;
 // This module is intentionally named "V8" rather than "v8" to match the
 // C++ namespace "v8::V8".
 #[allow(non_snake_case)]
 pub mod V8;
 
 pub use array_buffer::*;
+pub use class::Class;
+pub use convert::FromV8;
+pub use convert::ToV8;
+pub use convert::TypedFunctionResult;
+pub use convert::TypedFunctionTag;
 pub use data::*;
 pub use exception::*;
 pub use external_references::ExternalReference;
@@ -101,6 +125,11 @@ pub use handle::Handle;
 pub use handle::Local;
 pub use handle::TracedReference;
 pub use handle::Weak;
+pub use host_object::HostObject;
+pub use host_object::PropertyKey;
+pub use isolate::ConsoleCallback;
+pub use isolate::ConsoleMessage;
+pub use isolate::ConsoleMethod;
 pub use isolate::GarbageCollectionType;
 pub use isolate::HeapStatistics;
 pub use isolate::HostCreateShadowRealmContextCallback;
@@ -124,6 +153,9 @@ pub use isolate::UseCounterCallback;
 pub use isolate::UseCounterFeature;
 pub use isolate::WasmAsyncSuccess;
 pub use isolate_create_params::CreateParams;
+pub use js_error::JsError;
+pub use js_error::JsErrorLocation;
+pub use js_error::JsStackFrame;
 pub use microtask::MicrotaskQueue;
 pub use module::*;
 pub use object::*;
@@ -132,7 +164,14 @@ pub use platform::new_single_threaded_default_platform;
 pub use platform::new_unprotected_default_platform;
 pub use platform::Platform;
 pub use primitives::*;
-pub use promise::{PromiseRejectEvent, PromiseRejectMessage, PromiseState};
+pub use promise::{
+  PromiseRejectEvent, PromiseRejectMessage, PromiseState, UnhandledRejection,
+  UnhandledRejectionTracker,
+};
+pub use promise_future::future_to_promise;
+pub use promise_future::poll_spawned_futures;
+pub use promise_future::run_event_loop_until;
+pub use promise_future::PromiseFuture;
 pub use property_attribute::*;
 pub use property_descriptor::*;
 pub use property_filter::*;
@@ -150,8 +189,10 @@ pub use script_compiler::CachedData;
 pub use snapshot::FunctionCodeHandling;
 pub use snapshot::StartupData;
 pub use string::Encoding;
+pub use string::ExternalStringBuffer;
 pub use string::NewStringType;
 pub use string::OneByteConst;
+pub use string::StringView;
 pub use string::ValueView;
 pub use string::ValueViewData;
 pub use string::WriteOptions;
@@ -168,6 +209,10 @@ pub use value_serializer::ValueSerializerHelper;
 pub use value_serializer::ValueSerializerImpl;
 pub use wasm::CompiledWasmModule;
 pub use wasm::WasmStreaming;
+pub use wrapped::Wrapped;
+
+#[cfg(feature = "macros")]
+pub use v8_macros::class;
 
 /// https://v8.dev/docs/version-numbers
 pub const MAJOR_VERSION: u32 = binding::v8__MAJOR_VERSION;
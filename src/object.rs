@@ -7,6 +7,7 @@ use crate::support::int;
 use crate::support::MapFnTo;
 use crate::support::Maybe;
 use crate::support::MaybeBool;
+use crate::template::sealed::IntoAccessorConfiguration;
 use crate::AccessorConfiguration;
 use crate::AccessorNameGetterCallback;
 use crate::AccessorNameSetterCallback;
@@ -37,6 +38,7 @@ use std::ptr::null;
 
 extern "C" {
   fn v8__Object__New(isolate: *mut Isolate) -> *const Object;
+  fn v8__Object__GetIsolate(this: *const Object) -> *mut Isolate;
   fn v8__Object__New__with_prototype_and_properties(
     isolate: *mut Isolate,
     prototype_or_null: *const Value,
@@ -561,12 +563,13 @@ impl Object {
     )
   }
   #[inline(always)]
-  pub fn set_accessor_with_configuration(
+  pub fn set_accessor_with_configuration<'s>(
     &self,
     scope: &mut HandleScope,
     name: Local<Name>,
-    configuration: AccessorConfiguration,
+    configuration: impl IntoAccessorConfiguration<'s>,
   ) -> Option<bool> {
+    let configuration = configuration.into_accessor_configuration();
     unsafe {
       v8__Object__SetAccessor(
         self,
@@ -591,6 +594,12 @@ impl Object {
     unsafe { NonZeroI32::new_unchecked(v8__Object__GetIdentityHash(self)) }
   }
 
+  /// Returns the isolate that owns this object.
+  #[inline(always)]
+  pub(crate) fn get_isolate_ptr(&self) -> *mut Isolate {
+    unsafe { v8__Object__GetIsolate(self) }
+  }
+
   /// Returns the context in which the object was created.
   #[inline(always)]
   pub fn get_creation_context<'s>(
@@ -0,0 +1,73 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Tags that mark the objects owning a Rust value, so that pointers stored in
+//! internal fields by other code are never dereferenced.
+
+use std::ffi::c_void;
+
+use crate::Object;
+
+/// A marker whose address is stored in internal field [`Self::TAG_FIELD`] of
+/// the objects it tags, next to a pointer to their Rust value in
+/// [`Self::VALUE_FIELD`]. Each user declares its own `static` tag.
+///
+/// V8 only stores aligned pointers in internal fields, so the tag is aligned
+/// even though it holds no data.
+#[repr(align(8))]
+pub(crate) struct ObjectTag {
+  // A zero-sized tag could share its address with another static.
+  _byte: u8,
+}
+
+impl ObjectTag {
+  /// The internal field that holds the address of the tag.
+  pub(crate) const TAG_FIELD: i32 = 0;
+  /// The internal field that holds the value of a tagged object.
+  pub(crate) const VALUE_FIELD: i32 = 1;
+  /// The number of internal fields used by tagged objects.
+  pub(crate) const FIELD_COUNT: usize = 2;
+
+  pub(crate) const fn new() -> Self {
+    Self { _byte: 0 }
+  }
+
+  fn as_ptr(&'static self) -> *const c_void {
+    let ptr = self as *const Self as *const c_void;
+    debug_assert!(ptr as usize & 1 == 0, "unaligned object tag");
+    ptr
+  }
+
+  /// Whether `object` is tagged with this tag.
+  pub(crate) fn is_tagged(&'static self, object: &Object) -> bool {
+    object.internal_field_count() >= Self::FIELD_COUNT
+      && unsafe {
+        object.get_aligned_pointer_from_internal_field(Self::TAG_FIELD)
+      } == self.as_ptr()
+  }
+
+  /// Tags `object` and stores `value` in it.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `object` has fewer than [`Self::FIELD_COUNT`] internal fields.
+  pub(crate) fn tag(&'static self, object: &Object, value: *const c_void) {
+    assert!(
+      object.internal_field_count() >= Self::FIELD_COUNT,
+      "object has too few internal fields to be tagged"
+    );
+    object
+      .set_aligned_pointer_in_internal_field(Self::TAG_FIELD, self.as_ptr());
+    object.set_aligned_pointer_in_internal_field(Self::VALUE_FIELD, value);
+  }
+
+  /// Returns the value stored by [`Self::tag`], or `None` if `object` isn't
+  /// tagged with this tag.
+  pub(crate) fn value(&'static self, object: &Object) -> Option<*const c_void> {
+    if !self.is_tagged(object) {
+      return None;
+    }
+    Some(unsafe {
+      object.get_aligned_pointer_from_internal_field(Self::VALUE_FIELD)
+    })
+  }
+}
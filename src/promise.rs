@@ -1,12 +1,23 @@
+use std::cell::Cell;
+use std::cell::RefCell;
 use std::marker::PhantomData;
+use std::rc::Rc;
 
+use crate::scope::CallbackScope;
 use crate::support::MaybeBool;
+use crate::Array;
 use crate::Context;
+use crate::Exception;
 use crate::Function;
+use crate::Global;
 use crate::HandleScope;
+use crate::Isolate;
 use crate::Local;
+use crate::Object;
 use crate::Promise;
 use crate::PromiseResolver;
+use crate::PropertyAttribute;
+use crate::String;
 use crate::Value;
 
 extern "C" {
@@ -45,6 +56,9 @@ extern "C" {
     on_fulfilled: *const Function,
     on_rejected: *const Function,
   ) -> *const Promise;
+  fn v8__Promise__AggregateErrorFunction(
+    context: *const Context,
+  ) -> *const Function;
 
   fn v8__PromiseRejectMessage__GetPromise(
     this: *const PromiseRejectMessage,
@@ -140,6 +154,215 @@ impl Promise {
       })
     }
   }
+
+  /// Creates a promise that is fulfilled with an array of the fulfillment
+  /// values once all `promises` are fulfilled, or rejected as soon as one of
+  /// them is rejected. Equivalent to `Promise.all(promises)`.
+  #[inline(always)]
+  pub fn all<'s, 'a>(
+    scope: &mut HandleScope<'s>,
+    promises: impl IntoIterator<Item = Local<'a, Promise>>,
+  ) -> Option<Local<'s, Promise>> {
+    Self::combine(scope, Combinator::All, promises)
+  }
+
+  /// Creates a promise that is fulfilled once all `promises` are settled, with
+  /// an array of `{ status, value }` or `{ status, reason }` objects.
+  /// Equivalent to `Promise.allSettled(promises)`.
+  #[inline(always)]
+  pub fn all_settled<'s, 'a>(
+    scope: &mut HandleScope<'s>,
+    promises: impl IntoIterator<Item = Local<'a, Promise>>,
+  ) -> Option<Local<'s, Promise>> {
+    Self::combine(scope, Combinator::AllSettled, promises)
+  }
+
+  /// Creates a promise that is fulfilled as soon as one of `promises` is
+  /// fulfilled, or rejected with an `AggregateError` once all of them are
+  /// rejected. Equivalent to `Promise.any(promises)`.
+  #[inline(always)]
+  pub fn any<'s, 'a>(
+    scope: &mut HandleScope<'s>,
+    promises: impl IntoIterator<Item = Local<'a, Promise>>,
+  ) -> Option<Local<'s, Promise>> {
+    Self::combine(scope, Combinator::Any, promises)
+  }
+
+  /// Creates a promise that settles in the same way as the first of
+  /// `promises` to settle. Equivalent to `Promise.race(promises)`.
+  #[inline(always)]
+  pub fn race<'s, 'a>(
+    scope: &mut HandleScope<'s>,
+    promises: impl IntoIterator<Item = Local<'a, Promise>>,
+  ) -> Option<Local<'s, Promise>> {
+    Self::combine(scope, Combinator::Race, promises)
+  }
+
+  /// Creates a promise that is settled by `combinator` from the outcomes of
+  /// `promises`. The reactions are native functions, so scripts that replace
+  /// `Promise` or its methods do not change the result.
+  fn combine<'s, 'a>(
+    scope: &mut HandleScope<'s>,
+    combinator: Combinator,
+    promises: impl IntoIterator<Item = Local<'a, Promise>>,
+  ) -> Option<Local<'s, Promise>> {
+    let promises = promises.into_iter().collect::<Vec<_>>();
+    let resolver = PromiseResolver::new(scope)?;
+    let state = Rc::new(CombinedState {
+      combinator,
+      resolver: Global::new(scope, resolver),
+      results: RefCell::new(vec![None; promises.len()]),
+      remaining: Cell::new(promises.len()),
+    });
+    if promises.is_empty() {
+      state.finish(scope)?;
+    }
+    for (index, promise) in promises.into_iter().enumerate() {
+      let on_fulfilled = state.clone().reaction(scope, index, true)?;
+      let on_rejected = state.clone().reaction(scope, index, false)?;
+      promise.then2(scope, on_fulfilled, on_rejected)?;
+    }
+    Some(resolver.get_promise(scope))
+  }
+}
+
+/// The promise combinators of [`Promise`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Combinator {
+  All,
+  AllSettled,
+  Any,
+  Race,
+}
+
+/// The state shared by the reactions of a promise created by
+/// [`Promise::combine`].
+struct CombinedState {
+  combinator: Combinator,
+  resolver: Global<PromiseResolver>,
+  /// The fulfillment values for `all`, the result objects for `allSettled`
+  /// and the rejection reasons for `any`, by index.
+  results: RefCell<Vec<Option<Global<Value>>>>,
+  remaining: Cell<usize>,
+}
+
+impl CombinedState {
+  /// Creates the reaction to the fulfillment or rejection of the promise at
+  /// `index`.
+  fn reaction<'s>(
+    self: Rc<Self>,
+    scope: &mut HandleScope<'s>,
+    index: usize,
+    fulfilled: bool,
+  ) -> Option<Local<'s, Function>> {
+    Function::new_closure(scope, move |scope, args, _| {
+      self.settle(scope, index, fulfilled, args.get(0));
+    })
+  }
+
+  fn settle(
+    &self,
+    scope: &mut HandleScope,
+    index: usize,
+    fulfilled: bool,
+    value: Local<Value>,
+  ) -> Option<()> {
+    let resolver = Local::new(scope, &self.resolver);
+    match (self.combinator, fulfilled) {
+      (Combinator::Any | Combinator::Race, true) => {
+        resolver.resolve(scope, value)?;
+      }
+      (Combinator::All | Combinator::Race, false) => {
+        resolver.reject(scope, value)?;
+      }
+      (Combinator::All, true) | (Combinator::Any, false) => {
+        self.record(scope, index, value)?;
+      }
+      (Combinator::AllSettled, _) => {
+        let (status, key) = if fulfilled {
+          ("fulfilled", "value")
+        } else {
+          ("rejected", "reason")
+        };
+        let result = Object::new(scope);
+        let status_key = String::new(scope, "status")?;
+        let status = String::new(scope, status)?;
+        result.create_data_property(scope, status_key.into(), status.into())?;
+        let key = String::new(scope, key)?;
+        result.create_data_property(scope, key.into(), value)?;
+        self.record(scope, index, result.into())?;
+      }
+    }
+    Some(())
+  }
+
+  /// Records the result at `index`, and settles the promise once every
+  /// result is known.
+  fn record(
+    &self,
+    scope: &mut HandleScope,
+    index: usize,
+    value: Local<Value>,
+  ) -> Option<()> {
+    self.results.borrow_mut()[index] = Some(Global::new(scope, value));
+    self.remaining.set(self.remaining.get() - 1);
+    if self.remaining.get() == 0 {
+      self.finish(scope)?;
+    }
+    Some(())
+  }
+
+  /// Settles the promise once every result is known: `all` and `allSettled`
+  /// are fulfilled with the results and `any` is rejected with an
+  /// `AggregateError`. `race` stays pending.
+  fn finish(&self, scope: &mut HandleScope) -> Option<()> {
+    let results = self
+      .results
+      .borrow()
+      .iter()
+      .map(|result| Local::new(scope, result.as_ref().unwrap()))
+      .collect::<Vec<_>>();
+    let results = Array::new_with_elements(scope, &results);
+    let resolver = Local::new(scope, &self.resolver);
+    match self.combinator {
+      Combinator::All | Combinator::AllSettled => {
+        resolver.resolve(scope, results.into())?;
+      }
+      Combinator::Any => {
+        let error = aggregate_error(scope, results)?;
+        resolver.reject(scope, error)?;
+      }
+      Combinator::Race => {}
+    }
+    Some(())
+  }
+}
+
+/// Creates an `AggregateError` with the given errors, as `Promise.any` does,
+/// without going through the global `AggregateError` constructor.
+fn aggregate_error<'s>(
+  scope: &mut HandleScope<'s>,
+  errors: Local<Array>,
+) -> Option<Local<'s, Value>> {
+  let constructor = unsafe {
+    scope.cast_local(|sd| {
+      v8__Promise__AggregateErrorFunction(sd.get_current_context())
+    })
+  }?;
+  let key = String::new(scope, "prototype")?;
+  let prototype = constructor.get(scope, key.into())?;
+  let message = String::new(scope, "All promises were rejected")?;
+  let error = Exception::error(scope, message);
+  let error = Local::<Object>::try_from(error).ok()?;
+  error.set_prototype(scope, prototype)?;
+  let key = String::new(scope, "errors")?;
+  error.define_own_property(
+    scope,
+    key.into(),
+    errors.into(),
+    PropertyAttribute::DONT_ENUM,
+  )?;
+  Some(error.into())
 }
 
 impl PromiseResolver {
@@ -231,3 +454,97 @@ impl<'msg> PromiseRejectMessage<'msg> {
     unsafe { Local::from_raw(v8__PromiseRejectMessage__GetValue(self)) }
   }
 }
+
+/// A promise that was rejected without a handler, and didn't get one before
+/// [`UnhandledRejectionTracker::take_unhandled`] was called.
+#[derive(Debug)]
+pub struct UnhandledRejection {
+  pub promise: Global<Promise>,
+  pub reason: Global<Value>,
+}
+
+/// Keeps track of rejected promises that have no rejection handler.
+///
+/// V8 reports a `PromiseRejectWithNoHandler` event as soon as a promise
+/// without handlers is rejected, and a `PromiseHandlerAddedAfterReject` event
+/// if a handler is attached later on, which commonly happens within the same
+/// microtask checkpoint. The tracker pairs these events up, so that after a
+/// checkpoint [`Self::take_unhandled`] only returns the rejections that are
+/// still unhandled.
+///
+/// Either feed it events from your own promise reject callback through
+/// [`Self::on_promise_reject`], or let [`Self::install`] set one up that
+/// stores the tracker in an isolate slot.
+#[derive(Debug, Default)]
+pub struct UnhandledRejectionTracker {
+  pending: Vec<UnhandledRejection>,
+}
+
+impl UnhandledRejectionTracker {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Stores a new tracker in an isolate slot and installs a promise reject
+  /// callback that feeds it. This replaces any previously set promise reject
+  /// callback.
+  pub fn install(isolate: &mut Isolate) {
+    isolate.set_slot(Self::new());
+    isolate.set_promise_reject_callback(Self::promise_reject_callback);
+  }
+
+  /// Returns the tracker installed with [`Self::install`].
+  pub fn get(isolate: &mut Isolate) -> Option<&mut Self> {
+    isolate.get_slot_mut::<Self>()
+  }
+
+  extern "C" fn promise_reject_callback(message: PromiseRejectMessage) {
+    let scope = &mut unsafe { CallbackScope::new(&message) };
+    let Some(mut tracker) = scope.remove_slot::<Self>() else {
+      return;
+    };
+    tracker.on_promise_reject(scope, &message);
+    scope.set_slot(tracker);
+  }
+
+  /// Records a promise reject event.
+  pub fn on_promise_reject(
+    &mut self,
+    isolate: &mut Isolate,
+    message: &PromiseRejectMessage,
+  ) {
+    let promise = message.get_promise();
+    match message.get_event() {
+      PromiseRejectEvent::PromiseRejectWithNoHandler => {
+        let reason = message
+          .get_value()
+          .unwrap_or_else(|| crate::undefined(isolate).into());
+        let reason = Global::new(isolate, reason);
+        self.pending.push(UnhandledRejection {
+          promise: Global::new(isolate, promise),
+          reason,
+        });
+      }
+      PromiseRejectEvent::PromiseHandlerAddedAfterReject => {
+        self
+          .pending
+          .retain(|rejection| rejection.promise != promise);
+      }
+      PromiseRejectEvent::PromiseRejectAfterResolved
+      | PromiseRejectEvent::PromiseResolveAfterResolved => {}
+    }
+  }
+
+  /// Returns whether there are rejections that haven't been handled yet.
+  pub fn has_unhandled(&self) -> bool {
+    !self.pending.is_empty()
+  }
+
+  /// Removes and returns the rejections that haven't been handled so far, in
+  /// the order in which the promises were rejected. Call this after a
+  /// microtask checkpoint, when handlers attached during that checkpoint have
+  /// already been accounted for.
+  pub fn take_unhandled(&mut self) -> Vec<UnhandledRejection> {
+    std::mem::take(&mut self.pending)
+  }
+}
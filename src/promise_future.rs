@@ -0,0 +1,322 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Bridges between JavaScript promises and Rust futures.
+//!
+//! A [`PromiseFuture`] resolves once the `Promise` it was created from
+//! settles, and [`future_to_promise`] exposes a Rust future to JavaScript as a
+//! `Promise`. Neither makes progress on its own: promise reactions only run
+//! during a microtask checkpoint, and spawned futures are only polled by
+//! [`poll_spawned_futures`]. [`run_event_loop_until`] drives both, together
+//! with the platform's message loop, until a given future completes.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::mem::replace;
+use std::mem::take;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Poll;
+use std::task::Wake;
+use std::task::Waker;
+use std::thread::Thread;
+
+use crate::External;
+use crate::Function;
+use crate::FunctionCallbackArguments;
+use crate::Global;
+use crate::HandleScope;
+use crate::Local;
+use crate::Platform;
+use crate::Promise;
+use crate::PromiseResolver;
+use crate::PromiseState;
+use crate::ReturnValue;
+use crate::Value;
+use crate::Weak;
+
+type PromiseResult = Result<Global<Value>, Global<Value>>;
+
+enum PromiseFutureState {
+  Pending(Option<Waker>),
+  Settled(PromiseResult),
+  Taken,
+}
+
+/// A future that completes when a JavaScript `Promise` settles.
+///
+/// The output is `Ok` with the fulfillment value, or `Err` with the rejection
+/// reason. Creating a `PromiseFuture` for a pending promise attaches reaction
+/// handlers to it, so a rejection observed this way counts as handled.
+///
+/// The reaction handlers run during a microtask checkpoint on the isolate's
+/// thread, so the future only completes if the embedder keeps performing
+/// checkpoints, e.g. by awaiting it through [`run_event_loop_until`].
+pub struct PromiseFuture {
+  state: Rc<RefCell<PromiseFutureState>>,
+}
+
+impl PromiseFuture {
+  pub fn new(scope: &mut HandleScope, promise: Local<Promise>) -> Self {
+    let state = match promise.state() {
+      PromiseState::Pending => PromiseFutureState::Pending(None),
+      PromiseState::Fulfilled => {
+        let value = promise.result(scope);
+        PromiseFutureState::Settled(Ok(Global::new(scope, value)))
+      }
+      PromiseState::Rejected => {
+        let reason = promise.result(scope);
+        PromiseFutureState::Settled(Err(Global::new(scope, reason)))
+      }
+    };
+    let state = Rc::new(RefCell::new(state));
+
+    if promise.state() == PromiseState::Pending {
+      let reaction = Box::into_raw(Box::new(PromiseReaction {
+        state: state.clone(),
+        // Gets replaced below, once the `External` exists.
+        self_weak: Weak::empty(scope),
+      }));
+      let data = External::new(scope, reaction as *mut _);
+
+      // Both handler functions share `data`, so once it has been collected
+      // neither of them can run anymore and the reaction can be dropped.
+      let self_weak = Weak::with_guaranteed_finalizer(
+        scope,
+        data,
+        Box::new(move || {
+          // SAFETY: The finalizer runs at most once, after the `External`
+          // (and thus every function that could access the reaction) has
+          // been collected.
+          let _ = unsafe { Box::from_raw(reaction) };
+        }),
+      );
+      // SAFETY: The reaction can only be dropped by the finalizer above, which
+      // cannot run while `data` is still alive.
+      unsafe { (*reaction).self_weak = self_weak };
+
+      let on_fulfilled = Function::builder(PromiseReaction::on_fulfilled)
+        .data(data.into())
+        .build(scope)
+        .unwrap();
+      let on_rejected = Function::builder(PromiseReaction::on_rejected)
+        .data(data.into())
+        .build(scope)
+        .unwrap();
+      promise.then2(scope, on_fulfilled, on_rejected);
+    }
+
+    Self { state }
+  }
+}
+
+impl Future for PromiseFuture {
+  type Output = PromiseResult;
+
+  fn poll(
+    self: Pin<&mut Self>,
+    cx: &mut std::task::Context<'_>,
+  ) -> Poll<Self::Output> {
+    let mut state = self.state.borrow_mut();
+    match replace(&mut *state, PromiseFutureState::Taken) {
+      PromiseFutureState::Pending(_) => {
+        *state = PromiseFutureState::Pending(Some(cx.waker().clone()));
+        Poll::Pending
+      }
+      PromiseFutureState::Settled(result) => Poll::Ready(result),
+      PromiseFutureState::Taken => {
+        panic!("PromiseFuture polled after completion")
+      }
+    }
+  }
+}
+
+struct PromiseReaction {
+  state: Rc<RefCell<PromiseFutureState>>,
+  self_weak: Weak<External>,
+}
+
+impl PromiseReaction {
+  fn on_fulfilled(
+    scope: &mut HandleScope,
+    args: FunctionCallbackArguments,
+    _rv: ReturnValue,
+  ) {
+    let value = Global::new(scope, args.get(0));
+    Self::settle(args, Ok(value));
+  }
+
+  fn on_rejected(
+    scope: &mut HandleScope,
+    args: FunctionCallbackArguments,
+    _rv: ReturnValue,
+  ) {
+    let reason = Global::new(scope, args.get(0));
+    Self::settle(args, Err(reason));
+  }
+
+  fn settle(args: FunctionCallbackArguments, result: PromiseResult) {
+    let data = args.data().cast::<External>();
+    // SAFETY: `data` is alive, so the reaction hasn't been finalized yet.
+    let reaction = unsafe { &*(data.value() as *const Self) };
+    debug_assert!(!reaction.self_weak.is_empty());
+    let previous = replace(
+      &mut *reaction.state.borrow_mut(),
+      PromiseFutureState::Settled(result),
+    );
+    if let PromiseFutureState::Pending(Some(waker)) = previous {
+      waker.wake();
+    }
+  }
+}
+
+type SettleFn = Box<dyn FnOnce(&mut HandleScope)>;
+
+/// Futures passed to [`future_to_promise`] that haven't completed yet. Stored
+/// in an isolate slot.
+#[derive(Default)]
+struct SpawnedFutures(Vec<Pin<Box<dyn Future<Output = SettleFn>>>>);
+
+/// Exposes a Rust future to JavaScript as a `Promise`.
+///
+/// The future is polled on the isolate's thread by [`poll_spawned_futures`].
+/// Once it completes, `settle` converts its output into the value the promise
+/// is fulfilled with (`Ok`) or rejected with (`Err`).
+pub fn future_to_promise<'s, T, F, S>(
+  scope: &mut HandleScope<'s>,
+  future: F,
+  settle: S,
+) -> Local<'s, Promise>
+where
+  T: 'static,
+  F: Future<Output = T> + 'static,
+  S: for<'a> FnOnce(
+      &mut HandleScope<'a>,
+      T,
+    ) -> Result<Local<'a, Value>, Local<'a, Value>>
+    + 'static,
+{
+  let resolver = PromiseResolver::new(scope).unwrap();
+  let promise = resolver.get_promise(scope);
+  let resolver = Global::new(scope, resolver);
+
+  let task = async move {
+    let output = future.await;
+    let settle_fn: SettleFn = Box::new(move |scope| {
+      let resolver = Local::new(scope, resolver);
+      match settle(scope, output) {
+        Ok(value) => resolver.resolve(scope, value),
+        Err(reason) => resolver.reject(scope, reason),
+      };
+    });
+    settle_fn
+  };
+
+  if scope.get_slot::<SpawnedFutures>().is_none() {
+    scope.set_slot(SpawnedFutures::default());
+  }
+  scope
+    .get_slot_mut::<SpawnedFutures>()
+    .unwrap()
+    .0
+    .push(Box::pin(task));
+  promise
+}
+
+/// Polls every future spawned with [`future_to_promise`] once, and settles the
+/// promises of those that completed.
+///
+/// Returns `Poll::Ready(())` if no spawned futures remain.
+pub fn poll_spawned_futures(
+  scope: &mut HandleScope,
+  waker: &Waker,
+) -> Poll<()> {
+  let pending = match scope.get_slot_mut::<SpawnedFutures>() {
+    Some(spawned) => take(&mut spawned.0),
+    None => return Poll::Ready(()),
+  };
+
+  let cx = &mut std::task::Context::from_waker(waker);
+  let mut still_pending = Vec::with_capacity(pending.len());
+  for mut task in pending {
+    match task.as_mut().poll(cx) {
+      Poll::Ready(settle) => {
+        let scope = &mut HandleScope::new(scope);
+        settle(scope);
+      }
+      Poll::Pending => still_pending.push(task),
+    }
+  }
+
+  // Settling a promise may have spawned new futures in the meantime.
+  let spawned = scope.get_slot_mut::<SpawnedFutures>().unwrap();
+  still_pending.append(&mut spawned.0);
+  spawned.0 = still_pending;
+  if spawned.0.is_empty() {
+    Poll::Ready(())
+  } else {
+    Poll::Pending
+  }
+}
+
+struct ThreadWaker {
+  thread: Thread,
+  woken: AtomicBool,
+}
+
+impl Wake for ThreadWaker {
+  fn wake(self: Arc<Self>) {
+    self.wake_by_ref()
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.woken.store(true, Ordering::SeqCst);
+    self.thread.unpark();
+  }
+}
+
+/// Runs the isolate's event loop until `future` completes.
+///
+/// Each iteration polls `future` and the futures spawned with
+/// [`future_to_promise`], performs a microtask checkpoint and pumps the
+/// platform's message loop. When none of these make progress the thread
+/// blocks until a waker fires or a background task posts work, so, like any
+/// `block_on`, this never returns if `future` never completes.
+pub fn run_event_loop_until<F: Future>(
+  scope: &mut HandleScope,
+  future: F,
+) -> F::Output {
+  let platform = crate::V8::get_current_platform();
+  let thread_waker = Arc::new(ThreadWaker {
+    thread: std::thread::current(),
+    woken: AtomicBool::new(false),
+  });
+  let waker = Waker::from(thread_waker.clone());
+  let cx = &mut std::task::Context::from_waker(&waker);
+
+  let mut future = std::pin::pin!(future);
+  loop {
+    thread_waker.woken.store(false, Ordering::SeqCst);
+    if let Poll::Ready(output) = future.as_mut().poll(cx) {
+      return output;
+    }
+
+    let _ = poll_spawned_futures(scope, &waker);
+    scope.perform_microtask_checkpoint();
+    let mut ran_task = false;
+    while Platform::pump_message_loop(&platform, scope, false) {
+      ran_task = true;
+    }
+
+    if ran_task || thread_waker.woken.load(Ordering::SeqCst) {
+      continue;
+    }
+    if scope.has_pending_background_tasks() {
+      Platform::pump_message_loop(&platform, scope, true);
+    } else {
+      std::thread::park();
+    }
+  }
+}
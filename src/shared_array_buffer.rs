@@ -4,6 +4,7 @@ use std::ffi::c_void;
 
 use crate::support::SharedRef;
 use crate::support::UniqueRef;
+use crate::ArrayBuffer;
 use crate::BackingStore;
 use crate::BackingStoreDeleterCallback;
 use crate::HandleScope;
@@ -22,6 +23,9 @@ extern "C" {
   ) -> *const SharedArrayBuffer;
   fn v8__SharedArrayBuffer__ByteLength(this: *const SharedArrayBuffer)
     -> usize;
+  fn v8__SharedArrayBuffer__MaxByteLength(
+    this: *const SharedArrayBuffer,
+  ) -> usize;
   fn v8__SharedArrayBuffer__GetBackingStore(
     this: *const SharedArrayBuffer,
   ) -> SharedRef<BackingStore>;
@@ -29,6 +33,10 @@ extern "C" {
     isolate: *mut Isolate,
     byte_length: usize,
   ) -> *mut BackingStore;
+  fn v8__SharedArrayBuffer__NewGrowableBackingStore(
+    byte_length: usize,
+    max_byte_length: usize,
+  ) -> *mut BackingStore;
   fn v8__SharedArrayBuffer__NewBackingStore__with_data(
     data: *mut c_void,
     byte_length: usize,
@@ -91,6 +99,13 @@ impl SharedArrayBuffer {
     unsafe { v8__SharedArrayBuffer__ByteLength(self) }
   }
 
+  /// Maximum length in bytes. For a growable SharedArrayBuffer this is the
+  /// length it may grow to, otherwise it equals byte_length().
+  #[inline(always)]
+  pub fn max_byte_length(&self) -> usize {
+    unsafe { v8__SharedArrayBuffer__MaxByteLength(self) }
+  }
+
   /// Get a shared pointer to the backing store of this array buffer. This
   /// pointer coordinates the lifetime management of the internal storage
   /// with any live ArrayBuffers on the heap, even across isolates. The embedder
@@ -122,6 +137,29 @@ impl SharedArrayBuffer {
     }
   }
 
+  /// Returns a new standalone BackingStore for a growable SharedArrayBuffer.
+  /// It reserves `max_byte_length` bytes of address space up front and
+  /// commits `byte_length` bytes, so the buffer can later grow in place
+  /// through `SharedArrayBuffer.prototype.grow` without moving its contents.
+  ///
+  /// Panics if `byte_length` exceeds `max_byte_length`, or if
+  /// `max_byte_length` exceeds [`ArrayBuffer::MAX_BYTE_LENGTH`]. Crashes with
+  /// an out-of-memory error if the memory can't be reserved.
+  #[inline(always)]
+  pub fn new_growable_backing_store(
+    byte_length: usize,
+    max_byte_length: usize,
+  ) -> UniqueRef<BackingStore> {
+    assert!(byte_length <= max_byte_length);
+    assert!(max_byte_length <= ArrayBuffer::MAX_BYTE_LENGTH);
+    unsafe {
+      UniqueRef::from_raw(v8__SharedArrayBuffer__NewGrowableBackingStore(
+        byte_length,
+        max_byte_length,
+      ))
+    }
+  }
+
   /// Returns a new standalone BackingStore that takes over the ownership of
   /// the given buffer.
   ///
@@ -0,0 +1,739 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Source Map v3 support for stack traces and messages, enabled by the
+//! `source_map` feature.
+//!
+//! Source maps are parsed into a [`SourceMap`] and registered, keyed by the
+//! name of the generated script, in a [`SourceMaps`] collection. Once that
+//! collection is installed on an isolate with [`install`], the `stack`
+//! property of errors reports original source positions, and
+//! [`SourceMaps::remap_js_error`] does the same for a [`JsError`].
+//!
+//! See https://sourcemaps.info/spec.html for the format.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::isolate::call_prepare_stack_trace_callback;
+use crate::isolate::PrepareStackTraceCallback;
+use crate::Array;
+use crate::Function;
+use crate::HandleScope;
+use crate::Isolate;
+use crate::JsError;
+use crate::JsStackFrame;
+use crate::Local;
+use crate::Message;
+use crate::Object;
+use crate::TryCatch;
+use crate::UnboundScript;
+use crate::Value;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceMapError {
+  /// The source map is not valid JSON, or lacks a required field.
+  InvalidJson,
+  /// Only version 3 source maps are supported.
+  UnsupportedVersion,
+  /// Index maps, which consist of `sections`, are not supported.
+  UnsupportedIndexMap,
+  /// The `mappings` field contains an invalid VLQ segment.
+  InvalidMappings,
+  /// The source map URL is a malformed `data:` URL.
+  InvalidDataUrl,
+  /// The source map URL isn't a `data:` URL and no loader could provide it.
+  NotFound(String),
+}
+
+impl fmt::Display for SourceMapError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidJson => f.write_str("invalid source map JSON"),
+      Self::UnsupportedVersion => f.write_str("unsupported source map version"),
+      Self::UnsupportedIndexMap => {
+        f.write_str("indexed source maps are not supported")
+      }
+      Self::InvalidMappings => f.write_str("invalid source map mappings"),
+      Self::InvalidDataUrl => f.write_str("invalid source map data URL"),
+      Self::NotFound(url) => write!(f, "source map not found: {}", url),
+    }
+  }
+}
+
+impl Error for SourceMapError {}
+
+/// A position in an original source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OriginalLocation {
+  /// The original source file, including the source map's `sourceRoot`.
+  pub source: String,
+  /// The 1-based line number.
+  pub line: usize,
+  /// The 1-based column number.
+  pub column: usize,
+  /// The original name of the symbol at this position, if recorded.
+  pub name: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Mapping {
+  generated_column: u32,
+  source: u32,
+  original_line: u32,
+  original_column: u32,
+  name: Option<u32>,
+}
+
+/// A parsed Source Map v3.
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+  file: Option<String>,
+  sources: Vec<String>,
+  sources_content: Vec<Option<String>>,
+  names: Vec<String>,
+  /// Mappings for each generated line, sorted by generated column. Segments
+  /// without an original position are omitted.
+  lines: Vec<Vec<Mapping>>,
+}
+
+impl SourceMap {
+  /// Parses a source map from its JSON text. The JSON is parsed by V8, so this
+  /// requires a scope with an entered context.
+  pub fn parse(
+    scope: &mut HandleScope,
+    json: &[u8],
+  ) -> Result<Self, SourceMapError> {
+    let scope = &mut HandleScope::new(scope);
+    let json =
+      crate::String::new_from_utf8(scope, json, crate::NewStringType::Normal)
+        .ok_or(SourceMapError::InvalidJson)?;
+    let value = {
+      let scope = &mut TryCatch::new(scope);
+      crate::json::parse(scope, json)
+    };
+    let object = value
+      .and_then(|value| Local::<Object>::try_from(value).ok())
+      .ok_or(SourceMapError::InvalidJson)?;
+
+    if get(scope, object, "sections").is_some_and(|v| !v.is_undefined()) {
+      return Err(SourceMapError::UnsupportedIndexMap);
+    }
+    let version = get(scope, object, "version")
+      .and_then(|version| version.uint32_value(scope));
+    if version != Some(3) {
+      return Err(SourceMapError::UnsupportedVersion);
+    }
+
+    let file = get_string(scope, object, "file");
+    let source_root =
+      get_string(scope, object, "sourceRoot").filter(|root| !root.is_empty());
+    let sources = get_string_array(scope, object, "sources")
+      .ok_or(SourceMapError::InvalidJson)?
+      .into_iter()
+      .map(|source| {
+        let source = source.unwrap_or_default();
+        match &source_root {
+          Some(root) if root.ends_with('/') => format!("{}{}", root, source),
+          Some(root) => format!("{}/{}", root, source),
+          None => source,
+        }
+      })
+      .collect::<Vec<_>>();
+    let sources_content =
+      get_string_array(scope, object, "sourcesContent").unwrap_or_default();
+    let names = get_string_array(scope, object, "names")
+      .unwrap_or_default()
+      .into_iter()
+      .map(Option::unwrap_or_default)
+      .collect();
+    let mappings = get_string(scope, object, "mappings")
+      .ok_or(SourceMapError::InvalidJson)?;
+    let lines = decode_mappings(&mappings)?;
+
+    Ok(Self {
+      file,
+      sources,
+      sources_content,
+      names,
+      lines,
+    })
+  }
+
+  /// Parses an inline source map from a `data:` URL, as produced by
+  /// `//# sourceMappingURL=data:application/json;base64,...`.
+  pub fn from_data_url(
+    scope: &mut HandleScope,
+    url: &str,
+  ) -> Result<Self, SourceMapError> {
+    let json = decode_data_url(url).ok_or(SourceMapError::InvalidDataUrl)?;
+    Self::parse(scope, &json)
+  }
+
+  /// The name of the generated file, as recorded in the source map.
+  pub fn file(&self) -> Option<&str> {
+    self.file.as_deref()
+  }
+
+  /// The original source files, with the `sourceRoot` applied.
+  pub fn sources(&self) -> &[String] {
+    &self.sources
+  }
+
+  /// The embedded content of the original source at `index` in
+  /// [`Self::sources`], if present.
+  pub fn source_content(&self, index: usize) -> Option<&str> {
+    self.sources_content.get(index)?.as_deref()
+  }
+
+  /// Maps a 1-based line and column in the generated file to the original
+  /// position.
+  pub fn lookup(&self, line: usize, column: usize) -> Option<OriginalLocation> {
+    let mappings = self.lines.get(line.checked_sub(1)?)?;
+    let column = column.saturating_sub(1) as u32;
+    let index = mappings
+      .partition_point(|mapping| mapping.generated_column <= column)
+      .checked_sub(1)?;
+    let mapping = mappings[index];
+    Some(OriginalLocation {
+      source: self.sources.get(mapping.source as usize)?.clone(),
+      line: mapping.original_line as usize + 1,
+      column: mapping.original_column as usize + 1,
+      name: mapping
+        .name
+        .and_then(|name| self.names.get(name as usize))
+        .cloned(),
+    })
+  }
+}
+
+type SourceMapLoader = Box<dyn Fn(&str) -> Option<Vec<u8>>>;
+
+/// Source maps of the scripts loaded into an isolate, keyed by script name.
+#[derive(Default)]
+pub struct SourceMaps {
+  maps: HashMap<String, SourceMap>,
+  loader: Option<SourceMapLoader>,
+  /// The prepare stack trace callback that was installed before [`install`].
+  previous_callback: Option<PrepareStackTraceCallback<'static>>,
+}
+
+impl fmt::Debug for SourceMaps {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("SourceMaps")
+      .field("maps", &self.maps)
+      .finish_non_exhaustive()
+  }
+}
+
+impl SourceMaps {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the function used by [`register_script`] to fetch source maps whose
+  /// URL isn't a `data:` URL. It receives the URL and returns the source map
+  /// JSON, or `None` if it can't be found.
+  pub fn set_loader(
+    &mut self,
+    loader: impl Fn(&str) -> Option<Vec<u8>> + 'static,
+  ) {
+    self.loader = Some(Box::new(loader));
+  }
+
+  /// Associates `source_map` with the script called `script_name`, replacing
+  /// any previous source map for that script.
+  pub fn insert(&mut self, script_name: impl Into<String>, map: SourceMap) {
+    self.maps.insert(script_name.into(), map);
+  }
+
+  pub fn get(&self, script_name: &str) -> Option<&SourceMap> {
+    self.maps.get(script_name)
+  }
+
+  pub fn remove(&mut self, script_name: &str) -> Option<SourceMap> {
+    self.maps.remove(script_name)
+  }
+
+  /// Maps a 1-based line and column in the script called `script_name` to the
+  /// original position.
+  pub fn lookup(
+    &self,
+    script_name: &str,
+    line: usize,
+    column: usize,
+  ) -> Option<OriginalLocation> {
+    self.get(script_name)?.lookup(line, column)
+  }
+
+  /// Rewrites the location of `frame` to its original position. Returns false
+  /// if no mapping was found, in which case `frame` is left untouched.
+  pub fn remap_stack_frame(&self, frame: &mut JsStackFrame) -> bool {
+    let (Some(script_name), Some(line)) =
+      (&frame.script_name, frame.line_number)
+    else {
+      return false;
+    };
+    let column = frame.column_number.unwrap_or(1);
+    let Some(location) = self.lookup(script_name, line, column) else {
+      return false;
+    };
+    frame.script_name = Some(location.source);
+    frame.line_number = Some(location.line);
+    frame.column_number = Some(location.column);
+    true
+  }
+
+  /// Rewrites the stack frames, the `stack` and the location of `error`, and
+  /// those of its causes and aggregated errors, to their original positions.
+  pub fn remap_js_error(&self, error: &mut JsError) {
+    let error_location = &mut *error.location;
+    for frame in &mut error_location.frames {
+      self.remap_stack_frame(frame);
+    }
+    if let Some(stack) = &mut error_location.stack {
+      *stack = stack
+        .lines()
+        .map(|line| {
+          self
+            .remap_stack_line(line)
+            .unwrap_or_else(|| line.to_owned())
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    }
+    if let (Some(script_name), Some(line)) = (
+      &error_location.script_resource_name,
+      error_location.line_number,
+    ) {
+      let column = error_location.start_column.unwrap_or(0) + 1;
+      if let Some(location) = self.lookup(script_name, line, column) {
+        // The source line and column range refer to the generated code.
+        error_location.script_resource_name = Some(location.source);
+        error_location.line_number = Some(location.line);
+        error_location.start_column = Some(location.column - 1);
+        error_location.end_column = None;
+        error_location.source_line = None;
+      }
+    }
+    if let Some(cause) = &mut error.cause {
+      self.remap_js_error(cause);
+    }
+    for error in error.aggregated.iter_mut().flatten() {
+      self.remap_js_error(error);
+    }
+  }
+
+  /// Rewrites the location at the end of an `    at ...` line of a `stack`
+  /// property, as in `at f (gen.js:2:9)` or `at gen.js:4:1`. Returns `None`
+  /// if the line has no location or no mapping was found.
+  fn remap_stack_line(&self, line: &str) -> Option<String> {
+    let at = line.find("at ")?;
+    if !line[..at].trim().is_empty() {
+      return None;
+    }
+    let (prefix, location, suffix) = match line.strip_suffix(')') {
+      Some(rest) => {
+        let open = rest.rfind('(')? + 1;
+        (&line[..open], &rest[open..], ")")
+      }
+      None => (&line[..at + 3], &line[at + 3..], ""),
+    };
+    let (rest, column) = location.rsplit_once(':')?;
+    let (script_name, line_number) = rest.rsplit_once(':')?;
+    let location = self.lookup(
+      script_name,
+      line_number.parse().ok()?,
+      column.parse().ok()?,
+    )?;
+    Some(format!(
+      "{}{}:{}:{}{}",
+      prefix, location.source, location.line, location.column, suffix
+    ))
+  }
+
+  /// Returns the original position of the location `message` refers to.
+  pub fn remap_message(
+    &self,
+    scope: &mut HandleScope,
+    message: Local<Message>,
+  ) -> Option<OriginalLocation> {
+    let script_name = message
+      .get_script_resource_name(scope)?
+      .to_rust_string_lossy(scope);
+    let line = message.get_line_number(scope)?;
+    let column = message.get_start_column() + 1;
+    self.lookup(&script_name, line, column)
+  }
+}
+
+/// Stores `source_maps` in an isolate slot and installs a prepare stack trace
+/// callback that uses them to rewrite the locations in the `stack` property
+/// of errors to original source positions.
+///
+/// `Error.prepareStackTrace` keeps working, and receives the call sites
+/// unchanged. Otherwise, a callback installed earlier with
+/// [`Isolate::set_prepare_stack_trace_callback`] still formats the stack,
+/// and only its locations are rewritten.
+pub fn install(isolate: &mut Isolate, mut source_maps: SourceMaps) {
+  source_maps.previous_callback = match get_source_maps(isolate) {
+    Some(installed) => installed.previous_callback,
+    None => isolate.get_prepare_stack_trace_callback(),
+  };
+  isolate.set_slot(source_maps);
+  isolate.set_prepare_stack_trace_callback(prepare_stack_trace);
+}
+
+/// Returns the source maps installed with [`install`].
+pub fn get_source_maps(isolate: &mut Isolate) -> Option<&mut SourceMaps> {
+  isolate.get_slot_mut::<SourceMaps>()
+}
+
+/// Loads the source map referenced by the `//# sourceMappingURL` comment of
+/// `script` and registers it for `script_name` in the source maps installed
+/// with [`install`]. Returns false if the script has no source map URL.
+pub fn register_script(
+  scope: &mut HandleScope,
+  script_name: &str,
+  script: Local<UnboundScript>,
+) -> Result<bool, SourceMapError> {
+  let url = script.get_source_mapping_url(scope);
+  if !url.is_string() {
+    return Ok(false);
+  }
+  let url = url.to_rust_string_lossy(scope);
+
+  let map = if url.starts_with("data:") {
+    SourceMap::from_data_url(scope, &url)?
+  } else {
+    let json = get_source_maps(scope)
+      .and_then(|maps| maps.loader.as_ref())
+      .and_then(|loader| loader(&url))
+      .ok_or_else(|| SourceMapError::NotFound(url.clone()))?;
+    SourceMap::parse(scope, &json)?
+  };
+  if let Some(source_maps) = get_source_maps(scope) {
+    source_maps.insert(script_name, map);
+  }
+  Ok(true)
+}
+
+fn prepare_stack_trace<'s>(
+  scope: &mut HandleScope<'s>,
+  error: Local<'s, Value>,
+  sites: Local<'s, Array>,
+) -> Local<'s, Value> {
+  // Exceptions thrown by `Error.prepareStackTrace`, `toString` or patched
+  // `CallSite` methods are ignored: this callback can't report them.
+  let scope = &mut TryCatch::new(scope);
+  let stack = user_prepare_stack_trace(scope, error, sites)
+    .or_else(|| previous_prepare_stack_trace(scope, error, sites))
+    .unwrap_or_else(|| format_stack_trace(scope, error, sites));
+  match stack {
+    Some(stack) => stack,
+    None => crate::undefined(scope).into(),
+  }
+}
+
+/// Calls `Error.prepareStackTrace`, which V8 no longer calls itself once a
+/// prepare stack trace callback is installed.
+fn user_prepare_stack_trace<'s>(
+  scope: &mut HandleScope<'s>,
+  error: Local<'s, Value>,
+  sites: Local<'s, Array>,
+) -> Option<Option<Local<'s, Value>>> {
+  let global = scope.get_current_context().global(scope);
+  let constructor = get(scope, global, "Error")?;
+  let constructor = Local::<Object>::try_from(constructor).ok()?;
+  let prepare = get(scope, constructor, "prepareStackTrace")?;
+  let prepare = Local::<Function>::try_from(prepare).ok()?;
+  Some(prepare.call(scope, constructor.into(), &[error, sites.into()]))
+}
+
+/// Calls the callback that was installed before [`install`], and remaps the
+/// locations of the stack it returns.
+fn previous_prepare_stack_trace<'s>(
+  scope: &mut HandleScope<'s>,
+  error: Local<'s, Value>,
+  sites: Local<'s, Array>,
+) -> Option<Option<Local<'s, Value>>> {
+  let callback = get_source_maps(scope)?.previous_callback?;
+  let context = scope.get_current_context();
+  let stack =
+    call_prepare_stack_trace_callback(callback, context, error, sites);
+  let Some(text) = stack.filter(|stack| stack.is_string()) else {
+    return Some(stack);
+  };
+  let text = text.to_rust_string_lossy(scope);
+  let source_maps = get_source_maps(scope)?;
+  let text = text
+    .lines()
+    .map(|line| {
+      source_maps
+        .remap_stack_line(line)
+        .unwrap_or_else(|| line.to_owned())
+    })
+    .collect::<Vec<_>>()
+    .join("\n");
+  Some(crate::String::new(scope, &text).map(Into::into))
+}
+
+/// Formats the stack like V8 does, with the location of each frame mapped
+/// to its original position. The frames are formatted by
+/// `CallSite.prototype.toString`, so that type and method names, `async`
+/// frames and eval origins are kept.
+fn format_stack_trace<'s>(
+  scope: &mut HandleScope<'s>,
+  error: Local<'s, Value>,
+  sites: Local<'s, Array>,
+) -> Option<Local<'s, Value>> {
+  let mut stack = match error.to_string(scope) {
+    Some(header) => header.to_rust_string_lossy(scope),
+    None => "<error>".to_owned(),
+  };
+  for index in 0..sites.length() {
+    let Some(site) = sites
+      .get_index(scope, index)
+      .and_then(|site| Local::<Object>::try_from(site).ok())
+    else {
+      continue;
+    };
+    let Some(mut frame) = call_site_string(scope, site, "toString") else {
+      continue;
+    };
+    let script_name = call_site_string(scope, site, "getScriptNameOrSourceURL");
+    let line = call_site_number(scope, site, "getLineNumber");
+    let column = call_site_number(scope, site, "getColumnNumber");
+    if let (Some(script_name), Some(line), Some(column)) =
+      (script_name, line, column)
+    {
+      let location = format!("{}:{}:{}", script_name, line, column);
+      let original = get_source_maps(scope)
+        .and_then(|source_maps| source_maps.lookup(&script_name, line, column));
+      if let (Some(at), Some(original)) = (frame.rfind(&location), original) {
+        frame.replace_range(
+          at..at + location.len(),
+          &format!("{}:{}:{}", original.source, original.line, original.column),
+        );
+      }
+    }
+    stack.push_str("\n    at ");
+    stack.push_str(&frame);
+  }
+  crate::String::new(scope, &stack).map(Into::into)
+}
+
+fn call_site_method<'s>(
+  scope: &mut HandleScope<'s>,
+  site: Local<Object>,
+  name: &str,
+) -> Option<Local<'s, Value>> {
+  let method = get(scope, site, name)?;
+  let method = Local::<Function>::try_from(method).ok()?;
+  method.call(scope, site.into(), &[])
+}
+
+fn call_site_string(
+  scope: &mut HandleScope,
+  site: Local<Object>,
+  name: &str,
+) -> Option<String> {
+  call_site_method(scope, site, name)
+    .filter(|value| value.is_string())
+    .map(|value| value.to_rust_string_lossy(scope))
+    .filter(|value| !value.is_empty())
+}
+
+fn call_site_number(
+  scope: &mut HandleScope,
+  site: Local<Object>,
+  name: &str,
+) -> Option<usize> {
+  call_site_method(scope, site, name)
+    .filter(|value| value.is_number())
+    .and_then(|value| value.uint32_value(scope))
+    .map(|value| value as usize)
+}
+
+fn get<'s>(
+  scope: &mut HandleScope<'s>,
+  object: Local<Object>,
+  key: &str,
+) -> Option<Local<'s, Value>> {
+  let key = crate::String::new(scope, key)?;
+  object.get(scope, key.into())
+}
+
+fn get_string(
+  scope: &mut HandleScope,
+  object: Local<Object>,
+  key: &str,
+) -> Option<String> {
+  get(scope, object, key)
+    .filter(|value| value.is_string())
+    .map(|value| value.to_rust_string_lossy(scope))
+}
+
+/// Reads an array whose elements are strings or `null`.
+fn get_string_array(
+  scope: &mut HandleScope,
+  object: Local<Object>,
+  key: &str,
+) -> Option<Vec<Option<String>>> {
+  let array = get(scope, object, key)?;
+  let array = Local::<Array>::try_from(array).ok()?;
+  let mut strings = Vec::with_capacity(array.length() as usize);
+  for index in 0..array.length() {
+    let element = array.get_index(scope, index)?;
+    strings.push(
+      element
+        .is_string()
+        .then(|| element.to_rust_string_lossy(scope)),
+    );
+  }
+  Some(strings)
+}
+
+fn base64_value(byte: u8) -> Option<u8> {
+  match byte {
+    b'A'..=b'Z' => Some(byte - b'A'),
+    b'a'..=b'z' => Some(byte - b'a' + 26),
+    b'0'..=b'9' => Some(byte - b'0' + 52),
+    b'+' | b'-' => Some(62),
+    b'/' | b'_' => Some(63),
+    _ => None,
+  }
+}
+
+fn decode_base64(input: &[u8]) -> Option<Vec<u8>> {
+  let input = input
+    .iter()
+    .copied()
+    .filter(|byte| !byte.is_ascii_whitespace())
+    .collect::<Vec<_>>();
+  let input = match input.iter().position(|&byte| byte == b'=') {
+    Some(padding) if input[padding..].iter().all(|&byte| byte == b'=') => {
+      &input[..padding]
+    }
+    Some(_) => return None,
+    None => &input[..],
+  };
+  let mut output = Vec::with_capacity(input.len() * 3 / 4);
+  let mut buffer = 0u32;
+  let mut bits = 0;
+  for &byte in input {
+    buffer = (buffer << 6) | base64_value(byte)? as u32;
+    bits += 6;
+    if bits >= 8 {
+      bits -= 8;
+      output.push((buffer >> bits) as u8);
+    }
+  }
+  Some(output)
+}
+
+fn decode_percent(input: &[u8]) -> Option<Vec<u8>> {
+  let mut output = Vec::with_capacity(input.len());
+  let mut bytes = input.iter().copied();
+  while let Some(byte) = bytes.next() {
+    if byte == b'%' {
+      let hex = [bytes.next()?, bytes.next()?];
+      let hex = std::str::from_utf8(&hex).ok()?;
+      output.push(u8::from_str_radix(hex, 16).ok()?);
+    } else {
+      output.push(byte);
+    }
+  }
+  Some(output)
+}
+
+fn decode_data_url(url: &str) -> Option<Vec<u8>> {
+  let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+  let mut params = header.split(';');
+  let mime_type = params.next()?;
+  if !mime_type.is_empty()
+    && !mime_type.eq_ignore_ascii_case("application/json")
+    && !mime_type.eq_ignore_ascii_case("text/plain")
+  {
+    return None;
+  }
+  if params.any(|param| param == "base64") {
+    decode_base64(data.as_bytes())
+  } else {
+    decode_percent(data.as_bytes())
+  }
+}
+
+/// Decodes a single base64 VLQ value starting at `bytes[*pos]`.
+fn decode_vlq(bytes: &[u8], pos: &mut usize) -> Option<i64> {
+  let mut value = 0i64;
+  let mut shift = 0;
+  loop {
+    let digit = base64_value(*bytes.get(*pos)?)? as i64;
+    *pos += 1;
+    value |= (digit & 0b11111) << shift;
+    if digit & 0b100000 == 0 {
+      break;
+    }
+    shift += 5;
+    if shift > 60 {
+      return None;
+    }
+  }
+  Some(if value & 1 == 1 {
+    -(value >> 1)
+  } else {
+    value >> 1
+  })
+}
+
+fn decode_mappings(
+  mappings: &str,
+) -> Result<Vec<Vec<Mapping>>, SourceMapError> {
+  fn apply(base: &mut i64, delta: i64) -> Result<u32, SourceMapError> {
+    *base += delta;
+    u32::try_from(*base).map_err(|_| SourceMapError::InvalidMappings)
+  }
+
+  let mut lines = Vec::new();
+  // All fields except the generated column are relative to the previous
+  // segment in the whole file, not just the current line.
+  let (mut source, mut original_line, mut original_column, mut name) =
+    (0i64, 0i64, 0i64, 0i64);
+
+  for line in mappings.split(';') {
+    let mut segments = Vec::new();
+    let mut generated_column = 0i64;
+    for segment in line.split(',').filter(|segment| !segment.is_empty()) {
+      let bytes = segment.as_bytes();
+      let mut pos = 0;
+      let mut fields = Vec::with_capacity(5);
+      while pos < bytes.len() {
+        fields.push(
+          decode_vlq(bytes, &mut pos).ok_or(SourceMapError::InvalidMappings)?,
+        );
+      }
+      let generated = apply(&mut generated_column, fields[0])?;
+      match fields.len() {
+        1 => {}
+        4 | 5 => {
+          let mut mapping = Mapping {
+            generated_column: generated,
+            source: apply(&mut source, fields[1])?,
+            original_line: apply(&mut original_line, fields[2])?,
+            original_column: apply(&mut original_column, fields[3])?,
+            name: None,
+          };
+          if let Some(&delta) = fields.get(4) {
+            mapping.name = Some(apply(&mut name, delta)?);
+          }
+          segments.push(mapping);
+        }
+        _ => return Err(SourceMapError::InvalidMappings),
+      }
+    }
+    segments.sort_by_key(|mapping| mapping.generated_column);
+    lines.push(segments);
+  }
+  Ok(lines)
+}
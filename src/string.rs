@@ -3,11 +3,13 @@ use crate::support::char;
 use crate::support::int;
 use crate::support::size_t;
 use crate::support::Opaque;
+use crate::Global;
 use crate::HandleScope;
 use crate::Isolate;
 use crate::Local;
 use crate::String;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::default::Default;
 use std::ffi::c_void;
@@ -15,6 +17,7 @@ use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 use std::ptr::NonNull;
 use std::slice;
+use std::sync::Arc;
 
 extern "C" {
   fn v8__String__Empty(isolate: *mut Isolate) -> *const String;
@@ -103,6 +106,22 @@ extern "C" {
     length: int,
   ) -> *const String;
 
+  fn v8__String__NewExternalOneByteOwned(
+    isolate: *mut Isolate,
+    buffer: *const char,
+    length: size_t,
+    owner: *mut c_void,
+    drop: unsafe extern "C" fn(*mut c_void),
+  ) -> *const String;
+
+  fn v8__String__NewExternalTwoByteOwned(
+    isolate: *mut Isolate,
+    buffer: *const u16,
+    length: size_t,
+    owner: *mut c_void,
+    drop: unsafe extern "C" fn(*mut c_void),
+  ) -> *const String;
+
   #[allow(dead_code)]
   fn v8__String__IsExternal(this: *const String) -> bool;
   fn v8__String__IsExternalOneByte(this: *const String) -> bool;
@@ -177,6 +196,25 @@ impl ExternalOneByteStringResource {
   }
 }
 
+/// Internalized strings handed out by [`String::cached_key`], keyed by the
+/// address and length of the key. Stored in an isolate slot.
+struct KeyCache(HashMap<(usize, usize), Global<String>>);
+
+/// Creates a `&'static OneByteConst` from an ASCII string literal at compile
+/// time. Non-ASCII literals fail to compile.
+///
+/// ```ignore
+/// let key = v8::String::cached_key(scope, v8::onebyte_const!("then"));
+/// ```
+#[macro_export]
+macro_rules! onebyte_const {
+  ($value:expr) => {{
+    static ONE_BYTE_CONST: $crate::OneByteConst =
+      $crate::String::create_external_onebyte_const($value.as_bytes());
+    &ONE_BYTE_CONST
+  }};
+}
+
 /// A static ASCII string resource for usage in V8, created at build time.
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
@@ -540,6 +578,48 @@ impl String {
     Self::new_from_utf8(scope, value.as_ref(), NewStringType::Normal)
   }
 
+  /// Returns an internalized string for a static key, such as a property
+  /// name. `key` is UTF-8, e.g. a `&'static str` or a `&'static OneByteConst`
+  /// created with [`onebyte_const!`](crate::onebyte_const).
+  ///
+  /// The string is created the first time `key` is used with an isolate and
+  /// is kept alive for the isolate's lifetime. Later calls look it up by the
+  /// address of `key`, so they neither allocate nor hash the key's contents.
+  /// Equal keys stored at different addresses get separate cache entries, but
+  /// still resolve to the same internalized string.
+  pub fn cached_key<'s, K>(
+    scope: &mut HandleScope<'s, ()>,
+    key: &'static K,
+  ) -> Option<Local<'s, String>>
+  where
+    K: AsRef<[u8]> + ?Sized,
+  {
+    let bytes = key.as_ref();
+    let cache_key = (bytes.as_ptr() as usize, bytes.len());
+    let cached = scope
+      .get_slot::<KeyCache>()
+      .and_then(|cache| cache.0.get(&cache_key))
+      .map(|string| string as *const Global<String>);
+    if let Some(string) = cached {
+      // SAFETY: Cache entries are never removed, and creating a local handle
+      // doesn't access the isolate's slots.
+      return Some(Local::new(scope, unsafe { &*string }));
+    }
+
+    let string =
+      Self::new_from_utf8(scope, bytes, NewStringType::Internalized)?;
+    let global = Global::new(scope, string);
+    match scope.get_slot_mut::<KeyCache>() {
+      Some(cache) => {
+        cache.0.insert(cache_key, global);
+      }
+      None => {
+        scope.set_slot(KeyCache(HashMap::from([(cache_key, global)])));
+      }
+    }
+    Some(string)
+  }
+
   /// Compile-time function to create an external string resource.
   /// The buffer is checked to contain only ASCII characters.
   #[inline(always)]
@@ -676,6 +756,85 @@ impl String {
     }
   }
 
+  /// Creates an external one-byte `v8::String` backed by an owned Rust
+  /// buffer, without copying it. The bytes must be Latin-1 or ASCII.
+  ///
+  /// V8 takes ownership of `buffer` and drops it when the string is garbage
+  /// collected or the isolate is disposed. The buffer's size is reported to
+  /// the isolate as external memory while the string is alive. Passing an
+  /// `Arc<[u8]>` shares one buffer between strings in several isolates, in
+  /// which case each of them accounts for it.
+  #[inline(always)]
+  pub fn new_external_onebyte_owned<'s, B>(
+    scope: &mut HandleScope<'s, ()>,
+    buffer: B,
+  ) -> Option<Local<'s, String>>
+  where
+    B: ExternalStringBuffer<Char = u8>,
+  {
+    let owner = Box::into_raw(Box::new(buffer));
+    // SAFETY: `ExternalStringBuffer` guarantees that the slice stays valid,
+    // at the same address, until `owner` is dropped by V8.
+    let data = unsafe { (*owner).as_slice() };
+    unsafe {
+      scope.cast_local(|sd| {
+        v8__String__NewExternalOneByteOwned(
+          sd.get_isolate_ptr(),
+          data.as_ptr() as *const char,
+          data.len(),
+          owner.cast(),
+          drop_external_string_buffer::<B>,
+        )
+      })
+    }
+  }
+
+  /// Creates an external two-byte `v8::String` backed by an owned Rust
+  /// buffer of UTF-16 code units, without copying it.
+  ///
+  /// Ownership and memory accounting work as for
+  /// [`String::new_external_onebyte_owned`].
+  #[inline(always)]
+  pub fn new_external_twobyte_owned<'s, B>(
+    scope: &mut HandleScope<'s, ()>,
+    buffer: B,
+  ) -> Option<Local<'s, String>>
+  where
+    B: ExternalStringBuffer<Char = u16>,
+  {
+    let owner = Box::into_raw(Box::new(buffer));
+    // SAFETY: See `new_external_onebyte_owned`.
+    let data = unsafe { (*owner).as_slice() };
+    unsafe {
+      scope.cast_local(|sd| {
+        v8__String__NewExternalTwoByteOwned(
+          sd.get_isolate_ptr(),
+          data.as_ptr(),
+          data.len(),
+          owner.cast(),
+          drop_external_string_buffer::<B>,
+        )
+      })
+    }
+  }
+
+  /// Creates an external `v8::String` from shared UTF-8 text.
+  ///
+  /// ASCII text is exposed as a one-byte string that shares `text` without
+  /// copying it. Other text can't be represented as a one-byte string, so it
+  /// is converted to UTF-16 and backed by a `Box<[u16]>` instead.
+  pub fn new_external_str<'s>(
+    scope: &mut HandleScope<'s, ()>,
+    text: Arc<str>,
+  ) -> Option<Local<'s, String>> {
+    if text.is_ascii() {
+      Self::new_external_onebyte_owned(scope, AsciiStr(text))
+    } else {
+      let utf16 = text.encode_utf16().collect::<Box<[u16]>>();
+      Self::new_external_twobyte_owned(scope, utf16)
+    }
+  }
+
   /// Get the ExternalStringResource for an external string.
   ///
   /// Returns None if is_external() doesn't return true.
@@ -938,6 +1097,77 @@ impl String {
   }
 }
 
+/// Owned storage for the characters of an external string created with
+/// [`String::new_external_onebyte_owned`] or
+/// [`String::new_external_twobyte_owned`].
+///
+/// # Safety
+///
+/// `as_slice` must always return the same slice, which must stay valid and at
+/// the same address for as long as the value is alive, even if the value
+/// itself is moved. The contents must not change while the value is alive.
+pub unsafe trait ExternalStringBuffer: 'static {
+  /// `u8` for one-byte (Latin-1) strings, `u16` for two-byte strings.
+  type Char;
+
+  fn as_slice(&self) -> &[Self::Char];
+}
+
+macro_rules! impl_external_string_buffer {
+  ($($char:ty),*) => {
+    $(
+      unsafe impl ExternalStringBuffer for &'static [$char] {
+        type Char = $char;
+
+        fn as_slice(&self) -> &[$char] {
+          self
+        }
+      }
+
+      unsafe impl ExternalStringBuffer for Box<[$char]> {
+        type Char = $char;
+
+        fn as_slice(&self) -> &[$char] {
+          self
+        }
+      }
+
+      unsafe impl ExternalStringBuffer for Vec<$char> {
+        type Char = $char;
+
+        fn as_slice(&self) -> &[$char] {
+          self
+        }
+      }
+
+      unsafe impl ExternalStringBuffer for Arc<[$char]> {
+        type Char = $char;
+
+        fn as_slice(&self) -> &[$char] {
+          self
+        }
+      }
+    )*
+  };
+}
+
+impl_external_string_buffer!(u8, u16);
+
+/// ASCII text, which is valid Latin-1 as well.
+struct AsciiStr(Arc<str>);
+
+unsafe impl ExternalStringBuffer for AsciiStr {
+  type Char = u8;
+
+  fn as_slice(&self) -> &[u8] {
+    self.0.as_bytes()
+  }
+}
+
+unsafe extern "C" fn drop_external_string_buffer<B>(owner: *mut c_void) {
+  drop(unsafe { Box::from_raw(owner as *mut B) });
+}
+
 pub extern "C" fn free_rust_external_onebyte(s: *mut char, len: usize) {
   unsafe {
     let slice = std::slice::from_raw_parts_mut(s, len);
@@ -996,3 +1226,80 @@ impl<'s> Drop for ValueView<'s> {
     unsafe { v8__String__ValueView__DESTRUCT(self) }
   }
 }
+
+/// Borrowed access to a string's contents that avoids copying whenever the
+/// string's representation allows it.
+///
+/// V8 flattens the string when the view is created. Afterwards the view
+/// mutably borrows the isolate, so no JavaScript can run and no garbage
+/// collection can move the string for as long as the view is alive.
+pub struct StringView<'a> {
+  view: ValueView<'a>,
+  _no_gc: PhantomData<&'a mut Isolate>,
+}
+
+impl<'a> StringView<'a> {
+  #[inline(always)]
+  pub fn new(isolate: &'a mut Isolate, string: Local<'a, String>) -> Self {
+    Self {
+      view: ValueView::new(isolate, string),
+      _no_gc: PhantomData,
+    }
+  }
+
+  /// Returns the contents without copying if the string is one-byte and
+  /// consists of ASCII characters only.
+  #[inline(always)]
+  pub fn as_str(&self) -> Option<&str> {
+    match self.view.data() {
+      ValueViewData::OneByte(bytes) if bytes.is_ascii() => {
+        // SAFETY: ASCII is valid UTF-8.
+        Some(unsafe { std::str::from_utf8_unchecked(bytes) })
+      }
+      _ => None,
+    }
+  }
+
+  /// Returns the UTF-16 code units without copying if the string is
+  /// two-byte.
+  #[inline(always)]
+  pub fn as_utf16(&self) -> Option<&[u16]> {
+    match self.view.data() {
+      ValueViewData::TwoByte(units) => Some(units),
+      ValueViewData::OneByte(_) => None,
+    }
+  }
+
+  /// Returns the contents as UTF-8, borrowing them when the string is
+  /// one-byte ASCII and copying them otherwise. Unpaired surrogates are
+  /// replaced with U+FFFD.
+  pub fn to_str(&self) -> Cow<'_, str> {
+    match self.view.data() {
+      ValueViewData::OneByte(bytes) if bytes.is_ascii() => {
+        // SAFETY: ASCII is valid UTF-8.
+        Cow::Borrowed(unsafe { std::str::from_utf8_unchecked(bytes) })
+      }
+      // One-byte strings are Latin-1, which maps directly to code points.
+      ValueViewData::OneByte(bytes) => Cow::Owned(
+        bytes
+          .iter()
+          .map(|&byte| std::primitive::char::from(byte))
+          .collect(),
+      ),
+      ValueViewData::TwoByte(units) => {
+        Cow::Owned(std::string::String::from_utf16_lossy(units))
+      }
+    }
+  }
+
+  /// Returns the contents as UTF-16, borrowing them when the string is
+  /// two-byte and widening them otherwise.
+  pub fn to_utf16(&self) -> Cow<'_, [u16]> {
+    match self.view.data() {
+      ValueViewData::OneByte(bytes) => {
+        Cow::Owned(bytes.iter().map(|&byte| byte as u16).collect())
+      }
+      ValueViewData::TwoByte(units) => Cow::Borrowed(units),
+    }
+  }
+}
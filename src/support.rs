@@ -301,6 +301,12 @@ impl<T: Shared> SharedRef<T> {
   pub fn assert_use_count_eq(&self, expected: usize) {
     assert_shared_ptr_use_count_eq("SharedRef", &self.0, expected);
   }
+
+  /// Returns the number of references to the shared inner value, as reported
+  /// by `std::shared_ptr::use_count()`.
+  pub(crate) fn use_count(&self) -> usize {
+    <T as Shared>::use_count(&self.0) as usize
+  }
 }
 
 impl<T: Shared> Clone for SharedRef<T> {
@@ -3,7 +3,15 @@ use crate::data::FunctionTemplate;
 use crate::data::Name;
 use crate::data::ObjectTemplate;
 use crate::data::Template;
+use crate::fast_api::check_overloads;
 use crate::fast_api::CFunction;
+use crate::fast_api::FastArgs;
+use crate::fast_api::FastFunction;
+use crate::function::closure_accessor_getter;
+use crate::function::closure_accessor_setter;
+use crate::function::closure_data;
+use crate::function::closure_function_callback;
+use crate::function::AccessorClosures;
 use crate::isolate::Isolate;
 use crate::support::int;
 use crate::support::MapFnTo;
@@ -12,6 +20,7 @@ use crate::Context;
 use crate::Function;
 use crate::FunctionBuilder;
 use crate::FunctionCallback;
+use crate::FunctionCallbackArguments;
 use crate::HandleScope;
 use crate::IndexedDefinerCallback;
 use crate::IndexedDeleterCallback;
@@ -28,11 +37,14 @@ use crate::NamedSetterCallback;
 use crate::NamedSetterCallbackForAccessor;
 use crate::Object;
 use crate::PropertyAttribute;
+use crate::PropertyCallbackArguments;
 use crate::PropertyEnumeratorCallback;
 use crate::PropertyHandlerFlags;
+use crate::ReturnValue;
 use crate::SideEffectType;
 use crate::Signature;
 use crate::String;
+use crate::TypedFunctionTag;
 use crate::Value;
 use std::convert::TryFrom;
 use std::ptr::null;
@@ -84,6 +96,10 @@ extern "C" {
     this: *const FunctionTemplate,
     parent: *const FunctionTemplate,
   );
+  fn v8__FunctionTemplate__HasInstance(
+    this: *const FunctionTemplate,
+    object: *const Value,
+  ) -> bool;
   fn v8__FunctionTemplate__ReadOnlyPrototype(this: *const FunctionTemplate);
   fn v8__FunctionTemplate__RemovePrototype(this: *const FunctionTemplate);
 
@@ -297,6 +313,59 @@ impl<'s> AccessorConfiguration<'s> {
     self
   }
 
+  /// Creates an accessor configuration from a getter closure, which may
+  /// capture state. The closure is stored behind an `External` that serves as
+  /// the accessor's data, so the returned configuration has no `data`. It is
+  /// dropped once every object or template the accessor is installed on has
+  /// been garbage collected.
+  pub fn new_closure<G>(
+    scope: &mut HandleScope<'s, ()>,
+    getter: G,
+  ) -> ClosureAccessorConfiguration<'s>
+  where
+    G: for<'a> Fn(
+        &mut HandleScope<'a>,
+        Local<'a, Name>,
+        PropertyCallbackArguments<'a>,
+        ReturnValue<Value>,
+      ) + 'static,
+  {
+    let closures = AccessorClosures { getter, setter: () };
+    let data = closure_data(scope, closures);
+    ClosureAccessorConfiguration(
+      Self::new(closure_accessor_getter::<G, ()>).data(data.into()),
+    )
+  }
+
+  /// Like [`AccessorConfiguration::new_closure`], with a setter closure.
+  pub fn new_closure_with_setter<G, S>(
+    scope: &mut HandleScope<'s, ()>,
+    getter: G,
+    setter: S,
+  ) -> ClosureAccessorConfiguration<'s>
+  where
+    G: for<'a> Fn(
+        &mut HandleScope<'a>,
+        Local<'a, Name>,
+        PropertyCallbackArguments<'a>,
+        ReturnValue<Value>,
+      ) + 'static,
+    S: for<'a> Fn(
+        &mut HandleScope<'a>,
+        Local<'a, Name>,
+        Local<'a, Value>,
+        PropertyCallbackArguments<'a>,
+        ReturnValue<()>,
+      ) + 'static,
+  {
+    let data = closure_data(scope, AccessorClosures { getter, setter });
+    ClosureAccessorConfiguration(
+      Self::new(closure_accessor_getter::<G, S>)
+        .setter(closure_accessor_setter::<G, S>)
+        .data(data.into()),
+    )
+  }
+
   /// Set the associated data. The default is no associated data.
   pub fn data(mut self, data: Local<'s, Value>) -> Self {
     self.data = Some(data);
@@ -304,6 +373,42 @@ impl<'s> AccessorConfiguration<'s> {
   }
 }
 
+/// An accessor configuration whose callbacks are closures, created by
+/// [`AccessorConfiguration::new_closure`]. Its data holds the closures, so
+/// unlike [`AccessorConfiguration`] it can't be replaced.
+pub struct ClosureAccessorConfiguration<'s>(AccessorConfiguration<'s>);
+
+impl<'s> ClosureAccessorConfiguration<'s> {
+  pub fn property_attribute(
+    mut self,
+    property_attribute: PropertyAttribute,
+  ) -> Self {
+    self.0.property_attribute = property_attribute;
+    self
+  }
+}
+
+pub(crate) mod sealed {
+  /// A configuration accepted by `set_accessor_with_configuration`.
+  pub trait IntoAccessorConfiguration<'s> {
+    fn into_accessor_configuration(self) -> super::AccessorConfiguration<'s>;
+  }
+}
+
+impl<'s> sealed::IntoAccessorConfiguration<'s> for AccessorConfiguration<'s> {
+  fn into_accessor_configuration(self) -> AccessorConfiguration<'s> {
+    self
+  }
+}
+
+impl<'s> sealed::IntoAccessorConfiguration<'s>
+  for ClosureAccessorConfiguration<'s>
+{
+  fn into_accessor_configuration(self) -> AccessorConfiguration<'s> {
+    self.0
+  }
+}
+
 #[derive(Default)]
 pub struct NamedPropertyHandlerConfiguration<'s> {
   pub(crate) getter: Option<NamedPropertyGetterCallback<'s>>,
@@ -707,11 +812,21 @@ impl<'s> FunctionBuilder<'s, FunctionTemplate> {
   /// useful to pass them explicitly - eg. when you are snapshotting you'd provide
   /// the overloads and `CFunctionInfo` that would be placed in the external
   /// references array.
+  ///
+  /// V8 picks the overload to call by the number of arguments and, between
+  /// two overloads of the same arity, by whether an argument is an array or a
+  /// typed array; see [`check_overloads`].
+  ///
+  /// Panics if V8 can't tell the overloads apart. Use [`check_overloads`]
+  /// to handle that as an error instead.
   pub fn build_fast(
     self,
     scope: &mut HandleScope<'s, ()>,
     overloads: &[CFunction],
   ) -> Local<'s, FunctionTemplate> {
+    if let Err(error) = check_overloads(overloads) {
+      panic!("invalid fast function overloads: {error}");
+    }
     unsafe {
       scope.cast_local(|sd| {
         v8__FunctionTemplate__New(
@@ -784,6 +899,46 @@ impl FunctionTemplate {
     Self::builder_raw(callback).build(scope)
   }
 
+  /// Creates a function template with a typed slow callback, see
+  /// [`Function::new_typed`], and a fast function whose type information is
+  /// derived from its signature, see [`FastFunction`]. The arguments of the
+  /// fast function must be equivalent to those of the slow callback, which
+  /// V8 calls whenever the fast function can't be used.
+  #[inline(always)]
+  pub fn new_fast<'s, Args, FastArguments, F>(
+    scope: &mut HandleScope<'s, ()>,
+    slow: impl MapFnTo<FunctionCallback, TypedFunctionTag<Args>>,
+    fast: F,
+  ) -> Local<'s, FunctionTemplate>
+  where
+    FastArguments: FastArgs<Slow = Args>,
+    F: FastFunction<FastArguments>,
+  {
+    FunctionBuilder::new_typed(slow)
+      .build_fast(scope, &[CFunction::from_fn(fast)])
+  }
+
+  /// Creates a function template from a closure, which may capture state. See
+  /// [`Function::new_closure`]. The closure is dropped after the template and
+  /// all functions created from it have been garbage collected.
+  #[inline(always)]
+  pub fn new_closure<'s, C>(
+    scope: &mut HandleScope<'s, ()>,
+    callback: C,
+  ) -> Local<'s, FunctionTemplate>
+  where
+    C: for<'a> Fn(
+        &mut HandleScope<'a>,
+        FunctionCallbackArguments<'a>,
+        ReturnValue<Value>,
+      ) + 'static,
+  {
+    let data = closure_data(scope, callback);
+    Self::builder(closure_function_callback::<C>)
+      .data(data.into())
+      .build(scope)
+  }
+
   /// Returns the unique function instance in the current execution context.
   #[inline(always)]
   pub fn get_function<'s>(
@@ -838,6 +993,13 @@ impl FunctionTemplate {
     unsafe { v8__FunctionTemplate__Inherit(self, &*parent) };
   }
 
+  /// Returns true if the given object is an instance of this function
+  /// template, or of a function template that inherits from it.
+  #[inline(always)]
+  pub fn has_instance(&self, object: Local<Value>) -> bool {
+    unsafe { v8__FunctionTemplate__HasInstance(self, &*object) }
+  }
+
   /// Sets the ReadOnly flag in the attributes of the 'prototype' property
   /// of functions created from this FunctionTemplate to true.
   #[inline(always)]
@@ -937,11 +1099,12 @@ impl ObjectTemplate {
   }
 
   #[inline(always)]
-  pub fn set_accessor_with_configuration(
+  pub fn set_accessor_with_configuration<'s>(
     &self,
     key: Local<Name>,
-    configuration: AccessorConfiguration,
+    configuration: impl sealed::IntoAccessorConfiguration<'s>,
   ) {
+    let configuration = configuration.into_accessor_configuration();
     unsafe {
       v8__ObjectTemplate__SetNativeDataProperty(
         self,
@@ -2,10 +2,13 @@
 use crate::binding::v8__TypedArray__kMaxByteLength;
 use crate::support::size_t;
 use crate::ArrayBuffer;
+use crate::BackingStore;
 use crate::HandleScope;
+use crate::Isolate;
 use crate::Local;
 use crate::TypedArray;
 use paste::paste;
+use std::ptr::NonNull;
 
 extern "C" {
   fn v8__TypedArray__Length(this: *const TypedArray) -> size_t;
@@ -22,10 +25,36 @@ impl TypedArray {
   pub fn length(&self) -> usize {
     unsafe { v8__TypedArray__Length(self) }
   }
+
+  /// Returns a pointer to the first element and the number of elements, or
+  /// `None` if `isolate` doesn't own the array, its elements can be reached
+  /// without the isolate (see [`BackingStore::is_exclusive`]), or they aren't
+  /// aligned for `T`.
+  #[inline(always)]
+  fn elements<T>(&self, isolate: &Isolate) -> Option<(*mut T, usize)> {
+    if !std::ptr::eq(self.get_isolate_ptr(), isolate) {
+      return None;
+    }
+    let length = self.length();
+    if length == 0 {
+      // Detached and out-of-bounds arrays have a length of zero as well.
+      return Some((NonNull::dangling().as_ptr(), 0));
+    }
+    if !BackingStore::is_exclusive(&self.get_backing_store()?) {
+      return None;
+    }
+    let data = self.data() as *mut T;
+    // Stores created from Rust memory, such as a `Vec<u8>`, may not be
+    // aligned for the elements.
+    if !data.is_aligned() {
+      return None;
+    }
+    Some((data, length))
+  }
 }
 
 macro_rules! typed_array {
-  ($name:ident) => {
+  ($name:ident, $element:ty) => {
     paste! {
       use crate::$name;
       impl $name {
@@ -48,19 +77,57 @@ macro_rules! typed_array {
 
         #[doc = concat!("The largest ", stringify!($name), " size that can be constructed using `new`.")]
         pub const MAX_LENGTH: usize = crate::binding::[< v8__ $name __kMaxLength >];
+
+        /// Borrows the elements of this array without copying them.
+        ///
+        /// The slice mutably borrows the isolate, so no JavaScript can run and
+        /// the buffer can't be detached or resized while it is alive. Detached
+        /// and out-of-bounds arrays yield an empty slice. Returns `None` if
+        /// `isolate` doesn't own the array, or if its elements can be reached
+        /// without the isolate: when the array is backed by a
+        /// `SharedArrayBuffer`, whose contents other threads may modify at any
+        /// time, or when another ArrayBuffer or a `BackingStore` reference
+        /// shares its buffer's memory. Also returns `None` if the elements
+        /// aren't aligned for the element type, which can happen with backing
+        /// stores created from Rust memory.
+        #[inline(always)]
+        pub fn as_slice<'a>(
+          &'a self,
+          isolate: &'a mut Isolate,
+        ) -> Option<&'a [$element]> {
+          let (data, length) = self.elements(isolate)?;
+          // SAFETY: `elements` checked that the elements are aligned. They
+          // can't be modified or freed without the isolate, which is borrowed
+          // for `'a`.
+          Some(unsafe { std::slice::from_raw_parts(data, length) })
+        }
+
+        /// Mutably borrows the elements of this array without copying them.
+        ///
+        /// See [`Self::as_slice`].
+        #[inline(always)]
+        pub fn as_mut_slice<'a>(
+          &'a self,
+          isolate: &'a mut Isolate,
+        ) -> Option<&'a mut [$element]> {
+          let (data, length) = self.elements(isolate)?;
+          // SAFETY: See `as_slice`. Any other slice of this isolate's arrays
+          // would need another borrow of the isolate, so this one is unique.
+          Some(unsafe { std::slice::from_raw_parts_mut(data, length) })
+        }
       }
     }
   };
 }
 
-typed_array!(Uint8Array);
-typed_array!(Uint8ClampedArray);
-typed_array!(Int8Array);
-typed_array!(Uint16Array);
-typed_array!(Int16Array);
-typed_array!(Uint32Array);
-typed_array!(Int32Array);
-typed_array!(Float32Array);
-typed_array!(Float64Array);
-typed_array!(BigUint64Array);
-typed_array!(BigInt64Array);
+typed_array!(Uint8Array, u8);
+typed_array!(Uint8ClampedArray, u8);
+typed_array!(Int8Array, i8);
+typed_array!(Uint16Array, u16);
+typed_array!(Int16Array, i16);
+typed_array!(Uint32Array, u32);
+typed_array!(Int32Array, i32);
+typed_array!(Float32Array, f32);
+typed_array!(Float64Array, f64);
+typed_array!(BigUint64Array, u64);
+typed_array!(BigInt64Array, i64);
@@ -0,0 +1,140 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Rust values owned by JavaScript objects, in the manner of Node's
+//! `ObjectWrap`.
+
+use std::any::TypeId;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::ops::Deref;
+
+use crate::object_tag::ObjectTag;
+use crate::Global;
+use crate::HandleScope;
+use crate::Local;
+use crate::Object;
+use crate::Weak;
+
+/// Tags wrapped objects, whose value is their `Wrapped`.
+static WRAPPED_TAG: ObjectTag = ObjectTag::new();
+
+/// A Rust value owned by a JavaScript object.
+///
+/// [`Wrapped::wrap`] moves a value to the heap and attaches it to an object,
+/// tagged with its type. [`Wrapped::unwrap`] retrieves it from the object,
+/// checking the type. The value is dropped after the object has been garbage
+/// collected, or when the isolate is disposed.
+///
+/// The object only holds itself weakly. Use [`Wrapped::retain`] and
+/// [`Wrapped::release`] to keep it, and with it the value, alive while Rust
+/// code still needs it, e.g. during an asynchronous operation.
+#[repr(C)]
+pub struct Wrapped<T: 'static> {
+  // Must be the first field: `unwrap` reads it before the type is known.
+  type_id: TypeId,
+  handle: Option<Weak<Object>>,
+  strong: RefCell<Option<Global<Object>>>,
+  ref_count: Cell<usize>,
+  value: T,
+}
+
+impl<T: 'static> Wrapped<T> {
+  /// The number of internal fields used by `Wrapped`. Objects must have at
+  /// least this many internal fields to be wrapped.
+  pub const INTERNAL_FIELD_COUNT: usize = ObjectTag::FIELD_COUNT;
+
+  /// Makes `object` own `value` and returns the wrapper.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `object` has fewer than [`Self::INTERNAL_FIELD_COUNT`]
+  /// internal fields, or if it already owns a value.
+  pub fn wrap<'a>(
+    scope: &mut HandleScope<'_, ()>,
+    object: Local<'a, Object>,
+    value: T,
+  ) -> &'a Self {
+    assert!(
+      object.internal_field_count() >= Self::INTERNAL_FIELD_COUNT,
+      "object has too few internal fields to be wrapped"
+    );
+    assert!(!WRAPPED_TAG.is_tagged(&object), "object is already wrapped");
+    let wrapped = Box::into_raw(Box::new(Self {
+      type_id: TypeId::of::<T>(),
+      handle: None,
+      strong: RefCell::new(None),
+      ref_count: Cell::new(0),
+      value,
+    }));
+    let handle = Weak::with_guaranteed_finalizer(
+      scope,
+      object,
+      // SAFETY: The wrapper is freed exactly once, after `object` is gone.
+      Box::new(move || drop(unsafe { Box::from_raw(wrapped) })),
+    );
+    unsafe { (*wrapped).handle = Some(handle) };
+    WRAPPED_TAG.tag(&object, wrapped as *const c_void);
+    // SAFETY: The wrapper lives as long as `object`, which outlives `'a`.
+    unsafe { &*wrapped }
+  }
+
+  /// Returns the wrapper of `object`, or `None` if it does not own a value of
+  /// type `T`.
+  pub fn unwrap<'a>(object: Local<'a, Object>) -> Option<&'a Self> {
+    // SAFETY: Tagged objects hold a `Wrapped`, whose type id is at the same
+    // offset for every `T`.
+    let wrapped = WRAPPED_TAG.value(&object)? as *const Self;
+    if unsafe { *(wrapped as *const TypeId) } != TypeId::of::<T>() {
+      return None;
+    }
+    Some(unsafe { &*wrapped })
+  }
+
+  /// Returns the object that owns the value.
+  pub fn object<'s>(
+    &self,
+    scope: &mut HandleScope<'s, ()>,
+  ) -> Local<'s, Object> {
+    // The object can't have been collected while `self` is borrowed.
+    self.handle.as_ref().unwrap().to_local(scope).unwrap()
+  }
+
+  /// Increments the reference count of the wrapper. While it is positive,
+  /// the object is kept alive even if JavaScript no longer refers to it.
+  pub fn retain(&self, scope: &mut HandleScope<'_, ()>) {
+    if self.ref_count.get() == 0 {
+      let object = self.object(scope);
+      *self.strong.borrow_mut() = Some(Global::new(scope, object));
+    }
+    self.ref_count.set(self.ref_count.get() + 1);
+  }
+
+  /// Decrements the reference count of the wrapper. When it drops to zero,
+  /// the object can be garbage collected again.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the reference count is already zero.
+  pub fn release(&self) {
+    let ref_count = self.ref_count.get();
+    assert!(ref_count > 0, "released a wrapper that was not retained");
+    self.ref_count.set(ref_count - 1);
+    if ref_count == 1 {
+      self.strong.borrow_mut().take();
+    }
+  }
+
+  /// Returns the reference count of the wrapper.
+  pub fn ref_count(&self) -> usize {
+    self.ref_count.get()
+  }
+}
+
+impl<T: 'static> Deref for Wrapped<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.value
+  }
+}
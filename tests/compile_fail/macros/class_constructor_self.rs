@@ -0,0 +1,13 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+pub struct Counter;
+
+#[v8::class]
+impl Counter {
+  #[constructor]
+  fn new(&self) -> Self {
+    Self
+  }
+}
+
+pub fn main() {}
@@ -0,0 +1,8 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+pub struct Counter;
+
+#[v8::class(title = "Counter")]
+impl Counter {}
+
+pub fn main() {}
@@ -0,0 +1,18 @@
+// Copyright 2019-2020 the Deno authors. All rights reserved. MIT license.
+
+pub fn main() {
+  let mut isolate = v8::Isolate::new(mock());
+  let mut scope1 = v8::HandleScope::new(&mut isolate);
+  let context = v8::Context::new(&mut scope1, Default::default());
+  let mut scope2 = v8::ContextScope::new(&mut scope1, context);
+
+  let array: v8::Local<v8::Float64Array> = mock();
+  let slice = array.as_slice(&mut scope2).unwrap();
+  // Running JavaScript could detach the buffer while the slice is alive.
+  let _buffer = array.buffer(&mut scope2);
+  let _first = slice[0];
+}
+
+fn mock<T>() -> T {
+  unimplemented!()
+}
@@ -18,6 +18,7 @@ use std::sync::Mutex;
 use v8::fast_api;
 use v8::inspector::ChannelBase;
 use v8::AccessorConfiguration;
+use v8::ToV8;
 
 // TODO(piscisaureus): Ideally there would be no need to import this trait.
 use v8::MapFnTo;
@@ -1168,6 +1169,251 @@ fn try_catch_caught_lifetime() {
     .contains("DANG"));
 }
 
+#[test]
+fn try_catch_js_error() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  isolate.set_capture_stack_trace_for_uncaught_exceptions(true, 10);
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+    {
+      let tc = &mut v8::TryCatch::new(scope);
+      let result = eval(tc, "1 + 1");
+      let value = tc.result(result).unwrap();
+      assert_eq!(value.int32_value(tc), Some(2));
+    }
+    {
+      let tc = &mut v8::TryCatch::new(scope);
+      let result = eval(
+        tc,
+        "function inner() {\n  throw new TypeError('foo', { cause: new Error('bar') });\n}\ninner();",
+      );
+      let error = tc.result(result).unwrap_err();
+      assert_eq!(error.name.as_deref(), Some("TypeError"));
+      assert_eq!(error.message.as_deref(), Some("foo"));
+      assert_eq!(error.exception_message, "Uncaught TypeError: foo");
+      assert_eq!(error.location.line_number, Some(2));
+      assert_eq!(
+        error.location.source_line.as_deref(),
+        Some("  throw new TypeError('foo', { cause: new Error('bar') });")
+      );
+      assert!(error
+        .location
+        .stack
+        .as_deref()
+        .unwrap()
+        .starts_with("TypeError: foo"));
+      assert_eq!(error.location.frames.len(), 2);
+      assert_eq!(
+        error.location.frames[0].function_name.as_deref(),
+        Some("inner")
+      );
+      assert_eq!(error.location.frames[0].line_number, Some(2));
+      assert_eq!(error.location.frames[1].line_number, Some(4));
+
+      let cause = error.cause.as_deref().unwrap();
+      assert_eq!(cause.message.as_deref(), Some("bar"));
+      assert!(cause.cause.is_none());
+      let source = std::error::Error::source(&error).unwrap();
+      assert!(source.to_string().starts_with("Error: bar"));
+      assert!(error.to_string().contains("\nCaused by: Error: bar"));
+    }
+    {
+      let tc = &mut v8::TryCatch::new(scope);
+      let result = eval(
+        tc,
+        "throw new AggregateError([new Error('a'), 'b'], 'many');",
+      );
+      let error = tc.result(result).unwrap_err();
+      assert_eq!(error.name.as_deref(), Some("AggregateError"));
+      let aggregated = error.aggregated.unwrap();
+      assert_eq!(aggregated.len(), 2);
+      assert_eq!(aggregated[0].message.as_deref(), Some("a"));
+      assert_eq!(aggregated[1].name, None);
+      assert_eq!(aggregated[1].exception_message, "Uncaught b");
+    }
+    {
+      // Cyclic causes are only converted once.
+      let tc = &mut v8::TryCatch::new(scope);
+      let result =
+        eval(tc, "const e = new Error('loop'); e.cause = e; throw e;");
+      let error = tc.result(result).unwrap_err();
+      assert!(error.cause.is_none());
+    }
+    {
+      // A failure without an exception is not reported as a termination.
+      let tc = &mut v8::TryCatch::new(scope);
+      let error = tc.result::<v8::Value>(None).unwrap_err();
+      assert_ne!(error.exception_message, "execution terminated");
+      assert!(error.name.is_none());
+    }
+  }
+}
+
+#[cfg(feature = "source_map")]
+#[test]
+fn source_map() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  isolate.set_capture_stack_trace_for_uncaught_exceptions(true, 10);
+  v8::source_map::install(isolate, v8::source_map::SourceMaps::new());
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+
+    // Maps line 2, column 3 to orig.ts:10:5 and line 4, column 1 to
+    // orig.ts:20:1.
+    let map = br#"{"version":3,"sourceRoot":"src","sources":["orig.ts"],"names":[],"mappings":";EAASI;;AAAUJ"}"#;
+    let source_map = v8::source_map::SourceMap::parse(scope, map).unwrap();
+    assert_eq!(source_map.sources(), ["src/orig.ts"]);
+    let location = source_map.lookup(2, 9).unwrap();
+    assert_eq!(location.source, "src/orig.ts");
+    assert_eq!((location.line, location.column), (10, 5));
+    assert!(source_map.lookup(1, 1).is_none());
+    assert_eq!(
+      v8::source_map::SourceMap::parse(scope, b"{\"version\":2}").unwrap_err(),
+      v8::source_map::SourceMapError::UnsupportedVersion
+    );
+
+    let escaped_map = std::str::from_utf8(map).unwrap().replace('"', "%22");
+    let code = format!(
+      "function inner() {{\n  throw new Error('mapped');\n}}\ninner();\n//# sourceMappingURL=data:application/json,{}",
+      escaped_map
+    );
+    let code = v8::String::new(scope, &code).unwrap();
+    let resource_name = v8::String::new(scope, "gen.js").unwrap();
+    let origin = v8::ScriptOrigin::new(
+      scope,
+      resource_name.into(),
+      0,
+      0,
+      false,
+      0,
+      None,
+      false,
+      false,
+      false,
+      None,
+    );
+    let script = v8::Script::compile(scope, code, Some(&origin)).unwrap();
+    let unbound_script = script.get_unbound_script(scope);
+    assert!(
+      v8::source_map::register_script(scope, "gen.js", unbound_script).unwrap()
+    );
+
+    let tc = &mut v8::TryCatch::new(scope);
+    let result = script.run(tc);
+    let mut error = tc.result(result).unwrap_err();
+    let stack = error.location.stack.clone().unwrap();
+    assert_eq!(
+      stack,
+      "Error: mapped\n    at inner (src/orig.ts:10:5)\n    at src/orig.ts:20:1"
+    );
+
+    assert_eq!(error.location.frames[0].line_number, Some(2));
+    let source_maps = v8::source_map::get_source_maps(tc).unwrap();
+    source_maps.remap_js_error(&mut error);
+    assert_eq!(
+      error.location.frames[0].script_name.as_deref(),
+      Some("src/orig.ts")
+    );
+    assert_eq!(error.location.frames[0].line_number, Some(10));
+    assert_eq!(error.location.frames[1].line_number, Some(20));
+
+    // A `stack` formatted by V8 itself is rewritten as well.
+    error.location.stack = Some(
+      "Error: mapped\n    at inner (gen.js:2:9)\n    at gen.js:4:1".into(),
+    );
+    source_maps.remap_js_error(&mut error);
+    assert_eq!(error.location.stack.as_deref(), Some(stack.as_str()));
+    assert_eq!(
+      error.to_string(),
+      "Error: mapped\n    at inner (src/orig.ts:10:5)\n    at src/orig.ts:20:1"
+    );
+
+    // Only the locations are rewritten: V8 still formats the frames.
+    let source_map = v8::source_map::SourceMap::parse(tc, map).unwrap();
+    let source_maps = v8::source_map::get_source_maps(tc).unwrap();
+    source_maps.insert("method.js", source_map);
+    let code = v8::String::new(
+      tc,
+      "class Foo {\n  bar() { return new Error('method').stack; }\n}\nnew Foo().bar();",
+    )
+    .unwrap();
+    let resource_name = v8::String::new(tc, "method.js").unwrap();
+    let origin = v8::ScriptOrigin::new(
+      tc,
+      resource_name.into(),
+      0,
+      0,
+      false,
+      0,
+      None,
+      false,
+      false,
+      false,
+      None,
+    );
+    let script = v8::Script::compile(tc, code, Some(&origin)).unwrap();
+    let stack = script.run(tc).unwrap();
+    assert_eq!(
+      stack.to_rust_string_lossy(tc),
+      "Error: method\n    at Foo.bar (src/orig.ts:10:5)\n    at src/orig.ts:20:1"
+    );
+
+    // `Error.prepareStackTrace` is still called.
+    let stack = eval(
+      tc,
+      "Error.prepareStackTrace = (error, sites) => `${error.message} ${sites.length}`;
+      const stack = new Error('custom').stack;
+      delete Error.prepareStackTrace;
+      stack",
+    )
+    .unwrap();
+    assert_eq!(stack.to_rust_string_lossy(tc), "custom 1");
+  }
+}
+
+#[cfg(feature = "source_map")]
+#[test]
+fn source_map_previous_prepare_stack_trace() {
+  fn callback<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    _error: v8::Local<v8::Value>,
+    _sites: v8::Local<v8::Array>,
+  ) -> v8::Local<'s, v8::Value> {
+    v8::String::new(scope, "Custom\n    at gen.js:4:1")
+      .unwrap()
+      .into()
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  isolate.set_prepare_stack_trace_callback(callback);
+  v8::source_map::install(isolate, v8::source_map::SourceMaps::new());
+  // Installing again doesn't chain to the callback of the first `install`.
+  v8::source_map::install(isolate, v8::source_map::SourceMaps::new());
+
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let map = br#"{"version":3,"sourceRoot":"src","sources":["orig.ts"],"names":[],"mappings":";EAASI;;AAAUJ"}"#;
+  let source_map = v8::source_map::SourceMap::parse(scope, map).unwrap();
+  v8::source_map::get_source_maps(scope)
+    .unwrap()
+    .insert("gen.js", source_map);
+
+  // The earlier callback formats the stack, and its locations are remapped.
+  let stack = eval(scope, "new Error('x').stack").unwrap();
+  assert_eq!(
+    stack.to_rust_string_lossy(scope),
+    "Custom\n    at src/orig.ts:20:1"
+  );
+}
+
 #[test]
 fn throw_exception() {
   let _setup_guard = setup::parallel_test();
@@ -1959,6 +2205,65 @@ fn instance_template_with_internal_field() {
   assert_eq!(internal_field.integer_value(scope).unwrap(), 42);
 }
 
+#[test]
+fn wrapped_object() {
+  static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+  struct Tracked(&'static str);
+
+  impl Drop for Tracked {
+    fn drop(&mut self) {
+      DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+
+  let retained = {
+    let scope = &mut v8::HandleScope::new(scope);
+    let templ = v8::ObjectTemplate::new(scope);
+    templ
+      .set_internal_field_count(v8::Wrapped::<Tracked>::INTERNAL_FIELD_COUNT);
+    let first = templ.new_instance(scope).unwrap();
+    let second = templ.new_instance(scope).unwrap();
+
+    let wrapped = v8::Wrapped::wrap(scope, first, Tracked("first"));
+    assert_eq!(wrapped.0, "first");
+    assert_eq!(wrapped.object(scope), first);
+    v8::Wrapped::wrap(scope, second, Tracked("second"));
+
+    let unwrapped = v8::Wrapped::<Tracked>::unwrap(second).unwrap();
+    assert_eq!(unwrapped.0, "second");
+    assert!(v8::Wrapped::<u32>::unwrap(second).is_none());
+    let plain = v8::Object::new(scope);
+    assert!(v8::Wrapped::<Tracked>::unwrap(plain).is_none());
+    let empty = templ.new_instance(scope).unwrap();
+    assert!(v8::Wrapped::<Tracked>::unwrap(empty).is_none());
+
+    unwrapped.retain(scope);
+    unwrapped.retain(scope);
+    assert_eq!(unwrapped.ref_count(), 2);
+    unwrapped.release();
+    unwrapped as *const v8::Wrapped<Tracked>
+  };
+
+  DROPPED.store(0, Ordering::SeqCst);
+  scope.request_garbage_collection_for_testing(v8::GarbageCollectionType::Full);
+  assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+
+  // SAFETY: The object, and with it the wrapper, is still retained.
+  let retained = unsafe { &*retained };
+  assert_eq!(retained.0, "second");
+  assert_eq!(retained.ref_count(), 1);
+  retained.release();
+  scope.request_garbage_collection_for_testing(v8::GarbageCollectionType::Full);
+  assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+}
+
 #[test]
 fn object_template_set_accessor() {
   let _setup_guard = setup::parallel_test();
@@ -2939,6 +3244,158 @@ fn object_template_set_indexed_property_handler() {
   assert!(desc.get(scope, writable_key).unwrap().boolean_value(scope));
 }
 
+#[test]
+fn object_template_host_object() {
+  struct Store {
+    values: RefCell<HashMap<String, i32>>,
+    list: RefCell<Vec<i32>>,
+    dropped: Arc<AtomicUsize>,
+  }
+
+  impl Drop for Store {
+    fn drop(&mut self) {
+      self.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+  }
+
+  struct Other;
+
+  impl v8::HostObject for Other {}
+
+  impl v8::HostObject for Store {
+    fn get<'s>(
+      &self,
+      scope: &mut v8::HandleScope<'s>,
+      key: v8::PropertyKey<'s>,
+    ) -> Option<v8::Local<'s, v8::Value>> {
+      let value = match key {
+        v8::PropertyKey::Index(index) => {
+          *self.list.borrow().get(index as usize)?
+        }
+        key => *self.values.borrow().get(&key.to_rust_string(scope)?)?,
+      };
+      Some(v8::Integer::new(scope, value).into())
+    }
+
+    fn set<'s>(
+      &self,
+      scope: &mut v8::HandleScope<'s>,
+      key: v8::PropertyKey<'s>,
+      value: v8::Local<'s, v8::Value>,
+    ) -> bool {
+      let Some(value) = value.int32_value(scope) else {
+        return false;
+      };
+      match key {
+        v8::PropertyKey::Index(index) => {
+          let mut list = self.list.borrow_mut();
+          if index as usize >= list.len() {
+            list.resize(index as usize + 1, 0);
+          }
+          list[index as usize] = value;
+        }
+        key => {
+          let Some(name) = key.to_rust_string(scope) else {
+            return false;
+          };
+          self.values.borrow_mut().insert(name, value);
+        }
+      }
+      true
+    }
+
+    fn delete<'s>(
+      &self,
+      scope: &mut v8::HandleScope<'s>,
+      key: v8::PropertyKey<'s>,
+    ) -> Option<bool> {
+      let name = key.to_rust_string(scope)?;
+      self.values.borrow_mut().remove(&name).map(|_| true)
+    }
+
+    fn own_keys<'s>(
+      &self,
+      scope: &mut v8::HandleScope<'s>,
+    ) -> Vec<v8::PropertyKey<'s>> {
+      let mut keys = (0..self.list.borrow().len() as u32)
+        .map(v8::PropertyKey::Index)
+        .collect::<Vec<_>>();
+      let mut names = self.values.borrow().keys().cloned().collect::<Vec<_>>();
+      names.sort();
+      for name in names {
+        let name = v8::String::new(scope, &name).unwrap();
+        keys.push(v8::PropertyKey::Name(name.into()));
+      }
+      keys
+    }
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let dropped = Arc::new(AtomicUsize::new(0));
+
+  {
+    let scope = &mut v8::HandleScope::new(scope);
+    let templ = v8::ObjectTemplate::new(scope);
+    templ.set_host_object_handler();
+    assert_eq!(templ.internal_field_count(), 2);
+
+    let store = Store {
+      values: RefCell::new(HashMap::from([("a".to_string(), 1)])),
+      list: RefCell::new(vec![10, 20]),
+      dropped: dropped.clone(),
+    };
+    let obj = templ.new_host_object_instance(scope, store).unwrap();
+    let name = v8::String::new(scope, "store").unwrap();
+    context.global(scope).set(scope, name.into(), obj.into());
+
+    let result = eval(
+      scope,
+      "store.b = 2; store[3] = 40; delete store.a;
+       [store.a, store.b, store[1], 'b' in store, 'a' in store,
+        Object.keys(store).join(), store.missing].join(' ')",
+    )
+    .unwrap();
+    assert_eq!(
+      result.to_rust_string_lossy(scope),
+      " 2 20 true false 0,1,2,3,b "
+    );
+
+    let store = obj.get_host_object::<Store>().unwrap();
+    assert_eq!(store.values.borrow().get("b"), Some(&2));
+    assert_eq!(*store.list.borrow(), [10, 20, 0, 40]);
+    assert!(obj.get_host_object::<Other>().is_none());
+
+    // Ordinary instances of the template are not intercepted.
+    let plain = templ.new_instance(scope).unwrap();
+    assert!(plain.get_host_object::<Store>().is_none());
+    // Nor are instances whose fields hold pointers set by other code.
+    static FOREIGN: [u64; 4] = [0; 4];
+    for field in 0..2 {
+      plain.set_aligned_pointer_in_internal_field(
+        field,
+        FOREIGN.as_ptr() as *const std::ffi::c_void,
+      );
+    }
+    assert!(plain.get_host_object::<Store>().is_none());
+    let key = v8::String::new(scope, "x").unwrap();
+    let value = v8::Integer::new(scope, 5);
+    plain.set(scope, key.into(), value.into());
+    assert!(plain
+      .get(scope, key.into())
+      .unwrap()
+      .strict_equals(value.into()));
+
+    eval(scope, "delete globalThis.store").unwrap();
+  }
+
+  scope.request_garbage_collection_for_testing(v8::GarbageCollectionType::Full);
+  assert_eq!(dropped.load(Ordering::SeqCst), 1);
+}
+
 #[test]
 fn object() {
   let _setup_guard = setup::parallel_test();
@@ -3607,74 +4064,243 @@ fn promise_rejected() {
     assert_eq!(result.to_rust_string_lossy(scope), "test".to_string());
   }
 }
+
 #[test]
-fn proxy() {
+fn promise_future() {
   let _setup_guard = setup::parallel_test();
   let isolate = &mut v8::Isolate::new(Default::default());
   {
     let scope = &mut v8::HandleScope::new(isolate);
     let context = v8::Context::new(scope, Default::default());
     let scope = &mut v8::ContextScope::new(scope, context);
-    let target = v8::Object::new(scope);
-    let handler = v8::Object::new(scope);
-    let maybe_proxy = v8::Proxy::new(scope, target, handler);
-    assert!(maybe_proxy.is_some());
-    let proxy = maybe_proxy.unwrap();
-    assert!(target == proxy.get_target(scope));
-    assert!(handler == proxy.get_handler(scope));
-    assert!(!proxy.is_revoked());
-    proxy.revoke();
-    assert!(proxy.is_revoked());
+
+    let promise = eval(scope, "Promise.resolve(20).then(x => x + 22)").unwrap();
+    let promise = v8::Local::<v8::Promise>::try_from(promise).unwrap();
+    assert_eq!(promise.state(), v8::PromiseState::Pending);
+    let future = v8::PromiseFuture::new(scope, promise);
+    let result = v8::run_event_loop_until(scope, future).unwrap();
+    let result = v8::Local::new(scope, result);
+    assert_eq!(result.int32_value(scope), Some(42));
+
+    let promise =
+      eval(scope, "Promise.resolve().then(() => { throw 'no'; })").unwrap();
+    let promise = v8::Local::<v8::Promise>::try_from(promise).unwrap();
+    let future = v8::PromiseFuture::new(scope, promise);
+    let reason = v8::run_event_loop_until(scope, future).unwrap_err();
+    let reason = v8::Local::new(scope, reason);
+    assert_eq!(reason.to_rust_string_lossy(scope), "no");
+
+    // Already settled promises complete without a checkpoint.
+    let promise = eval(scope, "Promise.reject(1)").unwrap();
+    let promise = v8::Local::<v8::Promise>::try_from(promise).unwrap();
+    let future = v8::PromiseFuture::new(scope, promise);
+    assert!(v8::run_event_loop_until(scope, future).is_err());
   }
 }
 
-fn fn_callback_external(
-  scope: &mut v8::HandleScope,
-  args: v8::FunctionCallbackArguments,
-  mut rv: v8::ReturnValue<v8::Value>,
-) {
-  assert_eq!(args.length(), 0);
-  let data = args.data();
-  let external = v8::Local::<v8::External>::try_from(data).unwrap();
-  let data =
-    unsafe { std::slice::from_raw_parts(external.value() as *mut u8, 5) };
-  assert_eq!(&[0, 1, 2, 3, 4], data);
-  let s = v8::String::new(scope, "Hello callback!").unwrap();
-  assert!(rv.get(scope).is_undefined());
-  rv.set(s.into());
-}
+#[test]
+fn future_to_promise() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
 
-fn fn_callback(
-  scope: &mut v8::HandleScope,
-  args: v8::FunctionCallbackArguments,
-  mut rv: v8::ReturnValue<v8::Value>,
-) {
-  assert_eq!(args.length(), 0);
-  let s = v8::String::new(scope, "Hello callback!").unwrap();
-  assert!(rv.get(scope).is_undefined());
-  rv.set(s.into());
-}
+    let fulfilled = v8::future_to_promise(scope, async { 7 }, |scope, n| {
+      Ok(v8::Integer::new(scope, n).into())
+    });
+    let rejected =
+      v8::future_to_promise(scope, async { "boom" }, |scope, s| {
+        Err(v8::String::new(scope, s).unwrap().into())
+      });
+    assert_eq!(fulfilled.state(), v8::PromiseState::Pending);
+    assert_eq!(rejected.state(), v8::PromiseState::Pending);
 
-fn fn_callback_new(
-  scope: &mut v8::HandleScope,
-  args: v8::FunctionCallbackArguments,
-  mut rv: v8::ReturnValue<v8::Value>,
-) {
-  assert_eq!(args.length(), 0);
-  assert!(args.new_target().is_object());
-  let recv = args.this();
-  let key = v8::String::new(scope, "works").unwrap();
-  let value = v8::Boolean::new(scope, true);
-  assert!(recv.set(scope, key.into(), value.into()).unwrap());
-  assert!(rv.get(scope).is_undefined());
-  rv.set(recv.into());
+    let future = v8::PromiseFuture::new(scope, fulfilled);
+    let result = v8::run_event_loop_until(scope, future).unwrap();
+    let result = v8::Local::new(scope, result);
+    assert_eq!(result.int32_value(scope), Some(7));
+
+    assert_eq!(rejected.state(), v8::PromiseState::Rejected);
+    let reason = rejected.result(scope);
+    assert_eq!(reason.to_rust_string_lossy(scope), "boom");
+  }
 }
 
-fn fn_callback2(
-  scope: &mut v8::HandleScope,
-  args: v8::FunctionCallbackArguments,
-  mut rv: v8::ReturnValue<v8::Value>,
-) {
+#[test]
+fn promise_combinators() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+
+    let r1 = v8::PromiseResolver::new(scope).unwrap();
+    let r2 = v8::PromiseResolver::new(scope).unwrap();
+    let p1 = r1.get_promise(scope);
+    let p2 = r2.get_promise(scope);
+
+    let all = v8::Promise::all(scope, [p1, p2]).unwrap();
+    let all_settled = v8::Promise::all_settled(scope, [p1, p2]).unwrap();
+    let any = v8::Promise::any(scope, [p1, p2]).unwrap();
+    let race = v8::Promise::race(scope, [p1, p2]).unwrap();
+
+    let value = v8::Integer::new(scope, 1);
+    r1.resolve(scope, value.into());
+    let reason = v8::Integer::new(scope, 2);
+    r2.reject(scope, reason.into());
+    scope.perform_microtask_checkpoint();
+
+    assert_eq!(all.state(), v8::PromiseState::Rejected);
+    assert!(all.result(scope).strict_equals(reason.into()));
+    assert_eq!(any.state(), v8::PromiseState::Fulfilled);
+    assert!(any.result(scope).strict_equals(value.into()));
+    assert_eq!(race.state(), v8::PromiseState::Fulfilled);
+    assert!(race.result(scope).strict_equals(value.into()));
+    assert_eq!(all_settled.state(), v8::PromiseState::Fulfilled);
+    let results = all_settled.result(scope);
+    let json = v8::json::stringify(scope, results).unwrap();
+    assert_eq!(
+      json.to_rust_string_lossy(scope),
+      r#"[{"status":"fulfilled","value":1},{"status":"rejected","reason":2}]"#
+    );
+
+    let empty = v8::Promise::all(scope, []).unwrap();
+    assert_eq!(empty.state(), v8::PromiseState::Fulfilled);
+
+    let empty = v8::Promise::any(scope, []).unwrap();
+    assert_eq!(empty.state(), v8::PromiseState::Rejected);
+    let error = empty.result(scope);
+    let global = context.global(scope);
+    let key = v8::String::new(scope, "error").unwrap();
+    global.set(scope, key.into(), error);
+    let is_aggregate_error = eval(
+      scope,
+      "error instanceof AggregateError && !error.errors.length",
+    )
+    .unwrap();
+    assert!(is_aggregate_error.is_true());
+
+    // Scripts cannot change what the combinators do.
+    eval(
+      scope,
+      "Promise.all = () => 42; Promise = AggregateError = null;",
+    )
+    .unwrap();
+    let all = v8::Promise::all(scope, [p1]).unwrap();
+    scope.perform_microtask_checkpoint();
+    assert_eq!(all.state(), v8::PromiseState::Fulfilled);
+    let empty = v8::Promise::any(scope, []).unwrap();
+    assert_eq!(empty.state(), v8::PromiseState::Rejected);
+  }
+}
+
+#[test]
+fn unhandled_rejection_tracker() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  v8::UnhandledRejectionTracker::install(isolate);
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+
+    eval(
+      scope,
+      r#"
+      Promise.reject(new Error("unhandled"));
+      const late = Promise.reject(new Error("handled late"));
+      Promise.resolve().then(() => late.catch(() => {}));
+      Promise.reject(new Error("handled")).catch(() => {});
+      "#,
+    )
+    .unwrap();
+    scope.perform_microtask_checkpoint();
+
+    let tracker = v8::UnhandledRejectionTracker::get(scope).unwrap();
+    assert!(tracker.has_unhandled());
+    let unhandled = tracker.take_unhandled();
+    assert!(!v8::UnhandledRejectionTracker::get(scope)
+      .unwrap()
+      .has_unhandled());
+    assert_eq!(unhandled.len(), 1);
+    let promise = v8::Local::new(scope, &unhandled[0].promise);
+    assert_eq!(promise.state(), v8::PromiseState::Rejected);
+    let reason = v8::Local::new(scope, &unhandled[0].reason);
+    assert_eq!(reason.to_rust_string_lossy(scope), "Error: unhandled");
+  }
+}
+
+#[test]
+fn proxy() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+    let target = v8::Object::new(scope);
+    let handler = v8::Object::new(scope);
+    let maybe_proxy = v8::Proxy::new(scope, target, handler);
+    assert!(maybe_proxy.is_some());
+    let proxy = maybe_proxy.unwrap();
+    assert!(target == proxy.get_target(scope));
+    assert!(handler == proxy.get_handler(scope));
+    assert!(!proxy.is_revoked());
+    proxy.revoke();
+    assert!(proxy.is_revoked());
+  }
+}
+
+fn fn_callback_external(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue<v8::Value>,
+) {
+  assert_eq!(args.length(), 0);
+  let data = args.data();
+  let external = v8::Local::<v8::External>::try_from(data).unwrap();
+  let data =
+    unsafe { std::slice::from_raw_parts(external.value() as *mut u8, 5) };
+  assert_eq!(&[0, 1, 2, 3, 4], data);
+  let s = v8::String::new(scope, "Hello callback!").unwrap();
+  assert!(rv.get(scope).is_undefined());
+  rv.set(s.into());
+}
+
+fn fn_callback(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue<v8::Value>,
+) {
+  assert_eq!(args.length(), 0);
+  let s = v8::String::new(scope, "Hello callback!").unwrap();
+  assert!(rv.get(scope).is_undefined());
+  rv.set(s.into());
+}
+
+fn fn_callback_new(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue<v8::Value>,
+) {
+  assert_eq!(args.length(), 0);
+  assert!(args.new_target().is_object());
+  let recv = args.this();
+  let key = v8::String::new(scope, "works").unwrap();
+  let value = v8::Boolean::new(scope, true);
+  assert!(recv.set(scope, key.into(), value.into()).unwrap());
+  assert!(rv.get(scope).is_undefined());
+  rv.set(recv.into());
+}
+
+fn fn_callback2(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue<v8::Value>,
+) {
   assert_eq!(args.length(), 2);
   let arg1_val = v8::String::new(scope, "arg1").unwrap();
   let arg1 = args.get(0);
@@ -3758,6 +4384,348 @@ fn function_builder_raw() {
   }
 }
 
+#[test]
+fn function_closures() {
+  struct DropGuard(Arc<AtomicUsize>);
+
+  impl Drop for DropGuard {
+    fn drop(&mut self) {
+      self.0.fetch_add(1, Ordering::SeqCst);
+    }
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let dropped = Arc::new(AtomicUsize::new(0));
+
+  {
+    let scope = &mut v8::HandleScope::new(scope);
+    let global = context.global(scope);
+
+    let counter = std::rc::Rc::new(std::cell::Cell::new(0));
+    let guard = DropGuard(dropped.clone());
+    let count = counter.clone();
+    let function = v8::Function::new_closure(
+      scope,
+      move |scope: &mut v8::HandleScope,
+            args: v8::FunctionCallbackArguments,
+            mut rv: v8::ReturnValue| {
+        let _ = &guard;
+        count.set(count.get() + args.get(0).int32_value(scope).unwrap());
+        rv.set_int32(count.get());
+      },
+    )
+    .unwrap();
+    let name = v8::String::new(scope, "add").unwrap();
+    global.set(scope, name.into(), function.into());
+
+    let prefix = String::from("hello ");
+    let guard = DropGuard(dropped.clone());
+    let templ = v8::FunctionTemplate::new_closure(
+      scope,
+      move |scope: &mut v8::HandleScope,
+            args: v8::FunctionCallbackArguments,
+            mut rv: v8::ReturnValue| {
+        let _ = &guard;
+        let name = args.get(0).to_rust_string_lossy(scope);
+        let greeting = v8::String::new(scope, &(prefix.clone() + &name));
+        rv.set(greeting.unwrap().into());
+      },
+    );
+    let function = templ.get_function(scope).unwrap();
+    let name = v8::String::new(scope, "greet").unwrap();
+    global.set(scope, name.into(), function.into());
+
+    let stored = std::rc::Rc::new(std::cell::Cell::new(7));
+    let (get_stored, set_stored) = (stored.clone(), stored.clone());
+    let guard = DropGuard(dropped.clone());
+    let obj = v8::Object::new(scope);
+    let key = v8::String::new(scope, "value").unwrap();
+    let config = v8::AccessorConfiguration::new_closure_with_setter(
+      scope,
+      move |scope: &mut v8::HandleScope,
+            _key: v8::Local<v8::Name>,
+            _args: v8::PropertyCallbackArguments,
+            mut rv: v8::ReturnValue| {
+        let _ = &guard;
+        rv.set(v8::Integer::new(scope, get_stored.get()).into());
+      },
+      move |scope: &mut v8::HandleScope,
+            _key: v8::Local<v8::Name>,
+            value: v8::Local<v8::Value>,
+            _args: v8::PropertyCallbackArguments,
+            _rv: v8::ReturnValue<()>| {
+        set_stored.set(value.int32_value(scope).unwrap());
+      },
+    );
+    assert_eq!(
+      obj.set_accessor_with_configuration(scope, key.into(), config),
+      Some(true)
+    );
+    let name = v8::String::new(scope, "obj").unwrap();
+    global.set(scope, name.into(), obj.into());
+
+    let result = eval(
+      scope,
+      "add(2); add(3); obj.value = obj.value * 6;
+       [add(0), greet('closure'), obj.value].join()",
+    )
+    .unwrap();
+    assert_eq!(result.to_rust_string_lossy(scope), "5,hello closure,42");
+    assert_eq!(counter.get(), 5);
+    assert_eq!(stored.get(), 42);
+
+    eval(scope, "delete globalThis.add; delete globalThis.obj").unwrap();
+  }
+
+  // `greet` and its template are still alive.
+  scope.request_garbage_collection_for_testing(v8::GarbageCollectionType::Full);
+  assert_eq!(dropped.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn function_builder_typed() {
+  fn repeat(
+    _scope: &mut v8::HandleScope,
+    text: String,
+    count: u32,
+  ) -> Result<String, v8::JsError> {
+    if count > 3 {
+      return Err(v8::JsError::range_error("count is too large"));
+    }
+    Ok(text.repeat(count as usize))
+  }
+
+  fn sum(_scope: &mut v8::HandleScope, values: Vec<f64>) -> f64 {
+    values.iter().sum()
+  }
+
+  fn entry(
+    _scope: &mut v8::HandleScope,
+    entry: (String, Option<i64>),
+  ) -> (Option<i64>, String, bool) {
+    (entry.1, entry.0, true)
+  }
+
+  #[allow(clippy::boxed_local)]
+  fn reverse(_scope: &mut v8::HandleScope, bytes: Box<[u8]>) -> Box<[u8]> {
+    bytes.iter().rev().copied().collect()
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let global = context.global(scope);
+
+  let function = v8::FunctionBuilder::<v8::Function>::new_typed(repeat)
+    .length(2)
+    .build(scope)
+    .unwrap();
+  let name = v8::String::new(scope, "repeat").unwrap();
+  global.set(scope, name.into(), function.into());
+  for (name, function) in [
+    ("sum", v8::Function::new_typed(scope, sum).unwrap()),
+    ("entry", v8::Function::new_typed(scope, entry).unwrap()),
+    ("reverse", v8::Function::new_typed(scope, reverse).unwrap()),
+  ] {
+    let name = v8::String::new(scope, name).unwrap();
+    global.set(scope, name.into(), function.into());
+  }
+
+  let result = eval(
+    scope,
+    "JSON.stringify([repeat('ab', 3), sum([1, 2, 3.5]), entry(['k', null]),
+       entry(['k', 2n ** 60n]), Array.from(reverse(new Uint8Array([1, 2, 3])))],
+       (_, v) => typeof v === 'bigint' ? `${v}n` : v)",
+  )
+  .unwrap();
+  assert_eq!(
+    result.to_rust_string_lossy(scope),
+    r#"["ababab",6.5,[null,"k",true],["1152921504606846976n","k",true],[3,2,1]]"#
+  );
+
+  for (source, expected) in [
+    ("repeat(1, 2)", "TypeError: argument 1: expected a string"),
+    (
+      "repeat('a', 1.5)",
+      "RangeError: argument 2: expected an integer between 0 and 4294967295",
+    ),
+    ("repeat('a', 4)", "RangeError: count is too large"),
+    (
+      "sum([1, 'x'])",
+      "TypeError: argument 1: element 1: expected a number",
+    ),
+    (
+      "entry(['k'])",
+      "TypeError: argument 1: expected an array of length 2",
+    ),
+    (
+      "reverse([1])",
+      "TypeError: argument 1: expected a Uint8Array",
+    ),
+  ] {
+    let scope = &mut v8::TryCatch::new(scope);
+    assert!(eval(scope, source).is_none());
+    let exception = scope.exception().unwrap();
+    assert_eq!(exception.to_rust_string_lossy(scope), expected);
+  }
+
+  let value = (vec![1u32, 2], Some("x".to_string())).to_v8(scope).unwrap();
+  let round_trip =
+    <(Vec<u32>, Option<String>) as v8::FromV8>::from_v8(scope, value).unwrap();
+  assert_eq!(round_trip, (vec![1, 2], Some("x".to_string())));
+  let value = u64::MAX.to_v8(scope).unwrap();
+  assert!(value.is_big_int());
+  assert_eq!(
+    <u64 as v8::FromV8>::from_v8(scope, value).unwrap(),
+    u64::MAX
+  );
+  let value = v8::Integer::new(scope, -1).into();
+  assert!(<u8 as v8::FromV8>::from_v8(scope, value).is_err());
+}
+
+#[cfg(feature = "macros")]
+#[test]
+fn class_macro() {
+  use std::cell::Cell;
+  use v8::Class;
+
+  static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+  struct Counter {
+    count: Cell<i32>,
+  }
+
+  impl Drop for Counter {
+    fn drop(&mut self) {
+      DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+  }
+
+  #[v8::class]
+  impl Counter {
+    #[constructor]
+    fn new(start: Option<i32>) -> Result<Self, v8::JsError> {
+      match start.unwrap_or(0) {
+        start if start < 0 => Err(v8::JsError::range_error("negative start")),
+        start => Ok(Self {
+          count: Cell::new(start),
+        }),
+      }
+    }
+
+    fn increment(&self, by: Option<i32>) -> i32 {
+      self.count.set(self.count.get() + by.unwrap_or(1));
+      self.count.get()
+    }
+
+    #[getter]
+    fn count(&self) -> i32 {
+      self.count.get()
+    }
+
+    #[setter]
+    fn set_count(&self, count: i32) {
+      self.count.set(count)
+    }
+
+    #[js_name = "toString"]
+    fn describe<'s>(
+      &self,
+      scope: &mut v8::HandleScope<'s>,
+    ) -> v8::Local<'s, v8::String> {
+      let text = format!("Counter({})", self.count.get());
+      v8::String::new(scope, &text).unwrap()
+    }
+
+    fn max_count() -> i32 {
+      i32::MAX
+    }
+
+    #[skip]
+    #[allow(dead_code)]
+    fn reset(&self) {
+      self.count.set(0)
+    }
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  DROPPED.store(0, Ordering::SeqCst);
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+    let global = context.global(scope);
+
+    assert_eq!(Counter::external_references().len(), 6);
+    let constructor = Counter::constructor(scope).unwrap();
+    let name = v8::String::new(scope, Counter::NAME).unwrap();
+    global.set(scope, name.into(), constructor.into());
+
+    let result = eval(
+      scope,
+      "var c = new Counter(5);
+       c.increment(); c.increment(3); c.count += 10;
+       [c.count, String(c), Counter.name, Counter.maxCount(), Counter.length,
+        Object.getOwnPropertyNames(Counter.prototype).sort().join(),
+        Object.keys(c).length, 'reset' in c].join(' ')",
+    )
+    .unwrap();
+    assert_eq!(
+      result.to_rust_string_lossy(scope),
+      "19 Counter(19) Counter 2147483647 1 constructor,count,increment,toString 0 false"
+    );
+
+    let c = eval(scope, "c").unwrap();
+    let c = v8::Local::<v8::Object>::try_from(c).unwrap();
+    assert_eq!(Counter::from_object(scope, c).unwrap().count.get(), 19);
+    assert!(Counter::from_object(scope, global).is_none());
+
+    let object = Counter {
+      count: Cell::new(7),
+    }
+    .into_object(scope)
+    .unwrap();
+    let name = v8::String::new(scope, "fromRust").unwrap();
+    global.set(scope, name.into(), object.into());
+    let result =
+      eval(scope, "fromRust instanceof Counter && fromRust.increment()")
+        .unwrap();
+    assert_eq!(result.int32_value(scope), Some(8));
+
+    for (source, expected) in [
+      (
+        "Counter(1)",
+        "TypeError: Class constructor Counter cannot be invoked without 'new'",
+      ),
+      ("new Counter(-1)", "RangeError: negative start"),
+      (
+        "new Counter('x')",
+        "TypeError: argument 1: expected a number",
+      ),
+      ("c.count = 1.5", "RangeError: argument 1: expected an integer between -2147483648 and 2147483647"),
+      ("Counter.prototype.increment.call(Object.create(Counter.prototype))", "TypeError: Illegal invocation"),
+      ("new c.increment()", "TypeError: c.increment is not a constructor"),
+    ] {
+      let scope = &mut v8::TryCatch::new(scope);
+      assert!(eval(scope, source).is_none());
+      let exception = scope.exception().unwrap();
+      assert_eq!(exception.to_rust_string_lossy(scope), expected);
+    }
+
+    eval(scope, "c = undefined; delete globalThis.fromRust").unwrap();
+  }
+  isolate
+    .request_garbage_collection_for_testing(v8::GarbageCollectionType::Full);
+  assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+}
+
 #[test]
 fn return_value() {
   let _setup_guard = setup::parallel_test();
@@ -5923,6 +6891,90 @@ fn typed_array_constructors() {
   }
 }
 
+#[test]
+fn typed_array_slices() {
+  let _setup_guard = setup::parallel_test();
+  let other_isolate = &mut v8::Isolate::new(Default::default());
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+
+  let value = eval(scope, "new Float64Array([1.5, 2.5, 3.5])").unwrap();
+  let array = v8::Local::<v8::Float64Array>::try_from(value).unwrap();
+  assert_eq!(array.as_slice(scope).unwrap(), [1.5, 2.5, 3.5]);
+  for element in array.as_mut_slice(scope).unwrap() {
+    *element *= 2.0;
+  }
+  let global = context.global(scope);
+  let name = v8::String::new(scope, "array").unwrap();
+  global.set(scope, name.into(), array.into());
+  let sum = eval(scope, "array.reduce((a, b) => a + b)").unwrap();
+  assert_eq!(sum.number_value(scope), Some(15.0));
+
+  // Views with an offset only cover their own elements.
+  let value = eval(scope, "new Int16Array(array.buffer, 8, 2)").unwrap();
+  let view = v8::Local::<v8::Int16Array>::try_from(value).unwrap();
+  assert_eq!(view.as_slice(scope).unwrap().len(), 2);
+
+  let buffer = array.buffer(scope).unwrap();
+  assert_eq!(buffer.as_slice(scope).unwrap().len(), 24);
+  buffer.detach(None);
+  assert_eq!(array.as_slice(scope).unwrap(), [0f64; 0]);
+  assert_eq!(buffer.as_mut_slice(scope).unwrap(), [0u8; 0]);
+
+  let value = eval(scope, "new Uint8Array(new SharedArrayBuffer(4))").unwrap();
+  let shared = v8::Local::<v8::Uint8Array>::try_from(value).unwrap();
+  assert!(shared.as_slice(scope).is_none());
+  let shared_buffer = shared.buffer(scope).unwrap();
+  assert!(shared_buffer.as_slice(scope).is_none());
+
+  // Buffers that share their memory with another buffer can't be borrowed.
+  let buffer = v8::ArrayBuffer::new(scope, 8);
+  assert!(buffer.as_mut_slice(scope).is_some());
+  let store = buffer.get_backing_store();
+  assert!(buffer.as_mut_slice(scope).is_none());
+  let other = v8::ArrayBuffer::with_backing_store(scope, &store);
+  drop(store);
+  assert!(buffer.as_mut_slice(scope).is_none());
+  assert!(other.as_slice(scope).is_none());
+
+  // Nor can buffers be borrowed with the wrong isolate.
+  let buffer = v8::ArrayBuffer::new(scope, 8);
+  assert!(buffer.as_mut_slice(other_isolate).is_none());
+  let array = v8::Uint8Array::new(scope, buffer, 0, 8).unwrap();
+  assert!(array.as_mut_slice(other_isolate).is_none());
+  assert!(array.as_mut_slice(scope).is_some());
+
+  // Stores created from Rust memory may not be aligned for the elements.
+  unsafe extern "C" fn drop_vec(
+    _data: *mut c_void,
+    _byte_length: usize,
+    deleter_data: *mut c_void,
+  ) {
+    drop(unsafe { Box::from_raw(deleter_data as *mut Vec<u8>) });
+  }
+  let mut bytes = Box::new(vec![0u8; 17]);
+  let data = unsafe { bytes.as_mut_ptr().add(1) };
+  assert!(!data.cast::<f64>().is_aligned());
+  let store = unsafe {
+    v8::ArrayBuffer::new_backing_store_from_ptr(
+      data as *mut c_void,
+      16,
+      drop_vec,
+      Box::into_raw(bytes) as *mut c_void,
+    )
+  }
+  .make_shared();
+  let buffer = v8::ArrayBuffer::with_backing_store(scope, &store);
+  drop(store);
+  let array = v8::Float64Array::new(scope, buffer, 0, 2).unwrap();
+  assert!(array.as_slice(scope).is_none());
+  assert!(array.as_mut_slice(scope).is_none());
+  let array = v8::Uint8Array::new(scope, buffer, 0, 16).unwrap();
+  assert_eq!(array.as_slice(scope).unwrap().len(), 16);
+}
+
 #[test]
 fn dynamic_import() {
   let _setup_guard = setup::parallel_test();
@@ -6996,13 +8048,443 @@ fn inspector_console_api_message() {
   let aux_data_view = StringView::from(&aux_data[..]);
   inspector.context_created(context, 1, name_view, aux_data_view);
 
-  let source = r#"
-    console.log("one");
-    console.error("two");
-    console.trace("three");
-  "#;
-  let _ = eval(scope, source).unwrap();
-  assert_eq!(client.messages, vec!["one", "two", "three"]);
+  let source = r#"
+    console.log("one");
+    console.error("two");
+    console.trace("three");
+  "#;
+  let _ = eval(scope, source).unwrap();
+  assert_eq!(client.messages, vec!["one", "two", "three"]);
+}
+
+#[cfg(feature = "inspector_server")]
+#[test]
+fn inspector_server() {
+  use std::io::BufRead;
+  use std::io::Read;
+  use std::io::Write;
+  use v8::inspector_server::*;
+
+  fn send(socket: &mut std::net::TcpStream, message: &str) {
+    // Client frames are masked, here with a zero key.
+    let mut frame = vec![0x81, 0x80 | 126];
+    frame.extend_from_slice(&(message.len() as u16).to_be_bytes());
+    frame.extend_from_slice(&[0; 4]);
+    frame.extend_from_slice(message.as_bytes());
+    socket.write_all(&frame).unwrap();
+  }
+
+  fn receive_until(reader: &mut impl Read, pattern: &str) -> String {
+    loop {
+      let mut head = [0; 2];
+      reader.read_exact(&mut head).unwrap();
+      let length = match head[1] {
+        126 => {
+          let mut length = [0; 2];
+          reader.read_exact(&mut length).unwrap();
+          u16::from_be_bytes(length) as usize
+        }
+        127 => {
+          let mut length = [0; 8];
+          reader.read_exact(&mut length).unwrap();
+          u64::from_be_bytes(length) as usize
+        }
+        length => length as usize,
+      };
+      let mut payload = vec![0; length];
+      reader.read_exact(&mut payload).unwrap();
+      let message = String::from_utf8(payload).unwrap();
+      if message.contains(pattern) {
+        return message;
+      }
+    }
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let mut server = InspectorServer::new(
+    isolate,
+    InspectorServerOptions {
+      port: 0,
+      title: "inspector_server".to_owned(),
+      ..Default::default()
+    },
+  )
+  .unwrap();
+  let address = server.local_addr();
+  let websocket_url = server.websocket_url();
+
+  let client = std::thread::spawn(move || {
+    let mut http = std::net::TcpStream::connect(address).unwrap();
+    write!(http, "GET /json/list HTTP/1.1\r\nHost: {address}\r\n\r\n").unwrap();
+    let mut list = String::new();
+    http.read_to_string(&mut list).unwrap();
+    assert!(list.starts_with("HTTP/1.1 200 OK"));
+    assert!(list.contains(r#""title":"inspector_server""#));
+    assert!(list.contains(&websocket_url));
+
+    let mut http = std::net::TcpStream::connect(address).unwrap();
+    write!(http, "GET /json/list HTTP/1.1\r\nHost: example.com\r\n\r\n")
+      .unwrap();
+    let mut forbidden = String::new();
+    http.read_to_string(&mut forbidden).unwrap();
+    assert!(forbidden.starts_with("HTTP/1.1 403 Forbidden"));
+
+    // Oversized headers close the connection without a response.
+    let mut http = std::net::TcpStream::connect(address).unwrap();
+    let header = "a".repeat(1 << 20);
+    let _ = write!(http, "GET /json/list HTTP/1.1\r\nX-Large: {header}\r\n");
+    let mut response = String::new();
+    let _ = http.read_to_string(&mut response);
+    assert!(response.is_empty());
+
+    let path = websocket_url
+      .strip_prefix(&format!("ws://{address}"))
+      .unwrap();
+    let mut socket = std::net::TcpStream::connect(address).unwrap();
+    write!(
+      socket,
+      "GET {path} HTTP/1.1\r\nHost: {address}\r\nUpgrade: websocket\r\n\
+       Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+       Sec-WebSocket-Version: 13\r\n\r\n"
+    )
+    .unwrap();
+    let mut reader = std::io::BufReader::new(socket.try_clone().unwrap());
+    let mut response = String::new();
+    while !response.ends_with("\r\n\r\n") {
+      reader.read_line(&mut response).unwrap();
+    }
+    assert!(response.starts_with("HTTP/1.1 101 Switching Protocols"));
+    assert!(response
+      .contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+    send(
+      &mut socket,
+      r#"{"id":1,"method":"Runtime.evaluate","params":{"expression":"1 + 2"}}"#,
+    );
+    let result = receive_until(&mut reader, r#""id":1"#);
+    assert!(result.contains(r#""value":3"#));
+    send(&mut socket, r#"{"id":2,"method":"Debugger.enable"}"#);
+    receive_until(&mut reader, r#""id":2"#);
+    send(
+      &mut socket,
+      r#"{"id":3,"method":"Runtime.runIfWaitingForDebugger"}"#,
+    );
+    receive_until(&mut reader, r#""method":"Debugger.paused""#);
+    send(&mut socket, r#"{"id":4,"method":"Debugger.resume"}"#);
+    receive_until(&mut reader, r#""id":4"#);
+  });
+
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+    server.context_created(context, "main");
+    if !server.wait_for_session_timeout(std::time::Duration::from_secs(60)) {
+      // Reports the panic of the client, if it failed.
+      client.join().unwrap();
+      panic!("no client connected");
+    }
+    assert!(server.has_sessions());
+
+    // Pauses until the client resumes.
+    let result = eval(scope, "debugger; 42").unwrap();
+    assert_eq!(result.int32_value(scope), Some(42));
+    client.join().unwrap();
+    server.context_destroyed(context);
+  }
+}
+
+#[cfg(feature = "inspector_protocol")]
+#[test]
+fn inspector_protocol_session() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+
+  use v8::inspector::*;
+  use v8::inspector_protocol::*;
+  let mut default_client = ClientCounter::new();
+  let mut inspector = V8Inspector::create(isolate, &mut default_client);
+
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let _scope = &mut v8::ContextScope::new(scope, context);
+
+  let name = b"";
+  let name_view = StringView::from(&name[..]);
+  let aux_data = b"{\"isDefault\": true}";
+  let aux_data_view = StringView::from(&aux_data[..]);
+  inspector.context_created(context, 1, name_view, aux_data_view);
+
+  let session = ProtocolSession::connect(&mut inspector, 1);
+  session.call(&runtime::Enable {}).unwrap();
+  let events = session.take_events();
+  assert!(events.iter().any(|event| matches!(
+    event,
+    Ok(Event::Runtime(runtime::Event::ExecutionContextCreated(_)))
+  )));
+
+  let response = session
+    .call(&runtime::Evaluate {
+      expression: "console.log('hi', 1); 1 + 2".into(),
+      ..Default::default()
+    })
+    .unwrap();
+  assert!(response.exception_details.is_none());
+  assert_eq!(response.result.r#type, "number");
+  assert_eq!(response.result.value, Some(3.into()));
+  match session.next_event() {
+    Some(Ok(Event::Runtime(runtime::Event::ConsoleAPICalled(call)))) => {
+      assert_eq!(call.r#type, "log");
+      assert_eq!(call.args.len(), 2);
+      assert_eq!(call.args[0].value, Some("hi".into()));
+    }
+    event => panic!("unexpected event {:?}", event),
+  }
+
+  let response = session
+    .call(&runtime::Evaluate {
+      expression: "throw new Error('boom')".into(),
+      ..Default::default()
+    })
+    .unwrap();
+  let details = response.exception_details.unwrap();
+  assert_eq!(details.exception.unwrap().subtype.as_deref(), Some("error"));
+
+  let id = session.send(&debugger::Enable::default());
+  assert!(session.take_response(id).unwrap().is_ok());
+  assert!(session.take_response(id).is_none());
+  assert!(matches!(
+    session.call(&debugger::GetScriptSource {
+      script_id: "nonexistent".into(),
+    }),
+    Err(ProtocolError::Response { .. })
+  ));
+
+  // Without the filter, the debugger would report the evaluated script.
+  session.take_events();
+  session.set_event_filter(|method| method == "Runtime.consoleAPICalled");
+  session
+    .call(&runtime::Evaluate {
+      expression: "console.log(1)".into(),
+      ..Default::default()
+    })
+    .unwrap();
+  let events = session.take_events();
+  assert_eq!(events.len(), 1);
+  assert_eq!(
+    events[0].as_ref().unwrap().method(),
+    "Runtime.consoleAPICalled"
+  );
+
+  let event = Event::parse(r#"{"method":"Debugger.resumed","params":{}}"#);
+  assert!(matches!(
+    event,
+    Ok(Event::Debugger(debugger::Event::Resumed))
+  ));
+  let event = Event::parse(r#"{"method":"Network.enable","params":{}}"#);
+  assert_eq!(event.unwrap().method(), "Network.enable");
+  // Known notifications that don't match the protocol aren't hidden.
+  let event = Event::parse(r#"{"method":"Debugger.paused","params":{}}"#);
+  assert!(matches!(event, Err(ProtocolError::Json(_))));
+
+  drop(session);
+  inspector.context_destroyed(context);
+}
+
+#[cfg(feature = "inspector_protocol")]
+#[test]
+fn inspector_debugger() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+
+  use std::cell::RefCell;
+  use std::rc::Rc;
+  use v8::inspector_debugger::*;
+  let mut debugger = Debugger::new(isolate).unwrap();
+
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  debugger.context_created(context, "");
+
+  let source = r#"
+    function add(a, b) {
+      const sum = a + b;
+      return sum * 10;
+    }
+    add(1, 2);
+  "#;
+  let breakpoint_id = debugger.set_breakpoint("debugger_test.js", 2).unwrap();
+
+  let pauses = Rc::new(RefCell::new(Vec::new()));
+  {
+    let pauses = pauses.clone();
+    debugger.on_paused(move |paused| {
+      let frame = &paused.call_frames()[0];
+      let line_number = frame.location.line_number;
+      assert_eq!(frame.function_name, "add");
+      assert_eq!(frame.url, "debugger_test.js");
+      let a = paused.variable(0, "a").unwrap().unwrap();
+      assert_eq!(a.value, Some(1.into()));
+      let scopes = paused.scopes(0).unwrap();
+      assert_eq!(scopes[0].r#type, "local");
+      // `sum` is only initialized on the second pause.
+      let sum = paused.variable(0, "sum").unwrap().and_then(|sum| sum.value);
+      let doubled = paused.evaluate(0, "b * 2").unwrap();
+      assert_eq!(doubled.result.value, Some(4.into()));
+      if paused.hit_breakpoints().is_empty() {
+        paused.resume();
+      } else {
+        paused.step_over();
+      }
+      pauses.borrow_mut().push((line_number, sum));
+    });
+  }
+
+  let code = v8::String::new(scope, source).unwrap();
+  let resource_name = v8::String::new(scope, "debugger_test.js").unwrap();
+  let origin = v8::ScriptOrigin::new(
+    scope,
+    resource_name.into(),
+    0,
+    0,
+    false,
+    0,
+    None,
+    false,
+    false,
+    false,
+    None,
+  );
+  let script = v8::Script::compile(scope, code, Some(&origin)).unwrap();
+  assert_eq!(script.run(scope).unwrap().int32_value(scope), Some(30));
+  let pauses_seen = pauses.borrow();
+  assert_eq!(pauses_seen.len(), 2);
+  assert_eq!(pauses_seen[0].0, 2);
+  assert_eq!(pauses_seen[1], (3, Some(3.into())));
+  drop(pauses_seen);
+
+  debugger.remove_breakpoint(&breakpoint_id).unwrap();
+  pauses.borrow_mut().clear();
+  let _ = eval(scope, "add(1, 2)").unwrap();
+  assert!(pauses.borrow().is_empty());
+
+  let exceptions = Rc::new(RefCell::new(Vec::new()));
+  {
+    let exceptions = exceptions.clone();
+    debugger.on_paused(move |paused| {
+      assert_eq!(paused.reason(), "exception");
+      let exception = paused.exception().unwrap();
+      exceptions.borrow_mut().push(exception.description.unwrap());
+    });
+  }
+  debugger
+    .set_pause_on_exceptions(PauseOnExceptions::Caught)
+    .unwrap();
+  let _ = eval(scope, "try { throw new Error('boom') } catch {}").unwrap();
+  assert_eq!(exceptions.borrow().len(), 1);
+  assert!(exceptions.borrow()[0].starts_with("Error: boom"));
+
+  debugger.context_destroyed(context);
+}
+
+#[cfg(feature = "inspector_protocol")]
+#[test]
+fn inspector_coverage() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+
+  use v8::inspector::*;
+  use v8::inspector_coverage::*;
+  let mut default_client = ClientCounter::new();
+  let mut inspector = V8Inspector::create(isolate, &mut default_client);
+
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+
+  let name = b"";
+  let name_view = StringView::from(&name[..]);
+  let aux_data = b"{\"isDefault\": true}";
+  let aux_data_view = StringView::from(&aux_data[..]);
+  inspector.context_created(context, 1, name_view, aux_data_view);
+  let collector = CoverageCollector::start(&mut inspector, 1).unwrap();
+
+  let source = "function used(x) {
+  if (x > 0) {
+    return 'positive';
+  } else {
+    return 'negative';
+  }
+}
+function unused() {
+  return 1;
+}
+used(1);
+used(2);
+";
+  let code = v8::String::new(scope, source).unwrap();
+  let resource_name = v8::String::new(scope, "coverage_test.js").unwrap();
+  let origin = v8::ScriptOrigin::new(
+    scope,
+    resource_name.into(),
+    0,
+    0,
+    false,
+    0,
+    None,
+    false,
+    false,
+    false,
+    None,
+  );
+  let script = v8::Script::compile(scope, code, Some(&origin)).unwrap();
+  script.run(scope).unwrap();
+  let _ = eval(scope, "used(-1)").unwrap();
+
+  let report = collector.collect().unwrap();
+  assert_eq!(report.scripts.len(), 1);
+  let script = &report.scripts[0];
+  assert_eq!(script.url, "coverage_test.js");
+  assert_eq!(script.source, source);
+  let line_counts = script.line_counts();
+  assert!(line_counts.contains(&(3, 2)));
+  assert!(line_counts.contains(&(5, 1)));
+  assert!(line_counts.contains(&(9, 0)));
+  assert!(line_counts.contains(&(11, 1)));
+
+  let lcov = report.to_lcov();
+  assert!(lcov.starts_with("TN:\nSF:coverage_test.js\n"));
+  assert!(lcov.contains("FN:1,used\n"));
+  assert!(lcov.contains("FNDA:3,used\n"));
+  assert!(lcov.contains("FNDA:0,unused\n"));
+  assert!(lcov.contains("FNF:2\nFNH:1\n"));
+  assert!(lcov.contains("DA:9,0\n"));
+  assert!(lcov.ends_with("end_of_record\n"));
+
+  let istanbul: serde_json::Value =
+    serde_json::from_str(&report.to_istanbul_json()).unwrap();
+  let file = &istanbul["coverage_test.js"];
+  assert_eq!(file["path"], "coverage_test.js");
+  assert_eq!(file["fnMap"]["0"]["name"], "used");
+  assert_eq!(file["f"]["0"], 3);
+  assert_eq!(file["f"]["1"], 0);
+
+  let path = std::env::temp_dir().join("rusty_v8_inspector_coverage.lcov");
+  report.write_lcov(&path).unwrap();
+  assert_eq!(std::fs::read_to_string(&path).unwrap(), lcov);
+  std::fs::remove_file(&path).unwrap();
+
+  // Counts are reset when they are collected.
+  let report = collector.collect().unwrap();
+  assert!(report.scripts[0]
+    .line_counts()
+    .iter()
+    .all(|(_, count)| *count == 0));
+
+  drop(collector);
+  inspector.context_destroyed(context);
 }
 
 #[test]
@@ -7950,6 +9432,90 @@ fn bigint() {
   assert_eq!(raw_b.to_words_array(&mut vec), (true, &mut [10, 10][..]));
 }
 
+#[test]
+fn bigint_conversions() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+
+  for value in [0, 1, -1, i64::MIN as i128, i128::MAX, i128::MIN] {
+    let b = v8::BigInt::new_from_i128(scope, value);
+    assert_eq!(b.i128_value(), (value, true));
+    assert_eq!(b.to_decimal_string(), value.to_string());
+    let raw_b = eval(scope, &format!("{}n", value)).unwrap();
+    assert!(b == raw_b);
+  }
+  let b = v8::BigInt::new_from_u128(scope, u128::MAX);
+  assert_eq!(b.u128_value(), (u128::MAX, true));
+  assert_eq!(b.i128_value(), (-1, false));
+  assert_eq!(b.to_decimal_string(), u128::MAX.to_string());
+
+  let b = v8::BigInt::new_from_i128(scope, -5);
+  assert_eq!(b.u128_value(), (5u128.wrapping_neg(), false));
+  let b = eval(scope, "2n ** 128n").unwrap();
+  let b = v8::Local::<v8::BigInt>::try_from(b).unwrap();
+  assert_eq!(b.u128_value(), (0, false));
+  assert_eq!(
+    b.to_decimal_string(),
+    "340282366920938463463374607431768211456"
+  );
+
+  let b =
+    v8::BigInt::new_from_bytes_be(scope, true, &[1, 0, 0, 0, 0, 0, 0, 0, 2])
+      .unwrap();
+  let raw_b = eval(scope, "-(2n ** 64n + 2n)").unwrap();
+  assert!(b == raw_b);
+  assert_eq!(b.to_bytes_be(), (true, vec![1, 0, 0, 0, 0, 0, 0, 0, 2]));
+  assert_eq!(b.to_bytes_le(), (true, vec![2, 0, 0, 0, 0, 0, 0, 0, 1]));
+  let b2 =
+    v8::BigInt::new_from_bytes_le(scope, true, &b.to_bytes_le().1).unwrap();
+  assert!(b == b2);
+  let zero = v8::BigInt::new_from_bytes_le(scope, false, &[]).unwrap();
+  assert_eq!(zero.to_bytes_le(), (false, vec![0]));
+
+  let digits = "-123456789012345678901234567890123456789012345678901234567890";
+  let b = v8::BigInt::new_from_decimal(scope, digits).unwrap();
+  let raw_b = eval(scope, &format!("{}n", digits)).unwrap();
+  assert!(b == raw_b);
+  assert_eq!(b.to_decimal_string(), digits);
+  let b =
+    v8::BigInt::new_from_decimal(scope, "+0000000000000000000042").unwrap();
+  assert_eq!(b.to_decimal_string(), "42");
+  assert!(v8::BigInt::new_from_decimal(scope, "").is_none());
+  assert!(v8::BigInt::new_from_decimal(scope, "-").is_none());
+  assert!(v8::BigInt::new_from_decimal(scope, "12a").is_none());
+  assert!(v8::BigInt::new_from_decimal(scope, "0x10").is_none());
+}
+
+#[cfg(feature = "num-bigint")]
+#[test]
+fn bigint_num_bigint() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+
+  for digits in [
+    "0",
+    "1",
+    "-1",
+    "18446744073709551615",
+    "-18446744073709551616",
+    "-123456789012345678901234567890123456789012345678901234567890",
+  ] {
+    let value = digits.parse::<num_bigint::BigInt>().unwrap();
+    let b = v8::BigInt::new_from_num_bigint(scope, &value).unwrap();
+    let raw_b = eval(scope, &format!("{}n", digits)).unwrap();
+    assert!(b == raw_b);
+    assert_eq!(b.to_num_bigint(), value);
+    let raw_b = v8::Local::<v8::BigInt>::try_from(raw_b).unwrap();
+    assert_eq!(raw_b.to_num_bigint().to_string(), digits);
+  }
+}
+
 // SerDes testing
 type ArrayBuffers = Vec<v8::SharedRef<v8::BackingStore>>;
 
@@ -8072,7 +9638,7 @@ fn value_serializer_and_deserializer() {
     value_serializer.write_double(55.44);
     value_serializer.write_uint32(22);
     buffer = value_serializer.release();
-    assert_eq!(value_serializer.release(), Vec::new());
+    assert_eq!(value_serializer.release(), Vec::<u8>::new());
   }
 
   let mut double: f64 = 0.0;
@@ -9001,6 +10567,52 @@ fn run_with_rust_allocator() {
   }
 }
 
+#[test]
+fn accounting_allocator() {
+  let _setup_guard = setup::parallel_test();
+  let quota = v8::ArrayBufferQuota::new(1024 * 1024);
+  let create_params = v8::CreateParams::default()
+    .array_buffer_allocator(v8::new_accounting_allocator(quota.clone()));
+  let isolate = &mut v8::Isolate::new(create_params);
+
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+
+    let result = eval(scope, "globalThis.kept = new ArrayBuffer(512 * 1024)");
+    assert!(result.is_some());
+    assert_eq!(quota.live_bytes(), 512 * 1024);
+
+    let result = eval(
+      scope,
+      r#"
+        try {
+          new ArrayBuffer(2 * 1024 * 1024);
+          "allocated";
+        } catch (e) {
+          e instanceof RangeError ? "RangeError" : String(e);
+        }
+      "#,
+    )
+    .unwrap();
+    assert_eq!(result.to_rust_string_lossy(scope), "RangeError");
+    assert!(quota.rejected_allocations() > 0);
+    assert_eq!(quota.peak_bytes(), 512 * 1024);
+
+    // Raising the limit lets the same allocation succeed.
+    quota.set_limit(4 * 1024 * 1024);
+    let result = eval(scope, "new ArrayBuffer(2 * 1024 * 1024).byteLength");
+    assert_eq!(result.unwrap().int32_value(scope), Some(2 * 1024 * 1024));
+    assert!(quota.peak_bytes() >= 2560 * 1024);
+
+    eval(scope, "globalThis.kept = undefined").unwrap();
+  }
+
+  isolate.low_memory_notification();
+  assert_eq!(quota.live_bytes(), 0);
+}
+
 // Same as heap_limits()
 #[cfg(not(all(target_os = "android", target_arch = "x86_64")))]
 #[test]
@@ -9533,6 +11145,84 @@ fn external_strings() {
   }
 }
 
+#[test]
+fn external_strings_owned() {
+  let _setup_guard = setup::parallel_test();
+  let text: std::sync::Arc<str> = "shared source text".into();
+  let utf16: std::sync::Arc<[u16]> =
+    "∇gradients".encode_utf16().collect::<Vec<_>>().into();
+  {
+    let isolate = &mut v8::Isolate::new(Default::default());
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+
+    let one_byte = v8::String::new_external_str(scope, text.clone()).unwrap();
+    assert!(one_byte.is_external_onebyte());
+    assert_eq!(one_byte.to_rust_string_lossy(scope), "shared source text");
+    assert_eq!(std::sync::Arc::strong_count(&text), 2);
+
+    let non_ascii = v8::String::new_external_str(scope, "∇🦕".into()).unwrap();
+    assert!(non_ascii.is_external_twobyte());
+    assert_eq!(non_ascii.to_rust_string_lossy(scope), "∇🦕");
+
+    let two_byte =
+      v8::String::new_external_twobyte_owned(scope, utf16.clone()).unwrap();
+    assert!(two_byte.is_external_twobyte());
+    assert_eq!(two_byte.to_rust_string_lossy(scope), "∇gradients");
+    assert_eq!(two_byte.length(), 10);
+
+    let latin1 =
+      v8::String::new_external_onebyte_owned(scope, vec![b'a', 0xA9]).unwrap();
+    assert!(latin1.is_external_onebyte());
+    assert_eq!(latin1.to_rust_string_lossy(scope), "a©");
+
+    let empty =
+      v8::String::new_external_twobyte_owned(scope, Box::<[u16]>::default())
+        .unwrap();
+    assert_eq!(empty.length(), 0);
+  }
+  // Disposing the isolate drops the buffers of its external strings.
+  assert_eq!(std::sync::Arc::strong_count(&text), 1);
+  assert_eq!(std::sync::Arc::strong_count(&utf16), 1);
+}
+
+#[test]
+fn cached_key() {
+  let _setup_guard = setup::parallel_test();
+  static THEN: v8::OneByteConst =
+    v8::String::create_external_onebyte_const(b"then");
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+
+  let id = v8::String::cached_key(scope, "id").unwrap();
+  assert_eq!(id.to_rust_string_lossy(scope), "id");
+  let id2 = {
+    let scope = &mut v8::HandleScope::new(scope);
+    let id2 = v8::String::cached_key(scope, "id").unwrap();
+    assert!(id.strict_equals(id2.into()));
+    v8::Global::new(scope, id2)
+  };
+  assert_eq!(id, id2);
+
+  let then = v8::String::cached_key(scope, &THEN).unwrap();
+  let then2 =
+    v8::String::cached_key(scope, v8::onebyte_const!("then")).unwrap();
+  assert_eq!(then, then2);
+  assert_eq!(then.to_rust_string_lossy(scope), "then");
+
+  let object = v8::Object::new(scope);
+  let value = v8::Integer::new(scope, 42);
+  object.set(scope, id.into(), value.into()).unwrap();
+  let key = v8::String::new(scope, "id").unwrap();
+  assert!(object
+    .get(scope, key.into())
+    .unwrap()
+    .strict_equals(value.into()));
+}
+
 #[test]
 fn counter_lookup_callback() {
   #[derive(Eq, PartialEq, Hash)]
@@ -9783,6 +11473,137 @@ fn backing_store_resizable() {
   assert!(store_resizable.is_resizable_by_user_javascript());
 }
 
+#[test]
+fn backing_store_resizable_from_rust() {
+  let _setup_guard = setup::parallel_test();
+
+  let store = v8::ArrayBuffer::new_resizable_backing_store(8, 64);
+  assert!(store.is_resizable_by_user_javascript());
+  assert!(!store.is_shared());
+  assert_eq!(store.byte_length(), 8);
+  assert_eq!(store.max_byte_length(), 64);
+  let store = store.make_shared();
+
+  let shared_store = v8::SharedArrayBuffer::new_growable_backing_store(8, 64);
+  assert!(shared_store.is_resizable_by_user_javascript());
+  assert!(shared_store.is_shared());
+  assert_eq!(shared_store.max_byte_length(), 64);
+  let shared_store = shared_store.make_shared();
+
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let global = context.global(scope);
+
+  let ab = v8::ArrayBuffer::with_backing_store(scope, &store);
+  assert_eq!(ab.byte_length(), 8);
+  assert_eq!(ab.max_byte_length(), 64);
+  let name = v8::String::new(scope, "ab").unwrap();
+  global.set(scope, name.into(), ab.into());
+  let view = eval(scope, "const view = new Uint8Array(ab); view").unwrap();
+  eval(scope, "ab.resize(32); view[31] = 42;").unwrap();
+  assert_eq!(ab.byte_length(), 32);
+  assert_eq!(store.byte_length(), 32);
+  let view = v8::Local::<v8::Uint8Array>::try_from(view).unwrap();
+  assert_eq!(view.length(), 32);
+  assert_eq!(store[31].get(), 42);
+  // `store` can reach the memory without the isolate.
+  assert!(view.as_slice(scope).is_none());
+
+  let sab = v8::SharedArrayBuffer::with_backing_store(scope, &shared_store);
+  assert_eq!(sab.max_byte_length(), 64);
+  let name = v8::String::new(scope, "sab").unwrap();
+  global.set(scope, name.into(), sab.into());
+  let growable = eval(scope, "sab.growable").unwrap();
+  assert!(growable.is_true());
+  eval(scope, "sab.grow(64)").unwrap();
+  assert_eq!(sab.byte_length(), 64);
+
+  let fixed = v8::ArrayBuffer::new(scope, 8);
+  assert_eq!(fixed.max_byte_length(), 8);
+}
+
+#[test]
+fn backing_store_from_file() {
+  let _setup_guard = setup::parallel_test();
+
+  let path = std::env::temp_dir()
+    .join(format!("rusty_v8_backing_store_{}", std::process::id()));
+  std::fs::write(&path, b"hello mapped file").unwrap();
+  let file = std::fs::File::open(&path).unwrap();
+
+  let read_only = unsafe {
+    v8::ArrayBuffer::new_backing_store_from_file(
+      &file,
+      v8::FileMapMode::ReadOnly,
+    )
+  }
+  .unwrap()
+  .make_shared();
+  let copy_on_write = unsafe {
+    v8::ArrayBuffer::new_backing_store_from_file(
+      &file,
+      v8::FileMapMode::CopyOnWrite,
+    )
+  }
+  .unwrap()
+  .make_shared();
+  drop(file);
+  assert_eq!(read_only.byte_length(), 17);
+  assert_eq!(copy_on_write.byte_length(), 17);
+
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let mut stats = v8::HeapStatistics::default();
+  isolate.get_heap_statistics(&mut stats);
+  let external_memory_before = stats.external_memory();
+  {
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope, Default::default());
+    let scope = &mut v8::ContextScope::new(scope, context);
+    let global = context.global(scope);
+
+    let ab = v8::ArrayBuffer::with_backing_store(scope, &read_only);
+    let name = v8::String::new(scope, "ro").unwrap();
+    global.set(scope, name.into(), ab.into());
+    let ab = v8::ArrayBuffer::with_backing_store(scope, &copy_on_write);
+    let name = v8::String::new(scope, "cow").unwrap();
+    global.set(scope, name.into(), ab.into());
+
+    let result = eval(
+      scope,
+      "const decode = (ab) => String.fromCharCode(...new Uint8Array(ab));
+       new Uint8Array(cow)[0] = 72;
+       decode(ro) + ' ' + decode(cow)",
+    )
+    .unwrap();
+    assert_eq!(
+      result.to_rust_string_lossy(scope),
+      "hello mapped file Hello mapped file"
+    );
+
+    scope.get_heap_statistics(&mut stats);
+    assert!(stats.external_memory() >= external_memory_before + 2 * 17);
+  }
+
+  // Writes through a copy-on-write mapping never reach the file.
+  assert_eq!(std::fs::read(&path).unwrap(), b"hello mapped file");
+  std::fs::remove_file(&path).unwrap();
+
+  let empty_path = path.with_extension("empty");
+  let empty_file = std::fs::File::create(&empty_path).unwrap();
+  let empty = unsafe {
+    v8::ArrayBuffer::new_backing_store_from_file(
+      &empty_file,
+      v8::FileMapMode::ReadOnly,
+    )
+  }
+  .unwrap();
+  assert_eq!(empty.byte_length(), 0);
+  drop(empty_file);
+  std::fs::remove_file(&empty_path).unwrap();
+}
+
 #[test]
 fn current_stack_trace() {
   // Setup isolate
@@ -10392,62 +12213,235 @@ fn host_create_shadow_realm_context_callback() {
     assert!(tc_scope.has_caught());
   }
 
-  struct CheckData {
-    callback_called: bool,
-    main_context: v8::Global<v8::Context>,
-  }
+  struct CheckData {
+    callback_called: bool,
+    main_context: v8::Global<v8::Context>,
+  }
+
+  let main_context = v8::Global::new(scope, context);
+  scope.set_slot(CheckData {
+    callback_called: false,
+    main_context,
+  });
+
+  scope.set_host_create_shadow_realm_context_callback(|scope| {
+    let main_context = {
+      let data = scope.get_slot_mut::<CheckData>().unwrap();
+      data.callback_called = true;
+      data.main_context.clone()
+    };
+    assert_eq!(scope.get_current_context(), main_context);
+
+    // Can't return None without throwing.
+    let message = v8::String::new(scope, "Unsupported").unwrap();
+    let exception = v8::Exception::type_error(scope, message);
+    scope.throw_exception(exception);
+    None
+  });
+
+  {
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    assert!(eval(tc_scope, "new ShadowRealm()").is_none());
+    assert!(tc_scope.has_caught());
+    assert!(tc_scope.get_slot::<CheckData>().unwrap().callback_called);
+  }
+
+  scope.set_host_create_shadow_realm_context_callback(|scope| {
+    let main_context = {
+      let data = scope.get_slot_mut::<CheckData>().unwrap();
+      data.callback_called = true;
+      data.main_context.clone()
+    };
+    assert_eq!(scope.get_current_context(), main_context);
+
+    let new_context = v8::Context::new(scope, Default::default());
+    {
+      let scope = &mut v8::ContextScope::new(scope, new_context);
+      let global = new_context.global(scope);
+      let key = v8::String::new(scope, "test").unwrap();
+      let value = v8::Integer::new(scope, 42);
+      global.set(scope, key.into(), value.into()).unwrap();
+    }
+    Some(new_context)
+  });
+
+  let value =
+    eval(scope, "new ShadowRealm().evaluate(`globalThis.test`)").unwrap();
+  assert_eq!(value.uint32_value(scope), Some(42));
+  assert!(scope.get_slot::<CheckData>().unwrap().callback_called);
+}
+
+#[test]
+fn console_delegate() {
+  let _setup_guard = setup::parallel_test();
+
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+
+  // Without a delegate, console methods do nothing.
+  eval(scope, "console.log('ignored')").unwrap();
+
+  struct ConsoleCall {
+    method: v8::ConsoleMethod,
+    args: Vec<String>,
+    caller: Option<String>,
+  }
+
+  scope.set_slot(Vec::<ConsoleCall>::new());
+  scope.set_console_delegate(|scope, message| {
+    assert_eq!(message.context, scope.get_current_context());
+    let args = message
+      .args
+      .iter()
+      .map(|arg| arg.to_rust_string_lossy(scope))
+      .collect();
+    let caller = message
+      .stack_trace(scope)
+      .and_then(|stack_trace| stack_trace.get_frame(scope, 0))
+      .and_then(|frame| frame.get_function_name(scope))
+      .map(|name| name.to_rust_string_lossy(scope));
+    if message.method == v8::ConsoleMethod::Log {
+      assert!(message.args[1].is_number());
+      assert!(message.args[2].is_object());
+    }
+    scope
+      .get_slot_mut::<Vec<ConsoleCall>>()
+      .unwrap()
+      .push(ConsoleCall {
+        method: message.method,
+        args,
+        caller,
+      });
+  });
+
+  let source = r#"
+    function logAll() {
+      console.log("one", 2, { three: 3 });
+      console.warn("four");
+      console.groupEnd();
+    }
+    logAll();
+    console.assert(false, "five");
+  "#;
+  eval(scope, source).unwrap();
+  {
+    let calls = scope.get_slot::<Vec<ConsoleCall>>().unwrap();
+    assert_eq!(calls.len(), 4);
+    assert_eq!(calls[0].method, v8::ConsoleMethod::Log);
+    assert_eq!(calls[0].args, vec!["one", "2", "[object Object]"]);
+    assert_eq!(calls[0].caller.as_deref(), Some("logAll"));
+    assert_eq!(calls[1].method.name(), "warn");
+    assert_eq!(calls[1].args, vec!["four"]);
+    assert_eq!(calls[2].method, v8::ConsoleMethod::GroupEnd);
+    assert!(calls[2].args.is_empty());
+    assert_eq!(calls[3].method, v8::ConsoleMethod::Assert);
+    assert_eq!(calls[3].args, vec!["false", "five"]);
+  }
+
+  scope.remove_console_delegate();
+  eval(scope, "console.log('ignored')").unwrap();
+  assert_eq!(scope.get_slot::<Vec<ConsoleCall>>().unwrap().len(), 4);
+}
+
+#[test]
+fn console_delegate_inspector() {
+  use v8::inspector::*;
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+
+  struct Client {
+    base: V8InspectorClientBase,
+    messages: usize,
+  }
+
+  impl V8InspectorClientImpl for Client {
+    fn base(&self) -> &V8InspectorClientBase {
+      &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut V8InspectorClientBase {
+      &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const V8InspectorClientBase {
+      unsafe { addr_of!((*this).base) }
+    }
+
+    fn console_api_message(
+      &mut self,
+      _context_group_id: i32,
+      _level: i32,
+      _message: &StringView,
+      _url: &StringView,
+      _line_number: u32,
+      _column_number: u32,
+      _stack_trace: &mut V8StackTrace,
+    ) {
+      self.messages += 1;
+    }
+  }
+
+  struct DelegateCalls(usize);
 
-  let main_context = v8::Global::new(scope, context);
-  scope.set_slot(CheckData {
-    callback_called: false,
-    main_context,
+  isolate.set_slot(DelegateCalls(0));
+  isolate.set_console_delegate(|scope, _message| {
+    scope.get_slot_mut::<DelegateCalls>().unwrap().0 += 1;
   });
+  assert!(isolate.has_console_delegate());
 
-  scope.set_host_create_shadow_realm_context_callback(|scope| {
-    let main_context = {
-      let data = scope.get_slot_mut::<CheckData>().unwrap();
-      data.callback_called = true;
-      data.main_context.clone()
-    };
-    assert_eq!(scope.get_current_context(), main_context);
+  // Creating an inspector replaces the delegate.
+  let mut client = Client {
+    base: V8InspectorClientBase::new::<Client>(),
+    messages: 0,
+  };
+  let mut inspector = V8Inspector::create(isolate, &mut client);
+  assert!(!isolate.has_console_delegate());
 
-    // Can't return None without throwing.
-    let message = v8::String::new(scope, "Unsupported").unwrap();
-    let exception = v8::Exception::type_error(scope, message);
-    scope.throw_exception(exception);
-    None
-  });
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let name = b"";
+  let aux_data = b"{\"isDefault\": true}";
+  inspector.context_created(
+    context,
+    1,
+    StringView::from(&name[..]),
+    StringView::from(&aux_data[..]),
+  );
 
-  {
-    let tc_scope = &mut v8::TryCatch::new(scope);
-    assert!(eval(tc_scope, "new ShadowRealm()").is_none());
-    assert!(tc_scope.has_caught());
-    assert!(tc_scope.get_slot::<CheckData>().unwrap().callback_called);
-  }
+  eval(scope, "console.log(1)").unwrap();
+  assert_eq!(client.messages, 1);
+  assert_eq!(scope.get_slot::<DelegateCalls>().unwrap().0, 0);
 
-  scope.set_host_create_shadow_realm_context_callback(|scope| {
-    let main_context = {
-      let data = scope.get_slot_mut::<CheckData>().unwrap();
-      data.callback_called = true;
-      data.main_context.clone()
-    };
-    assert_eq!(scope.get_current_context(), main_context);
+  // Removing the callback leaves the inspector's delegate in place.
+  scope.remove_console_delegate();
+  eval(scope, "console.log(2)").unwrap();
+  assert_eq!(client.messages, 2);
 
-    let new_context = v8::Context::new(scope, Default::default());
-    {
-      let scope = &mut v8::ContextScope::new(scope, new_context);
-      let global = new_context.global(scope);
-      let key = v8::String::new(scope, "test").unwrap();
-      let value = v8::Integer::new(scope, 42);
-      global.set(scope, key.into(), value.into()).unwrap();
-    }
-    Some(new_context)
+  scope.set_console_delegate(|scope, _message| {
+    scope.get_slot_mut::<DelegateCalls>().unwrap().0 += 1;
   });
+  assert!(scope.has_console_delegate());
+  eval(scope, "console.log(3)").unwrap();
+  assert_eq!(client.messages, 2);
+  assert_eq!(scope.get_slot::<DelegateCalls>().unwrap().0, 1);
 
-  let value =
-    eval(scope, "new ShadowRealm().evaluate(`globalThis.test`)").unwrap();
-  assert_eq!(value.uint32_value(scope), Some(42));
-  assert!(scope.get_slot::<CheckData>().unwrap().callback_called);
+  // Dropping the inspector removes the delegate.
+  inspector.context_destroyed(context);
+  drop(inspector);
+  assert!(!scope.has_console_delegate());
+  eval(scope, "console.log(4)").unwrap();
+  assert_eq!(scope.get_slot::<DelegateCalls>().unwrap().0, 1);
+
+  scope.set_console_delegate(|scope, _message| {
+    scope.get_slot_mut::<DelegateCalls>().unwrap().0 += 1;
+  });
+  assert!(scope.has_console_delegate());
+  eval(scope, "console.log(5)").unwrap();
+  assert_eq!(scope.get_slot::<DelegateCalls>().unwrap().0, 2);
 }
 
 #[test]
@@ -10519,6 +12513,94 @@ fn test_fast_calls() {
   assert_eq!("fast", unsafe { WHO });
 }
 
+#[test]
+fn test_fast_calls_derived() {
+  static mut WHO: &str = "none";
+  fn fast_add(
+    _recv: v8::Local<v8::Object>,
+    a: u32,
+    b: u32,
+    options: &mut fast_api::FastApiCallbackOptions,
+  ) -> u32 {
+    assert!(options.data.is_undefined());
+    unsafe { WHO = "fast" };
+    a + b
+  }
+
+  fn slow_add(_scope: &mut v8::HandleScope, a: u32, b: u32) -> u32 {
+    unsafe { WHO = "slow" };
+    a + b
+  }
+
+  fn fast_sum(
+    _recv: v8::Local<v8::Object>,
+    prefix: &fast_api::FastApiOneByteString,
+    values: &fast_api::FastApiTypedArray<u32>,
+  ) -> u32 {
+    unsafe { WHO = "fast" };
+    let sum = (0..values.length()).map(|i| values.get(i)).sum::<u32>();
+    prefix.as_bytes().len() as u32 + sum
+  }
+
+  #[allow(clippy::boxed_local)]
+  fn slow_sum(
+    _scope: &mut v8::HandleScope,
+    prefix: String,
+    values: Box<[u32]>,
+  ) -> u32 {
+    unsafe { WHO = "slow" };
+    prefix.len() as u32 + values.iter().sum::<u32>()
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let global = context.global(scope);
+
+  let add = fast_api::CFunction::from_fn(fast_add);
+  assert_eq!(
+    add.address(),
+    fast_api::CFunction::from_fn(fast_add).address()
+  );
+  for (name, template) in [
+    (
+      "add",
+      v8::FunctionTemplate::new_fast(scope, slow_add, fast_add),
+    ),
+    (
+      "sum",
+      v8::FunctionTemplate::new_fast(scope, slow_sum, fast_sum),
+    ),
+  ] {
+    let name = v8::String::new(scope, name).unwrap();
+    let value = template.get_function(scope).unwrap();
+    global.set(scope, name.into(), value.into()).unwrap();
+  }
+
+  let source = r#"
+    function f(x, y) { return add(x, y); }
+    function g(s, a) { return sum(s, a); }
+    %PrepareFunctionForOptimization(f);
+    %PrepareFunctionForOptimization(g);
+    if (42 !== f(19, 23)) throw "unexpected";
+    if (10 !== g("abc", new Uint32Array([3, 4]))) throw "unexpected";
+  "#;
+  eval(scope, source).unwrap();
+  assert_eq!("slow", unsafe { WHO });
+
+  for source in [
+    "%OptimizeFunctionOnNextCall(f); if (42 !== f(19, 23)) throw 'unexpected';",
+    "%OptimizeFunctionOnNextCall(g);
+     if (10 !== g('abc', new Uint32Array([3, 4]))) throw 'unexpected';",
+  ] {
+    unsafe { WHO = "none" };
+    eval(scope, source).unwrap();
+    assert_eq!("fast", unsafe { WHO });
+  }
+}
+
 #[test]
 fn test_fast_calls_empty_buffer() {
   static mut WHO: &str = "none";
@@ -10986,6 +13068,167 @@ fn test_fast_calls_overload() {
   assert_eq!("fast_array", unsafe { WHO });
 }
 
+#[test]
+fn test_fast_calls_overloads_derived() {
+  static mut WHO: &str = "none";
+  fn fast_one(_recv: v8::Local<v8::Object>, a: u32) -> u32 {
+    unsafe { WHO = "fast_one" };
+    a
+  }
+
+  fn fast_two(_recv: v8::Local<v8::Object>, a: u32, b: u32) -> u32 {
+    unsafe { WHO = "fast_two" };
+    a + b
+  }
+
+  fn fast_sequence(
+    _recv: v8::Local<v8::Object>,
+    values: fast_api::FastApiSequence<u32>,
+  ) -> u32 {
+    unsafe { WHO = "fast_sequence" };
+    values.to_vec().unwrap().iter().sum()
+  }
+
+  fn fast_typed_array(
+    _recv: v8::Local<v8::Object>,
+    values: &fast_api::FastApiTypedArray<u32>,
+  ) -> u32 {
+    unsafe { WHO = "fast_typed_array" };
+    (0..values.length()).map(|i| values.get(i)).sum()
+  }
+
+  fn fast_byte_length(
+    _recv: v8::Local<v8::Object>,
+    buffer: fast_api::FastApiArrayBuffer,
+  ) -> u32 {
+    unsafe { WHO = "fast_byte_length" };
+    buffer.get().map_or(0, |buffer| buffer.byte_length() as u32)
+  }
+
+  fn slow_fn(
+    _: &mut v8::HandleScope,
+    _: v8::FunctionCallbackArguments,
+    _: v8::ReturnValue<v8::Value>,
+  ) {
+    unsafe { WHO = "slow" };
+  }
+
+  let one = fast_api::CFunction::from_fn(fast_one);
+  let two = fast_api::CFunction::from_fn(fast_two);
+  let sequence = fast_api::CFunction::from_fn(fast_sequence);
+  let typed_array = fast_api::CFunction::from_fn(fast_typed_array);
+  assert_eq!(one.arity(), 1);
+  assert_eq!(two.arity(), 2);
+  let no_arguments = fast_api::CFunctionInfo::new(
+    fast_api::Type::Void.scalar(),
+    &[],
+    fast_api::Int64Representation::Number,
+  );
+  let no_arguments = fast_api::CFunction::new(std::ptr::null(), &no_arguments);
+  assert_eq!(no_arguments.arity(), 0);
+  assert_eq!(
+    sequence.type_info().argument_info(1).sequence_type(),
+    fast_api::SequenceType::IsSequence
+  );
+  assert!(fast_api::check_overloads(&[one, two]).is_ok());
+  assert!(fast_api::check_overloads(&[sequence, typed_array]).is_ok());
+  assert!(fast_api::check_overloads(&[one, sequence]).is_err());
+  assert!(fast_api::check_overloads(&[sequence, typed_array, one]).is_err());
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let global = context.global(scope);
+
+  let byte_length = fast_api::CFunction::from_fn(fast_byte_length);
+  for (name, overloads) in [
+    ("add", &[one, two][..]),
+    ("sum", &[sequence, typed_array][..]),
+    ("byteLength", &[byte_length][..]),
+  ] {
+    let template =
+      v8::FunctionTemplate::builder(slow_fn).build_fast(scope, overloads);
+    let name = v8::String::new(scope, name).unwrap();
+    let value = template.get_function(scope).unwrap();
+    global.set(scope, name.into(), value.into()).unwrap();
+  }
+
+  let source = r#"
+    function g(x) { return add(x); }
+    function h(x, y) { return add(x, y); }
+    function s(values) { return sum(values); }
+    function b(buffer) { return byteLength(buffer); }
+    %PrepareFunctionForOptimization(g);
+    %PrepareFunctionForOptimization(h);
+    %PrepareFunctionForOptimization(s);
+    %PrepareFunctionForOptimization(b);
+    g(1);
+    h(1, 2);
+    s([1, 2]);
+    s(new Uint32Array([1, 2]));
+    b(new ArrayBuffer(8));
+  "#;
+  eval(scope, source).unwrap();
+  assert_eq!("slow", unsafe { WHO });
+
+  for (source, who, result) in [
+    ("%OptimizeFunctionOnNextCall(g); g(1)", "fast_one", 1),
+    ("%OptimizeFunctionOnNextCall(h); h(1, 2)", "fast_two", 3),
+    (
+      "%OptimizeFunctionOnNextCall(s); s([1, 2])",
+      "fast_sequence",
+      3,
+    ),
+    (
+      "%OptimizeFunctionOnNextCall(s); s(new Uint32Array([1, 2]))",
+      "fast_typed_array",
+      3,
+    ),
+    (
+      "%OptimizeFunctionOnNextCall(b); b(new ArrayBuffer(8))",
+      "fast_byte_length",
+      8,
+    ),
+  ] {
+    unsafe { WHO = "none" };
+    let value = eval(scope, source).unwrap();
+    assert_eq!(who, unsafe { WHO });
+    assert_eq!(value.uint32_value(scope), Some(result));
+  }
+}
+
+#[test]
+#[should_panic(expected = "invalid fast function overloads")]
+fn test_fast_calls_ambiguous_overloads() {
+  fn fast_a(_recv: v8::Local<v8::Object>, a: u32) -> u32 {
+    a
+  }
+
+  fn fast_b(_recv: v8::Local<v8::Object>, b: u32) -> u32 {
+    b
+  }
+
+  fn slow_fn(
+    _: &mut v8::HandleScope,
+    _: v8::FunctionCallbackArguments,
+    _: v8::ReturnValue<v8::Value>,
+  ) {
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  v8::FunctionTemplate::builder(slow_fn).build_fast(
+    scope,
+    &[
+      fast_api::CFunction::from_fn(fast_a),
+      fast_api::CFunction::from_fn(fast_b),
+    ],
+  );
+}
+
 #[test]
 fn test_fast_calls_callback_options_data() {
   static mut DATA: bool = false;
@@ -11055,6 +13298,104 @@ fn test_fast_calls_callback_options_data() {
   assert!(unsafe { DATA });
 }
 
+#[test]
+fn test_fast_calls_throw() {
+  static mut WHO: &str = "none";
+  fn fast_div(
+    _recv: v8::Local<v8::Object>,
+    a: u32,
+    b: u32,
+    options: &mut fast_api::FastApiCallbackOptions,
+  ) -> Result<u32, v8::JsError> {
+    assert!(options.data().is_undefined());
+    assert!(!options.isolate().is_execution_terminating());
+    unsafe { WHO = "fast" };
+    a.checked_div(b)
+      .ok_or_else(|| v8::JsError::range_error("division by zero"))
+  }
+
+  fn slow_div(
+    _scope: &mut v8::HandleScope,
+    a: u32,
+    b: u32,
+  ) -> Result<u32, v8::JsError> {
+    unsafe { WHO = "slow" };
+    a.checked_div(b)
+      .ok_or_else(|| v8::JsError::range_error("division by zero"))
+  }
+
+  fn fast_check(
+    _recv: v8::Local<v8::Object>,
+    value: u32,
+    options: &mut fast_api::FastApiCallbackOptions,
+  ) -> u32 {
+    unsafe { WHO = "fast" };
+    if value > 100 {
+      options.throw(v8::JsError::type_error("value too large"));
+    }
+    value
+  }
+
+  fn slow_check(_scope: &mut v8::HandleScope, value: u32) -> u32 {
+    unsafe { WHO = "slow" };
+    value
+  }
+
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+  let global = context.global(scope);
+
+  for (name, template) in [
+    (
+      "div",
+      v8::FunctionTemplate::new_fast(scope, slow_div, fast_div),
+    ),
+    (
+      "check",
+      v8::FunctionTemplate::new_fast(scope, slow_check, fast_check),
+    ),
+  ] {
+    let name = v8::String::new(scope, name).unwrap();
+    let value = template.get_function(scope).unwrap();
+    global.set(scope, name.into(), value.into()).unwrap();
+  }
+
+  let source = r#"
+    function f(x, y) { return div(x, y); }
+    function g(x) { return check(x); }
+    %PrepareFunctionForOptimization(f);
+    %PrepareFunctionForOptimization(g);
+    if (2 !== f(6, 3)) throw "unexpected";
+    if (7 !== g(7)) throw "unexpected";
+  "#;
+  eval(scope, source).unwrap();
+  assert_eq!("slow", unsafe { WHO });
+
+  let source = r#"
+    %OptimizeFunctionOnNextCall(f);
+    %OptimizeFunctionOnNextCall(g);
+    if (2 !== f(6, 3)) throw "unexpected";
+    if (7 !== g(7)) throw "unexpected";
+  "#;
+  unsafe { WHO = "none" };
+  eval(scope, source).unwrap();
+  assert_eq!("fast", unsafe { WHO });
+
+  for (source, expected) in [
+    ("f(1, 0)", "RangeError: division by zero"),
+    ("g(101)", "TypeError: value too large"),
+  ] {
+    unsafe { WHO = "none" };
+    let source = format!("try {{ {source}; }} catch (e) {{ String(e) }}");
+    let result = eval(scope, &source).unwrap();
+    assert_eq!("fast", unsafe { WHO });
+    assert_eq!(result.to_rust_string_lossy(scope), expected);
+  }
+}
+
 #[test]
 fn test_detach_key() {
   let _setup_guard = setup::parallel_test();
@@ -11896,6 +14237,51 @@ fn string_valueview() {
   }
 }
 
+#[test]
+fn string_view() {
+  let _setup_guard = setup::parallel_test();
+  let isolate = &mut v8::Isolate::new(Default::default());
+  let scope = &mut v8::HandleScope::new(isolate);
+  let context = v8::Context::new(scope, Default::default());
+  let scope = &mut v8::ContextScope::new(scope, context);
+
+  let ascii = v8::String::new(scope, "content-type").unwrap();
+  let view = v8::StringView::new(scope, ascii);
+  assert_eq!(view.as_str(), Some("content-type"));
+  assert_eq!(view.as_utf16(), None);
+  assert!(matches!(
+    view.to_str(),
+    std::borrow::Cow::Borrowed("content-type")
+  ));
+  drop(view);
+
+  let latin1 = v8::String::new_from_one_byte(
+    scope,
+    &[b'a', 0xA9],
+    v8::NewStringType::Normal,
+  )
+  .unwrap();
+  let view = v8::StringView::new(scope, latin1);
+  assert_eq!(view.as_str(), None);
+  assert!(matches!(view.to_str(), std::borrow::Cow::Owned(s) if s == "a©"));
+  assert_eq!(&*view.to_utf16(), [0x61, 0xA9]);
+  drop(view);
+
+  let two_byte = v8::String::new(scope, "∇🦕").unwrap();
+  let view = v8::StringView::new(scope, two_byte);
+  assert_eq!(view.as_str(), None);
+  assert_eq!(view.as_utf16(), Some(&[0x2207, 0xD83E, 0xDD95][..]));
+  assert!(matches!(view.to_utf16(), std::borrow::Cow::Borrowed(_)));
+  assert_eq!(view.to_str(), "∇🦕");
+  drop(view);
+
+  // Cons strings are flattened when the view is created.
+  let cons = eval(scope, "'x-request-' + 'id'.repeat(2)").unwrap();
+  let cons = v8::Local::<v8::String>::try_from(cons).unwrap();
+  let view = v8::StringView::new(scope, cons);
+  assert_eq!(view.as_str(), Some("x-request-idid"));
+}
+
 #[test]
 fn host_defined_options() {
   let _setup_guard = setup::parallel_test();
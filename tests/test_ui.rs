@@ -8,4 +8,6 @@ fn ui() {
 
   let t = trybuild::TestCases::new();
   t.compile_fail("tests/compile_fail/*.rs");
+  #[cfg(feature = "macros")]
+  t.compile_fail("tests/compile_fail/macros/*.rs");
 }
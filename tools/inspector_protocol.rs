@@ -0,0 +1,473 @@
+// Copyright 2019-2021 the Deno authors. All rights reserved. MIT license.
+
+//! Generates the domain modules of `v8::inspector_protocol` from V8's
+//! protocol definition, `v8/include/js_protocol.json`. It is included by
+//! `build.rs` when the `inspector_protocol` feature is enabled.
+//!
+//! Every domain becomes a module with a struct per object type, command,
+//! command response and notification, which `src/inspector_protocol.rs`
+//! ties together with its `commands!`, `events!` and `domains!` macros.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Generates `$OUT_DIR/inspector_protocol.rs` from the protocol definition
+/// at `path`.
+pub fn generate(path: &Path) {
+  println!("cargo:rerun-if-changed={}", path.display());
+  let protocol = fs::read_to_string(path).unwrap_or_else(|error| {
+    panic!(
+      "can't read the protocol definition {}: {}",
+      path.display(),
+      error
+    )
+  });
+  let protocol: Value =
+    serde_json::from_str(&protocol).expect("invalid protocol definition");
+  let out_path =
+    Path::new(&env::var_os("OUT_DIR").unwrap()).join("inspector_protocol.rs");
+  fs::write(out_path, Generator::new(&protocol).generate()).unwrap();
+}
+
+/// Rust keywords, which protocol members are named after as raw
+/// identifiers.
+const KEYWORDS: &[&str] = &[
+  "abstract", "as", "async", "await", "become", "box", "break", "const",
+  "continue", "do", "dyn", "else", "enum", "extern", "false", "final", "fn",
+  "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+  "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+  "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized",
+  "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can't be raw identifiers.
+const RESERVED: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Names that the macros of `src/inspector_protocol.rs` define in every
+/// domain module.
+const MACRO_NAMES: &[&str] = &["Event"];
+
+struct Generator<'a> {
+  domains: Vec<&'a Value>,
+  /// The object types of every domain, by domain and name.
+  types: HashMap<(&'a str, &'a str), &'a Value>,
+  out: String,
+}
+
+impl<'a> Generator<'a> {
+  fn new(protocol: &'a Value) -> Self {
+    let domains = array(protocol, "domains").iter().collect::<Vec<_>>();
+    let mut types = HashMap::new();
+    for domain in &domains {
+      for ty in array(domain, "types") {
+        types.insert((string(domain, "domain"), string(ty, "id")), ty);
+      }
+    }
+    Self {
+      domains,
+      types,
+      out: String::new(),
+    }
+  }
+
+  fn generate(mut self) -> String {
+    let mut domains_with_events = vec![];
+    for domain in self.domains.clone() {
+      self.write_domain(domain);
+      if !array(domain, "events").is_empty() {
+        domains_with_events.push(string(domain, "domain"));
+      }
+    }
+    self.out.push_str("domains! {\n");
+    for name in domains_with_events {
+      writeln!(self.out, "  {} {},", name, snake_case(name)).unwrap();
+    }
+    self.out.push_str("}\n");
+    self.out
+  }
+
+  fn write_domain(&mut self, domain: &'a Value) {
+    let name = string(domain, "domain");
+    check_names(domain);
+
+    let mut notes = vec![];
+    if domain.get("description").is_none() {
+      notes.push(format!("The `{}` domain.", name));
+    }
+    write_doc(&mut self.out, "", domain, &notes);
+    writeln!(self.out, "pub mod {} {{", snake_case(name)).unwrap();
+
+    for ty in array(domain, "types") {
+      let id = string(ty, "id");
+      if ty.get("properties").is_some() {
+        self.write_struct(name, id, ty, array(ty, "properties"), true);
+      } else {
+        write_doc(&mut self.out, "  ", ty, &enum_notes(ty));
+        let rust_type = self.rust_type(name, ty);
+        writeln!(self.out, "  pub type {} = {};\n", id, rust_type).unwrap();
+      }
+    }
+
+    let commands = array(domain, "commands");
+    for command in commands {
+      let command_name = upper_camel_case(string(command, "name"));
+      self.write_struct(
+        name,
+        &command_name,
+        command,
+        array(command, "parameters"),
+        false,
+      );
+      if !array(command, "returns").is_empty() {
+        let mut response = Value::Object(Default::default());
+        response["description"] =
+          format!("The response to `{}`.", command_name).into();
+        let response_name = format!("{}Response", command_name);
+        self.write_struct(
+          name,
+          &response_name,
+          &response,
+          array(command, "returns"),
+          false,
+        );
+      }
+    }
+    if !commands.is_empty() {
+      self.out.push_str("  commands! {\n");
+      for command in commands {
+        let command_name = upper_camel_case(string(command, "name"));
+        let response = if array(command, "returns").is_empty() {
+          "super::Empty".to_string()
+        } else {
+          format!("{}Response", command_name)
+        };
+        writeln!(
+          self.out,
+          "    {} => \"{}.{}\" -> {},",
+          command_name,
+          name,
+          string(command, "name"),
+          response
+        )
+        .unwrap();
+      }
+      self.out.push_str("  }\n\n");
+    }
+
+    let events = array(domain, "events");
+    for event in events {
+      if !array(event, "parameters").is_empty() {
+        let event_name = upper_camel_case(string(event, "name"));
+        self.write_struct(
+          name,
+          &event_name,
+          event,
+          array(event, "parameters"),
+          false,
+        );
+      }
+    }
+    if !events.is_empty() {
+      self.out.push_str("  events! {\n");
+      for event in events {
+        let event_name = upper_camel_case(string(event, "name"));
+        let params = if array(event, "parameters").is_empty() {
+          String::new()
+        } else {
+          format!("({})", event_name)
+        };
+        writeln!(
+          self.out,
+          "    {}{} => \"{}.{}\",",
+          event_name,
+          params,
+          name,
+          string(event, "name")
+        )
+        .unwrap();
+      }
+      self.out.push_str("  }\n");
+    }
+
+    self.out.push_str("}\n\n");
+  }
+
+  /// Writes a struct whose fields are `properties`. Fields of object types
+  /// that contain the struct are boxed if it is an object type itself.
+  fn write_struct(
+    &mut self,
+    domain: &'a str,
+    name: &str,
+    item: &Value,
+    properties: &'a [Value],
+    is_type: bool,
+  ) {
+    write_doc(&mut self.out, "  ", item, &[]);
+    self.out.push_str(
+      "  #[derive(Clone, Debug, Default, PartialEq, \
+       ::serde::Deserialize, ::serde::Serialize)]\n",
+    );
+    self
+      .out
+      .push_str("  #[serde(rename_all = \"camelCase\")]\n");
+    writeln!(self.out, "  pub struct {} {{", name).unwrap();
+    let mut fields = HashSet::new();
+    for property in properties {
+      let property_name = string(property, "name");
+      let field = field_name(property_name);
+      if !fields.insert(field.clone()) {
+        panic!("{}.{} has two fields named `{}`", domain, name, field);
+      }
+
+      let mut rust_type = self.rust_type(domain, property);
+      if is_type && self.contains(domain, property, (domain, name)) {
+        rust_type = format!("Box<{}>", rust_type);
+      }
+      let optional = property["optional"].as_bool().unwrap_or(false);
+      if optional {
+        rust_type = format!("Option<{}>", rust_type);
+      }
+
+      let mut serde = vec![];
+      if serde_camel_case(&field) != property_name {
+        serde.push(format!("rename = \"{}\"", property_name));
+      }
+      if optional {
+        serde.push("skip_serializing_if = \"Option::is_none\"".to_string());
+      }
+
+      write_doc(&mut self.out, "    ", property, &enum_notes(property));
+      if !serde.is_empty() {
+        writeln!(self.out, "    #[serde({})]", serde.join(", ")).unwrap();
+      }
+      writeln!(self.out, "    pub {}: {},", field, rust_type).unwrap();
+    }
+    self.out.push_str("  }\n\n");
+  }
+
+  fn rust_type(&self, domain: &str, item: &Value) -> String {
+    if let Some(reference) = item["$ref"].as_str() {
+      let (ref_domain, name) = split_reference(domain, reference);
+      return if ref_domain == domain {
+        name.to_string()
+      } else {
+        format!("super::{}::{}", snake_case(ref_domain), name)
+      };
+    }
+    match item["type"].as_str() {
+      Some("integer") => "i64".to_string(),
+      Some("number") => "f64".to_string(),
+      Some("boolean") => "bool".to_string(),
+      Some("string") | Some("binary") => "String".to_string(),
+      Some("any") => "::serde_json::Value".to_string(),
+      Some("object") if item.get("properties").is_none() => {
+        "::serde_json::Value".to_string()
+      }
+      Some("array") => {
+        format!("Vec<{}>", self.rust_type(domain, &item["items"]))
+      }
+      _ => panic!("unsupported protocol type in {}: {}", domain, item),
+    }
+  }
+
+  /// Returns whether `property` refers to an object type that contains
+  /// `target`, which would make it infinitely large unless boxed. Arrays
+  /// are on the heap already.
+  fn contains(
+    &self,
+    domain: &'a str,
+    property: &'a Value,
+    target: (&str, &str),
+  ) -> bool {
+    let Some(reference) = property["$ref"].as_str() else {
+      return false;
+    };
+    let mut stack = vec![split_reference(domain, reference)];
+    let mut visited = HashSet::new();
+    while let Some(key) = stack.pop() {
+      if key == target {
+        return true;
+      }
+      if !visited.insert(key) {
+        continue;
+      }
+      let Some(ty) = self.types.get(&key) else {
+        continue;
+      };
+      for property in array(ty, "properties") {
+        if let Some(reference) = property["$ref"].as_str() {
+          stack.push(split_reference(key.0, reference));
+        }
+      }
+    }
+    false
+  }
+}
+
+/// Checks that the names of the items of `domain` don't collide once
+/// converted to Rust.
+fn check_names(domain: &Value) {
+  let domain_name = string(domain, "domain");
+  let mut names = MACRO_NAMES
+    .iter()
+    .map(|name| name.to_string())
+    .collect::<HashSet<_>>();
+  let mut insert = |name: String| {
+    if !names.insert(name.clone()) {
+      panic!("{} has two items named `{}`", domain_name, name);
+    }
+  };
+  for ty in array(domain, "types") {
+    insert(string(ty, "id").to_string());
+  }
+  for command in array(domain, "commands") {
+    let name = upper_camel_case(string(command, "name"));
+    if !array(command, "returns").is_empty() {
+      insert(format!("{}Response", name));
+    }
+    insert(name);
+  }
+  for event in array(domain, "events") {
+    if !array(event, "parameters").is_empty() {
+      insert(upper_camel_case(string(event, "name")));
+    }
+  }
+}
+
+/// Writes the description of `item`, followed by `notes` and whether it is
+/// experimental or deprecated.
+fn write_doc(out: &mut String, indent: &str, item: &Value, notes: &[String]) {
+  let mut paragraphs = vec![];
+  if let Some(description) = item["description"].as_str() {
+    paragraphs.extend(description.split("\n\n").map(escape_markdown));
+  }
+  paragraphs.extend(notes.iter().cloned());
+  if item["experimental"].as_bool().unwrap_or(false) {
+    paragraphs.push("Experimental.".to_string());
+  }
+  if item["deprecated"].as_bool().unwrap_or(false) {
+    paragraphs.push("Deprecated.".to_string());
+  }
+  if !paragraphs.is_empty() {
+    writeln!(out, "{}#[doc = {:?}]", indent, paragraphs.join("\n\n")).unwrap();
+  }
+}
+
+/// Lists the values of a string that the protocol restricts to a set.
+fn enum_notes(item: &Value) -> Vec<String> {
+  let values = array(item, "enum");
+  if values.is_empty() {
+    return vec![];
+  }
+  let values = values
+    .iter()
+    .map(|value| format!("`{}`", value))
+    .collect::<Vec<_>>();
+  vec![format!("One of {}.", values.join(", "))]
+}
+
+/// Joins the lines of a protocol description, which are wrapped rather than
+/// formatted as Markdown, and escapes the characters that rustdoc would
+/// read as links or HTML outside of code spans.
+fn escape_markdown(paragraph: &str) -> String {
+  let mut escaped = String::new();
+  let mut in_code = false;
+  for c in paragraph.trim().chars() {
+    match c {
+      '\n' => escaped.push(' '),
+      '`' => {
+        in_code = !in_code;
+        escaped.push(c);
+      }
+      '[' | ']' | '<' | '>' | '*' | '_' if !in_code => {
+        escaped.push('\\');
+        escaped.push(c);
+      }
+      _ => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+fn split_reference<'a>(
+  domain: &'a str,
+  reference: &'a str,
+) -> (&'a str, &'a str) {
+  reference.split_once('.').unwrap_or((domain, reference))
+}
+
+fn field_name(property: &str) -> String {
+  let name = snake_case(property);
+  if KEYWORDS.contains(&name.as_str()) {
+    format!("r#{}", name)
+  } else if RESERVED.contains(&name.as_str()) {
+    format!("{}_", name)
+  } else {
+    name
+  }
+}
+
+/// Converts a protocol name to snake case, e.g. `"sourceMapURL"` to
+/// `"source_map_url"` and `"HeapProfiler"` to `"heap_profiler"`.
+fn snake_case(name: &str) -> String {
+  let chars = name.chars().collect::<Vec<_>>();
+  let mut snake = String::new();
+  for (i, &c) in chars.iter().enumerate() {
+    if c.is_ascii_uppercase() && i > 0 {
+      let previous = chars[i - 1];
+      let next_is_lower = chars
+        .get(i + 1)
+        .is_some_and(|next| next.is_ascii_lowercase());
+      if !previous.is_ascii_uppercase() || next_is_lower {
+        snake.push('_');
+      }
+    }
+    snake.push(c.to_ascii_lowercase());
+  }
+  snake
+}
+
+/// Converts a command or notification name to a type name, e.g.
+/// `"consoleAPICalled"` to `"ConsoleAPICalled"`.
+fn upper_camel_case(name: &str) -> String {
+  let mut chars = name.chars();
+  chars
+    .next()
+    .map(|first| first.to_ascii_uppercase())
+    .into_iter()
+    .chain(chars)
+    .collect()
+}
+
+/// The name that `#[serde(rename_all = "camelCase")]` gives a field.
+fn serde_camel_case(field: &str) -> String {
+  let field = field.strip_prefix("r#").unwrap_or(field);
+  let mut camel = String::new();
+  let mut capitalize = false;
+  for c in field.chars() {
+    if c == '_' {
+      capitalize = true;
+    } else if capitalize {
+      camel.push(c.to_ascii_uppercase());
+      capitalize = false;
+    } else {
+      camel.push(c);
+    }
+  }
+  camel
+}
+
+fn string<'a>(item: &'a Value, key: &str) -> &'a str {
+  item[key]
+    .as_str()
+    .unwrap_or_else(|| panic!("missing `{}` in {}", key, item))
+}
+
+fn array<'a>(item: &'a Value, key: &str) -> &'a [Value] {
+  item[key].as_array().map_or(&[], Vec::as_slice)
+}